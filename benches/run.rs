@@ -0,0 +1,56 @@
+// Times `rlox::run` end to end, from scanning to evaluation, on a few fixed workloads.  Run it with
+// `cargo bench` before and after a change that's meant to make things faster.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+// Each workload, with what it evaluates to, to catch a change that's faster because it's wrong.
+const WORKLOADS: &[(&str, &str, &str)] = &[
+    (
+        "workload",
+        include_str!("workload.lox"),
+        "[2584, 1046622.67552155]",
+    ),
+    ("scopes", include_str!("scopes.lox"), "200030000"),
+];
+
+// Keep running until both of these are reached, to smooth out noise.
+const MIN_RUNS: usize = 10;
+const MIN_TIME: Duration = Duration::from_secs(3);
+
+fn main() {
+    for (name, source, expected) in WORKLOADS {
+        time(name, source, expected);
+    }
+}
+
+fn time(name: &str, source: &str, expected: &str) {
+    match rlox::run(source) {
+        Ok(value) => assert_eq!(
+            value.to_string(),
+            expected,
+            "{} gave the wrong answer",
+            name
+        ),
+        Err(err) => panic!("{} failed: {:?}", name, err),
+    }
+
+    let mut times = Vec::new();
+    let started = Instant::now();
+    while times.len() < MIN_RUNS || started.elapsed() < MIN_TIME {
+        let start = Instant::now();
+        let _ = black_box(rlox::run(black_box(source)));
+        times.push(start.elapsed());
+    }
+
+    times.sort();
+    println!(
+        "{}: median {:?}, fastest {:?}, over {} runs",
+        name,
+        times[times.len() / 2],
+        times[0],
+        times.len()
+    );
+}
@@ -1,18 +1,91 @@
+use std::{cell::Cell, rc::Rc};
+
 use super::HasFileLocation;
-use crate::parser::{BinaryOp, Expr, UnaryOp, Visitor};
+use crate::lexer::Symbol;
+use crate::parser::{BinaryOp, Expr, FunctionDecl, Stmt, StmtVisitor, UnaryOp, Visitor};
 
 pub struct AstPrinter {
     indent_level: usize,
+    indent: String,
+    line_ending: String,
+}
+
+impl Default for AstPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AstPrinter {
     pub fn new() -> Self {
-        Self { indent_level: 0 }
+        Self {
+            indent_level: 0,
+            indent: "\t".to_string(),
+            line_ending: "\r\n".to_string(),
+        }
+    }
+
+    /**
+     * Use `indent` for each level of nesting instead of a tab.
+     */
+    pub fn with_indent(mut self, indent: &str) -> Self {
+        self.indent = indent.to_string();
+        self
+    }
+
+    /**
+     * Use `line_ending` between statements instead of `\r\n`.
+     */
+    pub fn with_line_ending(mut self, line_ending: &str) -> Self {
+        self.line_ending = line_ending.to_string();
+        self
     }
 
     pub fn print(&mut self, expr: &Expr) -> String {
         expr.accept(self)
     }
+
+    pub fn print_program(&mut self, program: &[Stmt]) -> String {
+        self.print_sequence("program", program)
+    }
+
+    /**
+     * The parameter names, with any defaults printed as `(= name value)` and the rest parameter
+     * as `...name`.
+     */
+    fn print_params(&mut self, decl: &FunctionDecl) -> String {
+        let mut params: Vec<String> = decl
+            .params
+            .iter()
+            .zip(&decl.defaults)
+            .map(|(param, default)| match default {
+                Some(default) => format!("(= {} {})", param, default.accept(self)),
+                None => param.to_string(),
+            })
+            .collect();
+        if let Some(rest) = &decl.rest {
+            params.push(format!("...{}", rest));
+        }
+        params.join(" ")
+    }
+
+    /**
+     * Print a list of statements, one per line, indented under `name`.
+     */
+    fn print_sequence(&mut self, name: &str, stmts: &[Stmt]) -> String {
+        let mut s = String::new();
+        s.push_str(&format!("({} {}", name, self.line_ending));
+        self.indent_level += 1;
+        for stmt in stmts {
+            s.push_str(&self.indent.repeat(self.indent_level));
+            s.push_str(&stmt.accept(self));
+            s.push_str(&self.line_ending);
+        }
+        self.indent_level -= 1;
+        s.push_str(&self.indent.repeat(self.indent_level));
+        s.push(')');
+        s
+    }
 }
 
 impl Visitor<String> for AstPrinter {
@@ -42,6 +115,127 @@ impl Visitor<String> for AstPrinter {
         format!("(group {})", e.accept(self))
     }
 
+    fn visit_call(&mut self, _loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) -> String {
+        let mut s = format!("(call {}", callee.accept(self));
+        for arg in args {
+            s.push(' ');
+            s.push_str(&arg.accept(self));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_method_call(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> String {
+        let mut s = format!("(. {} {}", receiver.accept(self), name);
+        for arg in args {
+            s.push_str(&format!(" {}", arg.accept(self)));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_get(&mut self, _loc: &dyn HasFileLocation, object: &Expr, name: &str) -> String {
+        format!("(get {} {})", object.accept(self), name)
+    }
+
+    fn visit_index(&mut self, _loc: &dyn HasFileLocation, object: &Expr, index: &Expr) -> String {
+        format!("(index {} {})", object.accept(self), index.accept(self))
+    }
+
+    fn visit_slice(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> String {
+        // A missing end prints as `_`.
+        let mut bound = |e: &Option<Box<Expr>>| match e {
+            Some(e) => e.accept(self),
+            None => "_".to_string(),
+        };
+        let start = bound(start);
+        let end = bound(end);
+        format!("(slice {} {} {})", object.accept(self), start, end)
+    }
+
+    fn visit_set(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> String {
+        format!(
+            "(set {} {} {})",
+            object.accept(self),
+            name,
+            value.accept(self)
+        )
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) -> String {
+        format!(
+            "(set{}= {} {} {})",
+            op,
+            object.accept(self),
+            name,
+            value.accept(self)
+        )
+    }
+
+    fn visit_list(&mut self, _loc: &dyn HasFileLocation, items: &[Expr]) -> String {
+        let mut s = String::from("(list");
+        for item in items {
+            s.push(' ');
+            s.push_str(&item.accept(self));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_interpolation(&mut self, _loc: &dyn HasFileLocation, parts: &[Expr]) -> String {
+        let mut s = String::from("(interpolate");
+        for part in parts {
+            s.push(' ');
+            s.push_str(&part.accept(self));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_range(&mut self, _loc: &dyn HasFileLocation, start: &Expr, end: &Expr) -> String {
+        format!("(range {} {})", start.accept(self), end.accept(self))
+    }
+
+    fn visit_ternary(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) -> String {
+        format!(
+            "(?: {} {} {})",
+            cond.accept(self),
+            then.accept(self),
+            else_.accept(self)
+        )
+    }
+
     fn visit_unary_op(
         &mut self,
         _loc: &dyn HasFileLocation,
@@ -50,6 +244,7 @@ impl Visitor<String> for AstPrinter {
     ) -> String {
         match op {
             UnaryOp::Neg => format!("(- {})", e.accept(self)),
+            UnaryOp::Pos => format!("(+ {})", e.accept(self)),
             UnaryOp::Not => format!("(! {})", e.accept(self)),
         }
     }
@@ -72,21 +267,69 @@ impl Visitor<String> for AstPrinter {
             BinaryOp::Le => format!("(<= {} {})", e1.accept(self), e2.accept(self)),
             BinaryOp::Gt => format!("(> {} {})", e1.accept(self), e2.accept(self)),
             BinaryOp::Ge => format!("(>= {} {})", e1.accept(self), e2.accept(self)),
+            BinaryOp::In => format!("(in {} {})", e1.accept(self), e2.accept(self)),
             BinaryOp::LogicalAnd => format!("(&& {} {})", e1.accept(self), e2.accept(self)),
             BinaryOp::LogicalOr => format!("(|| {} {})", e1.accept(self), e2.accept(self)),
         }
     }
 
-    fn visit_print(&mut self, _loc: &dyn HasFileLocation, expr: &Box<Expr>) -> String {
+    fn visit_assign(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        format!("(= {} {})", name, expr.accept(self))
+    }
+
+    fn visit_variable(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        format!("(var {})", name)
+    }
+
+    fn visit_super(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        method: &str,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        format!("(super {})", method)
+    }
+
+    fn visit_lambda(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> String {
+        let header = format!("fun ({})", self.print_params(decl));
+        self.print_sequence(&header, &decl.body)
+    }
+
+    fn visit_loop_expr(&mut self, _loc: &dyn HasFileLocation, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    fn visit_this(&mut self, _loc: &dyn HasFileLocation, _depth: &Cell<Option<usize>>) -> String {
+        "this".to_string()
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_expr_stmt(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    fn visit_print(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) -> String {
         format!("(print {})", expr.accept(self))
     }
 
     fn visit_if(
         &mut self,
         _loc: &dyn HasFileLocation,
-        cond: &Box<Expr>,
-        then: &Box<Expr>,
-        else_: &Option<Box<Expr>>,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
     ) -> String {
         match else_ {
             Some(else_) => format!(
@@ -99,86 +342,167 @@ impl Visitor<String> for AstPrinter {
         }
     }
 
-    fn visit_let(&mut self, _loc: &dyn HasFileLocation, name: &String) -> String {
-        format!("(let {})", name)
+    fn visit_let(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        init: &Option<Box<Expr>>,
+    ) -> String {
+        match init {
+            Some(init) => format!("(let {} {})", name, init.accept(self)),
+            None => format!("(let {})", name),
+        }
+    }
+
+    fn visit_delete(&mut self, _loc: &dyn HasFileLocation, name: &Symbol) -> String {
+        format!("(delete {})", name)
     }
 
-    fn visit_let_init(
+    fn visit_block(&mut self, _loc: &dyn HasFileLocation, stmts: &[Stmt]) -> String {
+        self.print_sequence("block", stmts)
+    }
+
+    fn visit_while(
         &mut self,
-        // And,
-        // Or,
         _loc: &dyn HasFileLocation,
-        name: &String,
-        expr: &Box<Expr>,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
     ) -> String {
-        format!("(let {} {})", name, expr.accept(self))
+        match increment {
+            Some(increment) => format!(
+                "({} {} {} {})",
+                labeled("while", label),
+                cond.accept(self),
+                body.accept(self),
+                increment.accept(self)
+            ),
+            None => format!(
+                "({} {} {})",
+                labeled("while", label),
+                cond.accept(self),
+                body.accept(self)
+            ),
+        }
     }
 
-    fn visit_assign(
+    fn visit_loop(
         &mut self,
         _loc: &dyn HasFileLocation,
-        name: &String,
-        expr: &Box<Expr>,
+        label: &Option<Symbol>,
+        body: &Stmt,
     ) -> String {
-        format!("(= {} {})", name, expr.accept(self))
+        format!("({} {})", labeled("loop", label), body.accept(self))
     }
 
-    fn visit_variable(&mut self, _loc: &dyn HasFileLocation, name: &String) -> String {
-        format!("(var {})", name)
+    fn visit_for_in(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> String {
+        format!(
+            "({} {} {} {})",
+            labeled("for-in", label),
+            name,
+            iterable.accept(self),
+            body.accept(self)
+        )
     }
 
-    fn visit_program(&mut self, _loc: &dyn HasFileLocation, exprs: &Vec<Expr>) -> String {
-        let mut s = String::new();
-        s.push_str("(program \r\n");
-        self.indent_level += 1;
-        for expr in exprs {
-            for _ in 0..self.indent_level {
-                s.push_str("\t");
-            }
-            s.push_str(&expr.accept(self));
-            s.push_str("\r\n");
+    fn visit_class(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) -> String {
+        let mut s = format!("(class {}", name);
+        if let Some(superclass) = superclass {
+            s.push_str(&format!(" (< {})", superclass.accept(self)));
         }
-        self.indent_level -= 1;
-        for _ in 0..self.indent_level {
-            s.push_str("\t");
+        for method in methods {
+            let header = format!("fun {} ({})", method.name, self.print_params(method));
+            s.push(' ');
+            s.push_str(&self.print_sequence(&header, &method.body));
         }
-        s.push_str(")");
+        s.push(')');
         s
     }
 
-    fn visit_block(&mut self, _loc: &dyn HasFileLocation, exprs: &Vec<Expr>) -> String {
-        let mut s = String::new();
-        s.push_str("(block \r\n");
-        self.indent_level += 1;
-        for expr in exprs {
-            for _ in 0..self.indent_level {
-                s.push_str("\t");
-            }
-            s.push_str(&expr.accept(self));
-            s.push_str("\r\n");
-        }
-        self.indent_level -= 1;
-        for _ in 0..self.indent_level {
-            s.push_str("\t");
-        }
-        s.push_str(")");
-        s
+    fn visit_function_stmt(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        decl: &Rc<FunctionDecl>,
+    ) -> String {
+        let header = format!("fun {} ({})", decl.name, self.print_params(decl));
+        self.print_sequence(&header, &decl.body)
     }
 
-    fn visit_while(
+    fn visit_break(
         &mut self,
         _loc: &dyn HasFileLocation,
-        cond: &Box<Expr>,
-        body: &Box<Expr>,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
     ) -> String {
-        format!("(while {} {})", cond.accept(self), body.accept(self))
+        match value {
+            Some(value) => format!("({} {})", labeled("break", label), value.accept(self)),
+            None => format!("({})", labeled("break", label)),
+        }
     }
 
-    fn visit_break(&mut self, _loc: &dyn HasFileLocation) -> String {
-        "(break)".to_string()
+    fn visit_continue(&mut self, _loc: &dyn HasFileLocation, label: &Option<Symbol>) -> String {
+        format!("({})", labeled("continue", label))
+    }
+}
+
+/**
+ * A loop or jump keyword followed by its label, if it has one, e.g. `while 'outer`.
+ */
+pub(super) fn labeled(keyword: &str, label: &Option<Symbol>) -> String {
+    match label {
+        Some(label) => format!("{} '{}", keyword, label),
+        None => keyword.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, scan_tokens};
+
+    #[test]
+    fn indentation_and_line_endings_can_be_configured() {
+        let program = parse(&scan_tokens("let a = 1;\n{ print a; }").unwrap()).unwrap();
+
+        let printed = AstPrinter::new().print_program(&program);
+        assert_eq!(
+            printed,
+            "(program \r\n\t(let a 1)\r\n\t(block \r\n\t\t(print (var a))\r\n\t)\r\n)"
+        );
+
+        let printed = AstPrinter::new()
+            .with_indent("  ")
+            .with_line_ending("\n")
+            .print_program(&program);
+        assert_eq!(
+            printed,
+            "(program \n  (let a 1)\n  (block \n    (print (var a))\n  )\n)"
+        );
     }
 
-    fn visit_continue(&mut self, _loc: &dyn HasFileLocation) -> String {
-        "(continue)".to_string()
+    #[test]
+    fn ternaries_print_with_all_three_parts() {
+        let program = parse(&scan_tokens("a ? b : c ? d : e;").unwrap()).unwrap();
+        let Stmt::Expr(_, expr) = &program[0] else {
+            panic!("expected an expression statement");
+        };
+        assert_eq!(
+            AstPrinter::new().print(expr),
+            "(?: (var a) (var b) (?: (var c) (var d) (var e)))"
+        );
     }
 }
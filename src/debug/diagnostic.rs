@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+};
+
+use crate::{
+    interpreter::{json_string, RuntimeError},
+    lexer::LexerError,
+    parser::ParserError,
+};
+
+use super::{ErrorSet, FileLocation, HasFileLocation};
+
+/**
+ * The part of the pipeline that found a problem.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    Lexer,
+    Parser,
+    Runtime,
+    Lint,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Lexer => write!(f, "lexer"),
+            Stage::Parser => write!(f, "parser"),
+            Stage::Runtime => write!(f, "runtime"),
+            Stage::Lint => write!(f, "lint"),
+        }
+    }
+}
+
+/**
+ * A problem found while scanning, parsing, or running a program, with where it happened.
+ *
+ * Each stage has its own error type; this is the one shape they all convert to, so callers can
+ * handle them the same way.
+ */
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub stage: Stage,
+    pub msg: String,
+    pub loc: FileLocation,
+}
+
+impl Diagnostic {
+    pub fn new(stage: Stage, msg: &str, loc: &dyn HasFileLocation) -> Self {
+        Self {
+            stage,
+            msg: msg.to_string(),
+            loc: FileLocation::from_loc(loc),
+        }
+    }
+
+    /**
+     * One diagnostic for each error in `errors`, all from the same stage.
+     */
+    pub fn from_set(stage: Stage, errors: &ErrorSet) -> Vec<Self> {
+        errors
+            .iter()
+            .map(|err| Self::new(stage, err.to_string().as_str(), err))
+            .collect()
+    }
+
+    /**
+     * Render as a JSON object with `stage`, `message`, `line`, and `column` fields, for editors and
+     * other tools to read.
+     */
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"stage\":\"{}\",\"message\":{},\"line\":{},\"column\":{}}}",
+            self.stage,
+            json_string(&self.msg),
+            self.get_line(),
+            self.get_column()
+        )
+    }
+}
+
+impl From<LexerError> for Diagnostic {
+    fn from(err: LexerError) -> Self {
+        Self::new(Stage::Lexer, err.msg.as_str(), &err)
+    }
+}
+
+impl From<ParserError> for Diagnostic {
+    fn from(err: ParserError) -> Self {
+        Self::new(Stage::Parser, err.msg.as_str(), &err)
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(err: RuntimeError) -> Self {
+        Self::new(Stage::Runtime, err.msg.as_str(), &err)
+    }
+}
+
+impl HasFileLocation for Diagnostic {
+    fn get_line(&self) -> usize {
+        self.loc.get_line()
+    }
+
+    fn get_column(&self) -> usize {
+        self.loc.get_column()
+    }
+
+    fn get_end_column(&self) -> usize {
+        self.loc.get_end_column()
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.loc.get_end_line()
+    }
+}
+
+impl Error for Diagnostic {}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl Debug for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} error: {} at line {} column {}",
+            self.stage,
+            self.msg,
+            self.get_line(),
+            self.get_column()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::{Interpreter, OutputBuffer};
+
+    fn first_diagnostic(source: &str) -> Diagnostic {
+        let mut interpreter = Interpreter::new().with_output(Box::new(OutputBuffer::new()));
+        interpreter.eval_source(source).unwrap_err().remove(0)
+    }
+
+    #[test]
+    fn each_stage_keeps_its_location() {
+        for (source, stage, line, column) in [
+            ("let a = 1;\nlet b = @;", Stage::Lexer, 2, 9),
+            ("let a = 1;\nprint (1;", Stage::Parser, 2, 9),
+            ("let a = 1;\nprint a + nil;", Stage::Runtime, 2, 9),
+        ] {
+            let diagnostic = first_diagnostic(source);
+            assert_eq!(
+                (
+                    diagnostic.stage,
+                    diagnostic.get_line(),
+                    diagnostic.get_column()
+                ),
+                (stage, line, column),
+                "{:?}",
+                diagnostic
+            );
+        }
+    }
+}
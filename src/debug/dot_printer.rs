@@ -0,0 +1,479 @@
+use std::{cell::Cell, rc::Rc};
+
+use super::{ast_printer::labeled, HasFileLocation};
+use crate::lexer::Symbol;
+use crate::parser::{BinaryOp, Expr, FunctionDecl, Stmt, StmtVisitor, UnaryOp, Visitor};
+
+/**
+ * Render a syntax tree as a Graphviz DOT graph.
+ *
+ * Every node gets its own graph node, with edges to its children labelled by the role each child
+ * plays (`cond`, `then`, `else`, and so on).  The visit methods return the id of the node they
+ * added, so the parent can link to it.
+ */
+pub struct DotPrinter {
+    next_id: usize,
+    lines: Vec<String>,
+}
+
+impl Default for DotPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DotPrinter {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn print(&mut self, expr: &Expr) -> String {
+        expr.accept(self);
+        self.finish()
+    }
+
+    pub fn print_program(&mut self, program: &Vec<Stmt>) -> String {
+        let id = self.node("program");
+        for stmt in program {
+            let child = stmt.accept(self);
+            self.edge(&id, &child, "");
+        }
+        self.finish()
+    }
+
+    fn finish(&mut self) -> String {
+        let mut s = String::from("digraph ast {\r\n");
+        for line in self.lines.drain(..) {
+            s.push('\t');
+            s.push_str(&line);
+            s.push_str("\r\n");
+        }
+        s.push('}');
+        self.next_id = 0;
+        s
+    }
+
+    fn node(&mut self, label: &str) -> String {
+        let id = format!("n{}", self.next_id);
+        self.next_id += 1;
+        let label = label.replace("\\", "\\\\").replace("\"", "\\\"");
+        self.lines.push(format!("{} [label=\"{}\"];", id, label));
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: &str) {
+        if label.is_empty() {
+            self.lines.push(format!("{} -> {};", from, to));
+        } else {
+            self.lines
+                .push(format!("{} -> {} [label=\"{}\"];", from, to, label));
+        }
+    }
+
+    /**
+     * Add a node for an expression, visit its children, and link them in.
+     */
+    fn expr_node(&mut self, label: &str, children: Vec<(&str, &Expr)>) -> String {
+        let id = self.node(label);
+        for (role, child) in children {
+            let child = child.accept(self);
+            self.edge(&id, &child, role);
+        }
+        id
+    }
+
+    fn stmt_node(
+        &mut self,
+        label: &str,
+        exprs: Vec<(&str, &Expr)>,
+        stmts: Vec<(&str, &Stmt)>,
+    ) -> String {
+        let id = self.expr_node(label, exprs);
+        for (role, child) in stmts {
+            let child = child.accept(self);
+            self.edge(&id, &child, role);
+        }
+        id
+    }
+
+    /**
+     * A function with its parameters in the label, and an edge to each default named after its
+     * parameter.
+     */
+    fn function_node(&mut self, name: &str, decl: &FunctionDecl) -> String {
+        let mut params: Vec<String> = decl.params.iter().map(|p| p.to_string()).collect();
+        if let Some(rest) = &decl.rest {
+            params.push(format!("...{}", rest));
+        }
+        let label = format!("{}({})", name, params.join(", "));
+        let defaults: Vec<(String, &Expr)> = decl
+            .params
+            .iter()
+            .zip(&decl.defaults)
+            .filter_map(|(param, default)| default.as_ref().map(|d| (param.to_string(), d)))
+            .collect();
+        let defaults = defaults
+            .iter()
+            .map(|(param, default)| (param.as_str(), *default))
+            .collect();
+        let body = decl.body.iter().map(|stmt| ("", stmt)).collect();
+        self.stmt_node(&label, defaults, body)
+    }
+}
+
+impl Visitor<String> for DotPrinter {
+    fn visit_number(&mut self, _loc: &dyn HasFileLocation, n: &f64) -> String {
+        self.node(&n.to_string())
+    }
+
+    fn visit_string(&mut self, _loc: &dyn HasFileLocation, s: &String) -> String {
+        let s = s
+            .replace("\r", "\\r")
+            .replace("\n", "\\n")
+            .replace("\t", "\\t");
+        self.node(&format!("\"{}\"", s))
+    }
+
+    fn visit_boolean(&mut self, _loc: &dyn HasFileLocation, b: &bool) -> String {
+        self.node(&b.to_string())
+    }
+
+    fn visit_nil(&mut self, _loc: &dyn HasFileLocation) -> String {
+        self.node("nil")
+    }
+
+    fn visit_grouping(&mut self, _loc: &dyn HasFileLocation, e: &Box<Expr>) -> String {
+        self.expr_node("group", vec![("", e)])
+    }
+
+    fn visit_call(&mut self, _loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) -> String {
+        let mut children = vec![("callee", callee)];
+        children.extend(args.iter().map(|arg| ("arg", arg)));
+        self.expr_node("call", children)
+    }
+
+    fn visit_method_call(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> String {
+        let mut children = vec![("receiver", receiver)];
+        children.extend(args.iter().map(|arg| ("arg", arg)));
+        self.expr_node(&format!(".{}", name), children)
+    }
+
+    fn visit_get(&mut self, _loc: &dyn HasFileLocation, object: &Expr, name: &str) -> String {
+        self.expr_node(&format!("get {}", name), vec![("object", object)])
+    }
+
+    fn visit_index(&mut self, _loc: &dyn HasFileLocation, object: &Expr, index: &Expr) -> String {
+        self.expr_node("index", vec![("object", object), ("index", index)])
+    }
+
+    fn visit_slice(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> String {
+        let mut children: Vec<(&str, &Expr)> = vec![("object", object)];
+        if let Some(start) = start {
+            children.push(("start", start));
+        }
+        if let Some(end) = end {
+            children.push(("end", end));
+        }
+        self.expr_node("slice", children)
+    }
+
+    fn visit_set(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> String {
+        self.expr_node(
+            &format!("set {}", name),
+            vec![("object", object), ("value", value)],
+        )
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) -> String {
+        self.expr_node(
+            &format!("set {} {}=", name, op),
+            vec![("object", object), ("value", value)],
+        )
+    }
+
+    fn visit_list(&mut self, _loc: &dyn HasFileLocation, items: &[Expr]) -> String {
+        self.expr_node("list", items.iter().map(|item| ("item", item)).collect())
+    }
+
+    fn visit_interpolation(&mut self, _loc: &dyn HasFileLocation, parts: &[Expr]) -> String {
+        self.expr_node(
+            "interpolate",
+            parts.iter().map(|part| ("part", part)).collect(),
+        )
+    }
+
+    fn visit_range(&mut self, _loc: &dyn HasFileLocation, start: &Expr, end: &Expr) -> String {
+        self.expr_node("range", vec![("start", start), ("end", end)])
+    }
+
+    fn visit_ternary(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) -> String {
+        self.expr_node("?:", vec![("cond", cond), ("then", then), ("else", else_)])
+    }
+
+    fn visit_unary_op(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        op: &UnaryOp,
+        e: &Box<Expr>,
+    ) -> String {
+        self.expr_node(&op.to_string(), vec![("", e)])
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        op: &BinaryOp,
+        e1: &Box<Expr>,
+        e2: &Box<Expr>,
+    ) -> String {
+        self.expr_node(&op.to_string(), vec![("left", e1), ("right", e2)])
+    }
+
+    fn visit_assign(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        self.expr_node(&format!("= {}", name), vec![("value", expr)])
+    }
+
+    fn visit_variable(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        self.node(&format!("var {}", name))
+    }
+
+    fn visit_super(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        method: &str,
+        _depth: &Cell<Option<usize>>,
+    ) -> String {
+        self.node(&format!("super.{}", method))
+    }
+
+    fn visit_lambda(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> String {
+        self.function_node("fun ", decl)
+    }
+
+    fn visit_loop_expr(&mut self, _loc: &dyn HasFileLocation, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    fn visit_this(&mut self, _loc: &dyn HasFileLocation, _depth: &Cell<Option<usize>>) -> String {
+        self.node("this")
+    }
+}
+
+impl StmtVisitor<String> for DotPrinter {
+    fn visit_expr_stmt(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) -> String {
+        expr.accept(self)
+    }
+
+    fn visit_print(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) -> String {
+        self.expr_node("print", vec![("", expr)])
+    }
+
+    fn visit_let(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        init: &Option<Box<Expr>>,
+    ) -> String {
+        let label = format!("let {}", name);
+        match init {
+            Some(init) => self.expr_node(&label, vec![("init", init)]),
+            None => self.node(&label),
+        }
+    }
+
+    fn visit_delete(&mut self, _loc: &dyn HasFileLocation, name: &Symbol) -> String {
+        self.node(&format!("delete {}", name))
+    }
+
+    fn visit_block(&mut self, _loc: &dyn HasFileLocation, stmts: &[Stmt]) -> String {
+        self.stmt_node(
+            "block",
+            vec![],
+            stmts.iter().map(|stmt| ("", stmt)).collect(),
+        )
+    }
+
+    fn visit_if(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) -> String {
+        let mut stmts = vec![("then", then)];
+        if let Some(else_) = else_ {
+            stmts.push(("else", else_.as_ref()));
+        }
+        self.stmt_node("if", vec![("cond", cond)], stmts)
+    }
+
+    fn visit_while(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> String {
+        let mut exprs = vec![("cond", cond)];
+        if let Some(increment) = increment {
+            exprs.push(("increment", increment.as_ref()));
+        }
+        self.stmt_node(&labeled("while", label), exprs, vec![("body", body)])
+    }
+
+    fn visit_loop(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        body: &Stmt,
+    ) -> String {
+        self.stmt_node(&labeled("loop", label), vec![], vec![("body", body)])
+    }
+
+    fn visit_for_in(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> String {
+        self.stmt_node(
+            &format!("{} {} in", labeled("for", label), name),
+            vec![("iterable", iterable)],
+            vec![("body", body)],
+        )
+    }
+
+    fn visit_class(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) -> String {
+        let id = self.node(&format!("class {}", name));
+        if let Some(superclass) = superclass {
+            let child = superclass.accept(self);
+            self.edge(&id, &child, "superclass");
+        }
+        for method in methods {
+            let child = self.function_node(&format!("fun {}", method.name), method);
+            self.edge(&id, &child, "method");
+        }
+        id
+    }
+
+    fn visit_function_stmt(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        decl: &Rc<FunctionDecl>,
+    ) -> String {
+        self.function_node(&format!("fun {}", decl.name), decl)
+    }
+
+    fn visit_break(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) -> String {
+        match value {
+            Some(value) => self.expr_node(&labeled("break", label), vec![("value", value)]),
+            None => self.node(&labeled("break", label)),
+        }
+    }
+
+    fn visit_continue(&mut self, _loc: &dyn HasFileLocation, label: &Option<Symbol>) -> String {
+        self.node(&labeled("continue", label))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, scan_tokens};
+
+    #[test]
+    fn every_node_is_linked_to_its_parent() {
+        let source = "if (1 < 2) { print \"a\"; } else { print \"b\"; }";
+        let program = parse(&scan_tokens(source).unwrap()).unwrap();
+        let dot = DotPrinter::new().print_program(&program);
+        assert!(dot.starts_with("digraph ast {\r\n"), "{}", dot);
+        assert!(dot.ends_with('}'), "{}", dot);
+
+        let (edges, nodes): (Vec<&str>, Vec<&str>) = dot
+            .lines()
+            .filter(|line| line.starts_with('\t'))
+            .partition(|line| line.contains("->"));
+        // program, if, group, <, 1, 2, and a block, print, and string for each branch.
+        assert_eq!(nodes.len(), 12);
+        // It's a tree, so everything but the root has one edge in.
+        assert_eq!(edges.len(), 11);
+        for role in ["cond", "then", "else", "left", "right"] {
+            let label = format!("[label=\"{}\"];", role);
+            assert_eq!(
+                edges.iter().filter(|edge| edge.ends_with(&label)).count(),
+                1,
+                "{}",
+                role
+            );
+        }
+    }
+
+    #[test]
+    fn ids_restart_for_each_graph() {
+        let program = parse(&scan_tokens("1;").unwrap()).unwrap();
+        let mut printer = DotPrinter::new();
+        assert_eq!(
+            printer.print_program(&program),
+            printer.print_program(&program)
+        );
+    }
+}
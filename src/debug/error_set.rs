@@ -1,4 +1,9 @@
-use std::{error::Error, fmt::Debug, fmt::Display};
+use std::{
+    error::Error,
+    fmt::Debug,
+    fmt::Display,
+    io::{self, Write},
+};
 
 use super::LocatableError;
 
@@ -6,6 +11,12 @@ pub struct ErrorSet {
     errors: Vec<Box<dyn LocatableError>>,
 }
 
+impl Default for ErrorSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ErrorSet {
     pub fn new() -> Self {
         ErrorSet { errors: Vec::new() }
@@ -15,6 +26,10 @@ impl ErrorSet {
         self.errors.is_empty()
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &dyn LocatableError> {
+        self.errors.iter().map(|err| err.as_ref())
+    }
+
     pub fn push<E>(&mut self, err: E)
     where
         E: 'static + LocatableError,
@@ -29,6 +44,25 @@ impl ErrorSet {
 
         eprint!("\r\n{}\r\n", self);
     }
+
+    /**
+     * Every error's report, without colors, followed by the count.
+     */
+    pub fn format_report(&self, input: &str) -> String {
+        let mut report = String::new();
+        for err in &self.errors {
+            report.push_str(&err.format_report(input));
+        }
+        report.push_str(&format!("\r\n{}\r\n", self));
+        report
+    }
+
+    /**
+     * Write the reports to `out` instead of stderr.
+     */
+    pub fn report_to(&self, input: &str, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "{}", self.format_report(input))
+    }
 }
 
 impl Error for ErrorSet {
@@ -1,18 +1,42 @@
 use super::HasFileLocation;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileLocation {
     line: usize,
     column: usize,
+    end_line: usize,
+    end_column: usize,
 }
 
 impl FileLocation {
     pub fn new(line: usize, column: usize) -> Self {
-        Self { line, column }
+        Self::span(line, column, column)
+    }
+
+    pub fn span(line: usize, column: usize, end_column: usize) -> Self {
+        Self::range(line, column, line, end_column)
+    }
+
+    /**
+     * A location that may run across several lines.
+     */
+    pub fn range(line: usize, column: usize, end_line: usize, end_column: usize) -> Self {
+        Self {
+            line,
+            column,
+            end_line,
+            end_column,
+        }
     }
 
     pub fn from_loc(other: &dyn HasFileLocation) -> Self {
-        Self::new(other.get_line(), other.get_column())
+        Self::range(
+            other.get_line(),
+            other.get_column(),
+            other.get_end_line(),
+            other.get_end_column(),
+        )
     }
 }
 
@@ -24,4 +48,12 @@ impl HasFileLocation for FileLocation {
     fn get_column(&self) -> usize {
         self.column
     }
+
+    fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.end_line
+    }
 }
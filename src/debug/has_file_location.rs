@@ -1,4 +1,18 @@
 pub trait HasFileLocation {
     fn get_line(&self) -> usize;
     fn get_column(&self) -> usize;
+
+    /**
+     * The column of the last character covered by this location.
+     */
+    fn get_end_column(&self) -> usize {
+        self.get_column()
+    }
+
+    /**
+     * The line holding the last character covered by this location.
+     */
+    fn get_end_line(&self) -> usize {
+        self.get_line()
+    }
 }
@@ -1,10 +1,89 @@
-use std::error::Error;
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    io::{self, Write},
+};
 
 use super::HasFileLocation;
 
+/**
+ * The ANSI escape sequences used to highlight error reports.
+ */
+struct Palette {
+    error: &'static str,
+    warning: &'static str,
+    source: &'static str,
+    caret: &'static str,
+    reset: &'static str,
+}
+
+impl Palette {
+    /**
+     * Colors are only used when stderr is a terminal and `NO_COLOR` isn't set.
+     */
+    fn for_stderr() -> Self {
+        Self::choose(env::var_os("NO_COLOR"), atty::is(atty::Stream::Stderr))
+    }
+
+    /**
+     * An empty `NO_COLOR` counts as unset, as the convention asks.
+     */
+    fn choose(no_color: Option<OsString>, terminal: bool) -> Self {
+        let no_color = no_color.is_some_and(|value| !value.is_empty());
+        if no_color || !terminal {
+            Self::plain()
+        } else {
+            Self::colored()
+        }
+    }
+
+    fn plain() -> Self {
+        Self {
+            error: "",
+            warning: "",
+            source: "",
+            caret: "",
+            reset: "",
+        }
+    }
+
+    fn colored() -> Self {
+        Self {
+            error: "\x1b[1;31m",
+            warning: "\x1b[1;33m",
+            source: "\x1b[1m",
+            caret: "\x1b[32m",
+            reset: "\x1b[0m",
+        }
+    }
+}
+
 pub trait LocatableError: Error + HasFileLocation + Send + Sync {
     fn as_error(&self) -> &(dyn Error + 'static);
+
+    /**
+     * Print the error to stderr, with the line of `source` it came from underlined.
+     */
     fn report(&self, source: &str);
+
+    /**
+     * The same report `report` prints, without colors, for callers that want to show it
+     * themselves.
+     */
+    fn format_report(&self, source: &str) -> String;
+
+    /**
+     * Like `report`, but headed as a warning, for problems that don't stop the program.
+     */
+    fn warn(&self, source: &str);
+
+    /**
+     * Write the report to `out` instead of stderr.
+     */
+    fn report_to(&self, source: &str, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "{}", self.format_report(source))
+    }
 }
 
 impl<T> LocatableError for T
@@ -16,21 +95,163 @@ where
     }
 
     fn report(&self, source: &str) {
-        eprint!("\r\nerror: {}\r\n", self);
-
-        // Take the 3rd line out the input text.
-        let lines: Vec<&str> = source.split('\n').collect();
-        let line = lines[self.get_line() - 1];
+        let palette = Palette::for_stderr();
+        eprint!("{}", render(self, source, "error", palette.error, &palette));
+    }
 
-        // Convert line to a string and get the length of it.
-        let len = self.get_line().to_string().len();
+    fn format_report(&self, source: &str) -> String {
+        let palette = Palette::plain();
+        render(self, source, "error", palette.error, &palette)
+    }
 
-        eprint!("\r\n");
-        eprint!("{} | {}\r\n", self.get_line(), line);
+    fn warn(&self, source: &str) {
+        let palette = Palette::for_stderr();
         eprint!(
-            "{:>width$}-- Here.\r\n",
-            "^",
-            width = self.get_column() + len + 3
+            "{}",
+            render(self, source, "warning", palette.warning, &palette)
         );
     }
 }
+
+/**
+ * Lay out the message under a `heading` like "error", the source line it came from, and carets
+ * under the columns it covers.
+ */
+fn render<T>(err: &T, source: &str, heading: &str, color: &str, palette: &Palette) -> String
+where
+    T: Error + HasFileLocation,
+{
+    let mut report = format!("\r\n{}{}:{} {}\r\n", color, heading, palette.reset, err);
+
+    // Take the 3rd line out the input text.
+    let lines: Vec<&str> = source.split('\n').collect();
+    let line = match err
+        .get_line()
+        .checked_sub(1)
+        .and_then(|index| lines.get(index))
+    {
+        Some(line) => line,
+        None if err.get_line() == 0 => {
+            report.push_str("\r\n(unknown location)\r\n");
+            return report;
+        }
+        None => {
+            report.push_str(&format!(
+                "\r\n(line {} is past the end of the input)\r\n",
+                err.get_line()
+            ));
+            return report;
+        }
+    };
+
+    // Convert line to a string and get the length of it.
+    let len = err.get_line().to_string().len();
+
+    report.push_str("\r\n");
+    report.push_str(&format!(
+        "{} | {}{}{}\r\n",
+        err.get_line(),
+        palette.source,
+        line,
+        palette.reset
+    ));
+    // Underline every column the error covers.  A span that runs onto later lines is
+    // underlined to the end of its first line.
+    let end_column = if err.get_end_line() > err.get_line() {
+        line.chars().count()
+    } else {
+        err.get_end_column()
+    };
+    let end_column = end_column.max(err.get_column());
+    let carets = "^".repeat(end_column - err.get_column() + 1);
+    let continues = if err.get_end_line() > err.get_line() {
+        format!(" (through line {})", err.get_end_line())
+    } else {
+        String::new()
+    };
+    report.push_str(&format!(
+        "{}{:>width$}-- Here.{}{}\r\n",
+        palette.caret,
+        carets,
+        continues,
+        palette.reset,
+        width = end_column + len + 3
+    ));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carets_cover_the_whole_token() {
+        let source = "print undefined_name;";
+        let err = crate::run(source).unwrap_err();
+        let report = err
+            .downcast_ref::<crate::RuntimeError>()
+            .unwrap()
+            .format_report(source);
+        let carets = report.lines().last().unwrap().trim_start();
+        assert_eq!(
+            carets,
+            format!("{}-- Here.", "^".repeat("undefined_name".len()))
+        );
+    }
+
+    #[test]
+    fn no_color_turns_the_escape_sequences_off() {
+        let source = "print undefined_name;";
+        let err = crate::run(source).unwrap_err();
+        let err = err.downcast_ref::<crate::RuntimeError>().unwrap();
+        let render_with = |palette: Palette| render(err, source, "error", palette.error, &palette);
+
+        let colored = render_with(Palette::choose(None, true));
+        assert!(colored.contains('\x1b'), "{:?}", colored);
+        let colored = render_with(Palette::choose(Some(OsString::new()), true));
+        assert!(colored.contains('\x1b'), "{:?}", colored);
+
+        let plain = render_with(Palette::choose(Some(OsString::from("1")), true));
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+        let plain = render_with(Palette::choose(None, false));
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+    }
+
+    #[test]
+    fn lines_outside_the_source_are_reported_without_it() {
+        let source = "print 1;\nprint 2;";
+
+        let report = crate::RuntimeError::new("nowhere", 0, 0).format_report(source);
+        assert!(report.contains("(unknown location)"), "{}", report);
+
+        let report = crate::RuntimeError::new("too far", 7, 3).format_report(source);
+        assert!(
+            report.contains("(line 7 is past the end of the input)"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn runtime_errors_can_be_reported_to_a_buffer() {
+        let source = "let a = 1;\nprint a + nil;";
+        let program = crate::parse(&crate::scan_tokens(source).unwrap()).unwrap();
+        let err = crate::Interpreter::new().execute(&program).unwrap_err();
+
+        let mut out: Vec<u8> = Vec::new();
+        err.report_to(source, &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(report.contains(&err.msg), "{}", report);
+        assert!(report.contains("2 | print a + nil;"), "{}", report);
+        assert!(report.contains("^-- Here."), "{}", report);
+    }
+
+    #[test]
+    fn spans_over_several_lines_are_underlined_to_the_end_of_the_first() {
+        let source = "let name = \"ab\ncd\";";
+        let tokens = crate::scan_tokens(source).unwrap();
+        let report = crate::RuntimeError::at("bad string", &tokens[3]).format_report(source);
+        let carets = report.lines().last().unwrap();
+        assert_eq!(carets, "               ^^^-- Here. (through line 2)");
+    }
+}
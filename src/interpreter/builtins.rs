@@ -0,0 +1,936 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, BufRead},
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, is_raw_mode_enabled};
+
+use crate::{
+    app_info::AppInfo,
+    debug::{FileLocation, HasFileLocation},
+    lexer::Symbol,
+};
+
+use super::{
+    Class, EnvironmentStack, HasStopFlag, Instance, Interpreter, NativeFunction, Object,
+    RuntimeError,
+};
+
+/**
+ * Define every native function in the global scope.
+ */
+pub fn define_builtins(environments: &mut EnvironmentStack) -> Result<(), RuntimeError> {
+    let natives = [
+        NativeFunction::new("len", 1, len),
+        NativeFunction::new("upper", 1, upper),
+        NativeFunction::new("lower", 1, lower),
+        NativeFunction::new("substring", 3, substring),
+        NativeFunction::new("join", 2, join),
+        NativeFunction::new("split", 2, split),
+        NativeFunction::new("input", 1, input),
+        NativeFunction::new("type", 1, type_of),
+        NativeFunction::new("number", 1, number),
+        NativeFunction::new("string", 1, string),
+        NativeFunction::with_arity("format", 1, None, format),
+        NativeFunction::with_arity("assert", 1, Some(2), assert),
+        NativeFunction::new("map", 2, map),
+        NativeFunction::new("filter", 2, filter),
+        NativeFunction::new("reduce", 3, reduce),
+        NativeFunction::with_arity("exit", 0, Some(1), exit),
+        NativeFunction::new("env", 1, env),
+        NativeFunction::new("read_file", 1, read_file),
+        NativeFunction::new("write_file", 2, write_file),
+        NativeFunction::new("dump_env", 0, dump_env),
+        NativeFunction::new("sqrt", 1, sqrt),
+        NativeFunction::new("abs", 1, abs),
+        NativeFunction::new("floor", 1, floor),
+        NativeFunction::new("ceil", 1, ceil),
+        NativeFunction::new("round", 1, round),
+        NativeFunction::with_arity("min", 1, None, min),
+        NativeFunction::with_arity("max", 1, None, max),
+        NativeFunction::new("pow", 2, pow),
+        NativeFunction::new("random", 0, random),
+        NativeFunction::new("randint", 2, randint),
+        NativeFunction::new("seed", 1, seed),
+        NativeFunction::new("clock", 0, clock),
+        NativeFunction::new("sleep", 1, sleep),
+        NativeFunction::new("version", 0, version),
+        NativeFunction::new("version_info", 0, version_info),
+        NativeFunction::new("to_utf8", 1, to_utf8),
+        NativeFunction::new("from_utf8", 1, from_utf8),
+        NativeFunction::new("base64_encode", 1, base64_encode),
+        NativeFunction::new("base64_decode", 1, base64_decode),
+    ];
+
+    // Builtins don't come from the source, so they have no location.
+    let loc = FileLocation::new(0, 0);
+    for native in natives {
+        let name = Symbol::intern(native.name);
+        environments.define_global(&loc, name, Object::NativeFunction(native))?;
+    }
+    environments.define_global(
+        &loc,
+        Symbol::intern("PI"),
+        Object::Number(std::f64::consts::PI),
+    )?;
+    Ok(())
+}
+
+/**
+ * Look up a built-in method on `receiver`.  The arity doesn't include the receiver itself.
+ */
+pub fn find_method(receiver: &Object, name: &str) -> Option<NativeFunction> {
+    let method = match (receiver, name) {
+        (Object::String(_) | Object::List(_) | Object::Bytes(_), "len") => {
+            NativeFunction::new("len", 0, len)
+        }
+        (Object::String(_), "upper") => NativeFunction::new("upper", 0, upper),
+        (Object::String(_), "lower") => NativeFunction::new("lower", 0, lower),
+        (Object::String(_), "substring") => NativeFunction::new("substring", 2, substring),
+        (Object::String(_), "split") => NativeFunction::new("split", 1, split),
+        (Object::List(_), "join") => NativeFunction::new("join", 1, join),
+        _ => return None,
+    };
+    Some(method)
+}
+
+fn expect_string<'a>(
+    loc: &dyn HasFileLocation,
+    name: &str,
+    value: &'a Object,
+) -> Result<&'a str, RuntimeError> {
+    match value {
+        Object::String(s) => Ok(s),
+        _ => Err(RuntimeError::at(
+            format!("{} expects a string", name).as_str(),
+            loc,
+        )),
+    }
+}
+
+fn expect_number(
+    loc: &dyn HasFileLocation,
+    name: &str,
+    value: &Object,
+) -> Result<f64, RuntimeError> {
+    match value {
+        Object::Number(n) => Ok(*n),
+        _ => Err(RuntimeError::at(
+            format!("{} expects a number", name).as_str(),
+            loc,
+        )),
+    }
+}
+
+fn expect_list<'a>(
+    loc: &dyn HasFileLocation,
+    name: &str,
+    value: &'a Object,
+) -> Result<&'a Rc<Vec<Object>>, RuntimeError> {
+    match value {
+        Object::List(items) => Ok(items),
+        _ => Err(RuntimeError::at(
+            format!("{} expects a list", name).as_str(),
+            loc,
+        )),
+    }
+}
+
+fn expect_bytes<'a>(
+    loc: &dyn HasFileLocation,
+    name: &str,
+    value: &'a Object,
+) -> Result<&'a Rc<Vec<u8>>, RuntimeError> {
+    match value {
+        Object::Bytes(bytes) => Ok(bytes),
+        _ => Err(RuntimeError::at(
+            format!("{} expects bytes", name).as_str(),
+            loc,
+        )),
+    }
+}
+
+fn expect_index(
+    loc: &dyn HasFileLocation,
+    name: &str,
+    value: &Object,
+) -> Result<usize, RuntimeError> {
+    match value {
+        Object::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(RuntimeError::at(
+            format!("{} expects a non-negative integer index", name).as_str(),
+            loc,
+        )),
+    }
+}
+
+/**
+ * The number of characters in a string, items in a list, or bytes in bytes.
+ */
+fn len(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    match &args[0] {
+        Object::List(items) => Ok(Object::Number(items.len() as f64)),
+        Object::Bytes(bytes) => Ok(Object::Number(bytes.len() as f64)),
+        value => {
+            let s = expect_string(loc, "len", value)?;
+            Ok(Object::Number(s.chars().count() as f64))
+        }
+    }
+}
+
+fn upper(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "upper", &args[0])?;
+    Ok(Object::String(s.to_uppercase()))
+}
+
+fn lower(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "lower", &args[0])?;
+    Ok(Object::String(s.to_lowercase()))
+}
+
+// There's no terminal on wasm, so it's never in raw mode.
+#[cfg(target_arch = "wasm32")]
+fn is_raw_mode_enabled() -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn enable_raw_mode() -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn disable_raw_mode() -> io::Result<()> {
+    Ok(())
+}
+
+/**
+ * Print the prompt where `print` writes, and read one line from stdin, without its line ending.
+ * Returns `nil` at the end of input.
+ */
+fn input(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let io_error = |e: io::Error| RuntimeError::at(format!("input failed: {}", e).as_str(), loc);
+
+    // The REPL runs the terminal in raw mode, which would hide the typed line and ignore Enter.
+    let was_raw = is_raw_mode_enabled().unwrap_or(false);
+    if was_raw {
+        disable_raw_mode().map_err(io_error)?;
+    }
+
+    let output = interpreter.output();
+    let mut line = String::new();
+    let result = write!(output, "{}", args[0])
+        .and_then(|_| output.flush())
+        .and_then(|_| io::stdin().lock().read_line(&mut line));
+
+    if was_raw {
+        enable_raw_mode().map_err(io_error)?;
+    }
+
+    if result.map_err(io_error)? == 0 {
+        return Ok(Object::Nil);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Object::String(line))
+}
+
+fn type_of(
+    _interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::String(args[0].type_name().to_string()))
+}
+
+/**
+ * Convert a value to a number.  Strings that don't hold a number are an error rather than `nil`.
+ */
+fn number(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    match &args[0] {
+        Object::Number(_) | Object::NaN => Ok(args[0].clone()),
+        Object::Boolean(b) => Ok(Object::Number(if *b { 1.0 } else { 0.0 })),
+        Object::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Object::Number(n)),
+            _ => Err(RuntimeError::at(
+                format!("cannot convert \"{}\" to a number", s).as_str(),
+                loc,
+            )),
+        },
+        value => Err(RuntimeError::at(
+            format!("cannot convert a {} to a number", value.type_name()).as_str(),
+            loc,
+        )),
+    }
+}
+
+fn string(
+    _interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::String(args[0].to_string()))
+}
+
+/**
+ * Fill each `{}` in `fmt` with the next argument, as it would print.  `{{` and `}}` stand for
+ * literal braces, and any other brace is an error, as is having too few or too many arguments.
+ */
+fn format(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let fmt = expect_string(loc, "format", &args[0])?;
+    let mut values = args[1..].iter();
+    let mut filled = 0;
+    let mut result = String::with_capacity(fmt.len());
+
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                let Some(value) = values.next() else {
+                    return Err(RuntimeError::at(
+                        format!(
+                            "format has more placeholders than the {} argument(s) given",
+                            args.len() - 1
+                        )
+                        .as_str(),
+                        loc,
+                    ));
+                };
+                result.push_str(&value.to_string());
+                filled += 1;
+            }
+            ('{', _) | ('}', _) => {
+                return Err(RuntimeError::at(
+                    format!(
+                        "format found an unmatched '{}'; use '{}{}' for a literal brace",
+                        c, c, c
+                    )
+                    .as_str(),
+                    loc,
+                ));
+            }
+            _ => result.push(c),
+        }
+    }
+
+    if values.len() > 0 {
+        return Err(RuntimeError::at(
+            format!(
+                "format was given {} argument(s) but has only {} placeholder(s)",
+                args.len() - 1,
+                filled
+            )
+            .as_str(),
+            loc,
+        ));
+    }
+    Ok(Object::String(result))
+}
+
+/**
+ * Raise a runtime error at the call if `cond` is falsy, using `message` if one was given.
+ */
+fn assert(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    if args[0].is_truthy() {
+        return Ok(Object::Nil);
+    }
+    let message = match args.get(1) {
+        Some(message) => format!("assertion failed: {}", message),
+        None => "assertion failed".to_string(),
+    };
+    Err(RuntimeError::at(message.as_str(), loc))
+}
+
+/**
+ * The characters of `s` from `start` up to, but not including, `end`.
+ */
+fn substring(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "substring", &args[0])?;
+    let start = expect_index(loc, "substring", &args[1])?;
+    let end = expect_index(loc, "substring", &args[2])?;
+
+    let length = s.chars().count();
+    if start > end || end > length {
+        return Err(RuntimeError::at(
+            format!(
+                "substring range {}..{} is out of bounds for a string of length {}",
+                start, end, length
+            )
+            .as_str(),
+            loc,
+        ));
+    }
+    Ok(Object::String(
+        s.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+/**
+ * The items of a list as they would print, with `sep` between each.  An empty list joins to `""`.
+ */
+fn join(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let items = expect_list(loc, "join", &args[0])?;
+    let sep = expect_string(loc, "join", &args[1])?;
+    let parts: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    Ok(Object::String(parts.join(sep)))
+}
+
+/**
+ * A list of the pieces of `s` between each `sep`.  An empty `sep` splits `s` into its characters.
+ */
+fn split(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "split", &args[0])?;
+    let sep = expect_string(loc, "split", &args[1])?;
+    let parts: Vec<Object> = if sep.is_empty() {
+        s.chars().map(|c| Object::String(c.to_string())).collect()
+    } else {
+        s.split(sep)
+            .map(|part| Object::String(part.to_string()))
+            .collect()
+    };
+    Ok(Object::List(Rc::new(parts)))
+}
+
+/**
+ * A new list holding the result of calling `f` on each item.
+ */
+fn map(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let items = expect_list(loc, "map", &args[0])?;
+    let mut values = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        values.push(interpreter.call_value(loc, args[1].clone(), vec![item.clone()])?);
+    }
+    Ok(Object::List(Rc::new(values)))
+}
+
+/**
+ * A new list holding the items for which `f` returns something truthy.
+ */
+fn filter(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let items = expect_list(loc, "filter", &args[0])?;
+    let mut values = Vec::new();
+    for item in items.iter() {
+        if interpreter
+            .call_value(loc, args[1].clone(), vec![item.clone()])?
+            .is_truthy()
+        {
+            values.push(item.clone());
+        }
+    }
+    Ok(Object::List(Rc::new(values)))
+}
+
+/**
+ * Fold the items into one value, starting from `init` and calling `f(accumulator, item)` for each.
+ */
+fn reduce(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let items = expect_list(loc, "reduce", &args[0])?;
+    let mut accumulator = args[2].clone();
+    for item in items.iter() {
+        accumulator =
+            interpreter.call_value(loc, args[1].clone(), vec![accumulator, item.clone()])?;
+    }
+    Ok(accumulator)
+}
+
+/**
+ * End the program with the given status, or 0.  This unwinds back to the caller of the
+ * interpreter rather than ending the process directly, so embedders stay in control.
+ */
+fn exit(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let code = match args.first() {
+        None => 0,
+        Some(Object::Number(n))
+            if n.fract() == 0.0 && *n >= i32::MIN as f64 && *n <= i32::MAX as f64 =>
+        {
+            *n as i32
+        }
+        Some(_) => return Err(RuntimeError::at("exit expects an integer status code", loc)),
+    };
+    Err(RuntimeError::exit(code))
+}
+
+/**
+ * The value of an environment variable, or `nil` if it isn't set.
+ */
+fn env(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let name = expect_string(loc, "env", &args[0])?;
+    match std::env::var(name) {
+        Ok(value) => Ok(Object::String(value)),
+        Err(_) => Ok(Object::Nil),
+    }
+}
+
+fn expect_file_access(
+    interpreter: &Interpreter,
+    loc: &dyn HasFileLocation,
+    name: &str,
+) -> Result<(), RuntimeError> {
+    if interpreter.has_file_access() {
+        Ok(())
+    } else {
+        Err(RuntimeError::at(
+            format!("{} needs file access, which isn't enabled", name).as_str(),
+            loc,
+        ))
+    }
+}
+
+/**
+ * The contents of a file, as a string.
+ */
+fn read_file(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    expect_file_access(interpreter, loc, "read_file")?;
+    let path = expect_string(loc, "read_file", &args[0])?;
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Object::String(contents)),
+        Err(e) => Err(RuntimeError::at(
+            format!("cannot read {}: {}", path, e).as_str(),
+            loc,
+        )),
+    }
+}
+
+/**
+ * Replace the contents of a file with a string.
+ */
+fn write_file(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    expect_file_access(interpreter, loc, "write_file")?;
+    let path = expect_string(loc, "write_file", &args[0])?;
+    let contents = expect_string(loc, "write_file", &args[1])?;
+    match fs::write(path, contents) {
+        Ok(()) => Ok(Object::Nil),
+        Err(e) => Err(RuntimeError::at(
+            format!("cannot write '{}': {}", path, e).as_str(),
+            loc,
+        )),
+    }
+}
+
+/**
+ * A listing of every variable visible from the call, one `name = value` per line.
+ */
+fn dump_env(
+    interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    _args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let lines: Vec<String> = interpreter
+        .variables()
+        .iter()
+        .map(|(name, value)| format!("{} = {}", name, value.repr()))
+        .collect();
+    Ok(Object::String(lines.join("\n")))
+}
+
+/**
+ * The square root.  A negative number has no real square root, so it's an error rather than NaN.
+ */
+fn sqrt(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let n = expect_number(loc, "sqrt", &args[0])?;
+    if n < 0.0 {
+        return Err(RuntimeError::at("sqrt expects a non-negative number", loc));
+    }
+    Ok(Object::number(n.sqrt()))
+}
+
+fn abs(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(expect_number(loc, "abs", &args[0])?.abs()))
+}
+
+fn floor(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(
+        expect_number(loc, "floor", &args[0])?.floor(),
+    ))
+}
+
+fn ceil(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(expect_number(loc, "ceil", &args[0])?.ceil()))
+}
+
+/**
+ * Round to the nearest whole number, with halves rounding away from zero.
+ */
+fn round(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(
+        expect_number(loc, "round", &args[0])?.round(),
+    ))
+}
+
+/**
+ * The smallest of one or more numbers.
+ */
+fn min(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let mut result = f64::INFINITY;
+    for arg in &args {
+        result = result.min(expect_number(loc, "min", arg)?);
+    }
+    Ok(Object::number(result))
+}
+
+/**
+ * The largest of one or more numbers.
+ */
+fn max(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let mut result = f64::NEG_INFINITY;
+    for arg in &args {
+        result = result.max(expect_number(loc, "max", arg)?);
+    }
+    Ok(Object::number(result))
+}
+
+/**
+ * `base` raised to the power `exponent`.  Results with no real value, like `pow(-8, 0.5)`, are NaN.
+ */
+fn pow(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let base = expect_number(loc, "pow", &args[0])?;
+    let exponent = expect_number(loc, "pow", &args[1])?;
+    Ok(Object::number(base.powf(exponent)))
+}
+
+/**
+ * A random number in `[0, 1)`.
+ */
+fn random(
+    interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    _args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(interpreter.rng().next_f64()))
+}
+
+/**
+ * A random integer from `low` to `high`, including both ends.
+ */
+fn randint(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let low = expect_number(loc, "randint", &args[0])?;
+    let high = expect_number(loc, "randint", &args[1])?;
+    if low.fract() != 0.0 || high.fract() != 0.0 {
+        return Err(RuntimeError::at("randint expects integer bounds", loc));
+    }
+    if low > high {
+        return Err(RuntimeError::at(
+            "randint expects the low bound to be no more than the high bound",
+            loc,
+        ));
+    }
+
+    let span = high - low + 1.0;
+    let offset = (interpreter.rng().next_f64() * span).floor();
+    Ok(Object::number(low + offset.min(span - 1.0)))
+}
+
+/**
+ * Restart the random numbers from `n`, so the same sequence comes out every run.
+ */
+fn seed(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let n = expect_number(loc, "seed", &args[0])?;
+    if n.fract() != 0.0 || !n.is_finite() {
+        return Err(RuntimeError::at("seed expects an integer", loc));
+    }
+    interpreter.rng().seed(n as i64 as u64);
+    Ok(Object::Nil)
+}
+
+/**
+ * The seconds since `clock` was first called, as a fraction.  Only the difference between two
+ * calls means anything.
+ */
+fn clock(
+    interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    _args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::number(interpreter.elapsed().as_secs_f64()))
+}
+
+// How long `sleep` waits between checks of the stop flag.
+const SLEEP_CHUNK: Duration = Duration::from_millis(10);
+
+/**
+ * Pause for `ms` milliseconds.  The wait is split into short chunks so that setting the stop flag
+ * interrupts it, which is a runtime error.
+ */
+fn sleep(
+    interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let ms = expect_number(loc, "sleep", &args[0])?;
+    if ms < 0.0 || !ms.is_finite() {
+        return Err(RuntimeError::at(
+            "sleep expects a non-negative number of milliseconds",
+            loc,
+        ));
+    }
+
+    let mut remaining = Duration::from_secs_f64(ms / 1000.0);
+    while !remaining.is_zero() {
+        if interpreter.is_stopped() {
+            return Err(RuntimeError::at("sleep was interrupted", loc));
+        }
+        let chunk = remaining.min(SLEEP_CHUNK);
+        thread::sleep(chunk);
+        remaining -= chunk;
+    }
+    Ok(Object::Nil)
+}
+
+/**
+ * The version of the interpreter, e.g. `"0.1.0"`.
+ */
+fn version(
+    _interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    _args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    Ok(Object::String(AppInfo::from_env().version.to_string()))
+}
+
+/**
+ * The name, version, and description of the interpreter, as the fields of a `VersionInfo`
+ * instance.
+ */
+fn version_info(
+    _interpreter: &mut Interpreter,
+    _loc: &dyn HasFileLocation,
+    _args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let app_info = AppInfo::from_env();
+    let mut info = Instance::new(Rc::new(Class::new("VersionInfo", None, &Vec::new())));
+    info.set_field("name", Object::String(app_info.name.to_string()));
+    info.set_field("version", Object::String(app_info.version.to_string()));
+    info.set_field(
+        "description",
+        Object::String(app_info.description.to_string()),
+    );
+    Ok(Object::Instance(Rc::new(RefCell::new(info))))
+}
+
+/**
+ * The UTF-8 encoding of a string, as bytes.
+ */
+fn to_utf8(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "to_utf8", &args[0])?;
+    Ok(Object::Bytes(Rc::new(s.as_bytes().to_vec())))
+}
+
+/**
+ * The string that `bytes` encode as UTF-8.  Bytes that aren't valid UTF-8 are an error, rather than
+ * being quietly replaced.
+ */
+fn from_utf8(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let bytes = expect_bytes(loc, "from_utf8", &args[0])?;
+    match String::from_utf8(bytes.to_vec()) {
+        Ok(s) => Ok(Object::String(s)),
+        Err(err) => Err(RuntimeError::at(
+            format!(
+                "from_utf8 got invalid UTF-8 at byte {}",
+                err.utf8_error().valid_up_to()
+            )
+            .as_str(),
+            loc,
+        )),
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/**
+ * Bytes as standard, padded base64.  A string is encoded as its UTF-8 bytes.
+ */
+fn base64_encode(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let bytes: &[u8] = match &args[0] {
+        Object::String(s) => s.as_bytes(),
+        value => expect_bytes(loc, "base64_encode", value)?,
+    };
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | (*byte as u32) << (16 - 8 * i)
+        });
+        // Three bytes make four characters; a short final chunk makes fewer, padded with `=`.
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    Ok(Object::String(encoded))
+}
+
+/**
+ * The bytes encoded by a standard base64 string.  The padding may be left off.
+ */
+fn base64_decode(
+    _interpreter: &mut Interpreter,
+    loc: &dyn HasFileLocation,
+    args: Vec<Object>,
+) -> Result<Object, RuntimeError> {
+    let s = expect_string(loc, "base64_decode", &args[0])?;
+    let invalid = || RuntimeError::at("base64_decode got invalid base64", loc);
+
+    let digits = s
+        .strip_suffix("==")
+        .or_else(|| s.strip_suffix('='))
+        .unwrap_or(s);
+    // A lone character left over can't make a whole byte.
+    if digits.len() % 4 == 1 {
+        return Err(invalid());
+    }
+
+    let mut decoded = Vec::with_capacity(digits.len() / 4 * 3 + 2);
+    for chunk in digits.as_bytes().chunks(4) {
+        let mut group = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or_else(invalid)?;
+            group |= (value as u32) << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            decoded.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(Object::Bytes(Rc::new(decoded)))
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+
+use crate::parser::FunctionDecl;
+
+use super::{Function, Object};
+
+/**
+ * A class declared in Lox code.  Calling it creates an instance and runs its `init` method.
+ */
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    methods: HashMap<String, Function>,
+}
+
+impl Class {
+    pub fn new(name: &str, superclass: Option<Rc<Class>>, methods: &[FunctionDecl]) -> Self {
+        let methods = methods
+            .iter()
+            .map(|method| {
+                let function = Function::method(Rc::new(method.clone()), superclass.clone());
+                (method.name.clone(), function)
+            })
+            .collect();
+        Self {
+            name: name.to_string(),
+            superclass,
+            methods,
+        }
+    }
+
+    /**
+     * Look up a method on this class, then on its superclasses.
+     */
+    pub fn find_method(&self, name: &str) -> Option<Function> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+// Classes are only equal to themselves.
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+impl Debug for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+/**
+ * An instance of a class, holding its own fields.
+ */
+pub struct Instance {
+    pub class: Rc<Class>,
+    fields: HashMap<String, Object>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<Object> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set_field(&mut self, name: &str, value: Object) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
+
+// Fields can refer back to the instance, so don't print them.
+impl Debug for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{} instance>", self.class.name)
+    }
+}
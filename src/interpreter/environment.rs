@@ -1,40 +1,101 @@
 use std::collections::HashMap;
 
-use crate::debug::HasFileLocation;
+use crate::{debug::HasFileLocation, lexer::Symbol};
 
 use super::{Object, RuntimeError};
 
+// Scopes with up to this many variables keep them in a list.
+const SMALL_SCOPE: usize = 8;
+
+/**
+ * The variables of one scope.  Most scopes are blocks or calls holding a handful of variables,
+ * where scanning a short list is cheaper than hashing, so a map is only used once a scope grows
+ * past `SMALL_SCOPE`, as the globals do.
+ */
+enum Values {
+    Small(Vec<(Symbol, Object)>),
+    Large(HashMap<Symbol, Object>),
+}
+
+impl Values {
+    fn get(&self, name: Symbol) -> Option<&Object> {
+        match self {
+            Values::Small(values) => values.iter().find(|(n, _)| *n == name).map(|(_, v)| v),
+            Values::Large(values) => values.get(&name),
+        }
+    }
+
+    fn get_mut(&mut self, name: Symbol) -> Option<&mut Object> {
+        match self {
+            Values::Small(values) => values.iter_mut().find(|(n, _)| *n == name).map(|(_, v)| v),
+            Values::Large(values) => values.get_mut(&name),
+        }
+    }
+
+    /**
+     * Add a variable that isn't already in the scope.
+     */
+    fn insert(&mut self, name: Symbol, value: Object) {
+        match self {
+            Values::Small(values) if values.len() < SMALL_SCOPE => values.push((name, value)),
+            Values::Small(values) => {
+                let mut map: HashMap<Symbol, Object> = values.drain(..).collect();
+                map.insert(name, value);
+                *self = Values::Large(map);
+            }
+            Values::Large(values) => {
+                values.insert(name, value);
+            }
+        }
+    }
+
+    fn remove(&mut self, name: Symbol) -> Option<Object> {
+        match self {
+            Values::Small(values) => {
+                let index = values.iter().position(|(n, _)| *n == name)?;
+                Some(values.swap_remove(index).1)
+            }
+            Values::Large(values) => values.remove(&name),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&Symbol, &Object)> + '_> {
+        match self {
+            Values::Small(values) => Box::new(values.iter().map(|(n, v)| (n, v))),
+            Values::Large(values) => Box::new(values.iter()),
+        }
+    }
+}
+
 pub struct Environment {
-    values: HashMap<String, Object>,
+    values: Values,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            values: HashMap::new(),
+            values: Values::Small(Vec::new()),
         }
     }
 
-    fn err_already_defined(&self, loc: &dyn HasFileLocation, name: &str) -> RuntimeError {
-        RuntimeError::new(
-            format!("variable {} already defined", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
-        )
+    fn err_already_defined(&self, loc: &dyn HasFileLocation, name: Symbol) -> RuntimeError {
+        RuntimeError::at(format!("variable {} already defined", name).as_str(), loc)
     }
 
-    fn err_not_defined(&self, loc: &dyn HasFileLocation, name: &str) -> RuntimeError {
-        RuntimeError::new(
-            format!("variable {} not defined", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
-        )
+    fn err_not_defined(&self, loc: &dyn HasFileLocation, name: Symbol) -> RuntimeError {
+        RuntimeError::at(format!("variable {} not defined", name).as_str(), loc)
     }
 
     fn assert_not_defined(
         &self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
     ) -> Result<(), RuntimeError> {
         if self.is_defined(name) {
             return Err(self.err_already_defined(loc, name));
@@ -42,7 +103,7 @@ impl Environment {
         Ok(())
     }
 
-    fn assert_defined(&self, loc: &dyn HasFileLocation, name: &str) -> Result<(), RuntimeError> {
+    fn assert_defined(&self, loc: &dyn HasFileLocation, name: Symbol) -> Result<(), RuntimeError> {
         if !self.is_defined(name) {
             return Err(self.err_not_defined(loc, name));
         }
@@ -52,15 +113,15 @@ impl Environment {
     pub fn define(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
         value: Object,
     ) -> Result<Object, RuntimeError> {
         self.assert_not_defined(loc, name)?;
-        self.values.insert(name.to_string(), value);
+        self.values.insert(name, value);
         self.get(loc, name)
     }
 
-    pub fn get(&self, loc: &dyn HasFileLocation, name: &str) -> Result<Object, RuntimeError> {
+    pub fn get(&self, loc: &dyn HasFileLocation, name: Symbol) -> Result<Object, RuntimeError> {
         if self.is_defined(name) {
             return Ok(self.values.get(name).unwrap().clone());
         }
@@ -70,11 +131,11 @@ impl Environment {
     pub fn assign(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
         value: Object,
     ) -> Result<Object, RuntimeError> {
-        if self.is_defined(name) {
-            self.values.insert(name.to_string(), value);
+        if let Some(slot) = self.values.get_mut(name) {
+            *slot = value;
             return self.get(loc, name);
         }
         Err(self.err_not_defined(loc, name))
@@ -83,14 +144,99 @@ impl Environment {
     pub fn delete(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
     ) -> Result<Object, RuntimeError> {
         self.assert_defined(loc, name)?;
-        self.values.remove(name);
-        self.get(loc, name)
+        // Hand back the value that was removed.
+        Ok(self.values.remove(name).unwrap())
+    }
+
+    pub fn is_defined(&self, name: Symbol) -> bool {
+        self.values.get(name).is_some()
+    }
+
+    /**
+     * Every variable in this scope, sorted by name.
+     */
+    pub fn values(&self) -> Vec<(String, Object)> {
+        let mut values: Vec<(String, Object)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::FileLocation;
+
+    fn number(env: &Environment, name: Symbol) -> f64 {
+        match env.get(&FileLocation::new(1, 1), name) {
+            Ok(Object::Number(n)) => n,
+            other => panic!("{} is {:?}", name, other.map_err(|e| e.msg)),
+        }
     }
 
-    pub fn is_defined(&self, name: &str) -> bool {
-        self.values.contains_key(name)
+    #[test]
+    fn large_scopes_behave_like_small_ones() {
+        let loc = FileLocation::new(1, 1);
+        let names: Vec<Symbol> = (0..SMALL_SCOPE * 2)
+            .map(|i| Symbol::intern(&format!("v{}", i)))
+            .collect();
+
+        let mut env = Environment::new();
+        for (i, name) in names.iter().enumerate() {
+            env.define(&loc, *name, Object::Number(i as f64)).unwrap();
+        }
+        assert!(matches!(env.values, Values::Large(_)));
+
+        for (i, name) in names.iter().enumerate() {
+            env.assign(&loc, *name, Object::Number(i as f64 * 10.0))
+                .unwrap();
+        }
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(number(&env, *name), i as f64 * 10.0);
+        }
+
+        let err = env.define(&loc, names[0], Object::Nil).unwrap_err();
+        assert_eq!(err.msg, "variable v0 already defined");
+
+        env.delete(&loc, names[0]).unwrap();
+        assert!(!env.is_defined(names[0]));
+        assert!(env.assign(&loc, names[0], Object::Nil).is_err());
+        assert_eq!(env.values().len(), names.len() - 1);
+    }
+
+    #[test]
+    fn large_scopes_shadow_and_restore_outer_variables() {
+        let source = "let a = 1; let b = 2;
+            {
+                let a = 10; let b = 20; let c = 3; let d = 4; let e = 5;
+                let f = 6; let g = 7; let h = 8; let i = 9; let j = 10;
+                a = a + j;
+                b = a + b;
+            }
+            [a, b]";
+        let (_, result) = crate::run_captured(source);
+        assert_eq!(
+            result.map(|value| value.to_string()).ok(),
+            Some("[1, 2]".to_string())
+        );
+
+        let source = "{
+                let a = 1; let b = 2; let c = 3; let d = 4; let e = 5;
+                let f = 6; let g = 7; let h = 8; let i = 9;
+                a = b = i;
+                [a, b, i]
+            }";
+        let (_, result) = crate::run_captured(source);
+        assert_eq!(
+            result.map(|value| value.to_string()).ok(),
+            Some("[9, 9, 9]".to_string())
+        );
     }
 }
@@ -1,133 +1,271 @@
-use crate::debug::HasFileLocation;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{debug::HasFileLocation, lexer::Symbol};
 
 use super::{Environment, Object, RuntimeError};
 
+/**
+ * A scope that closures can hold on to after the block that made it has finished.
+ */
+pub type Scope = Rc<RefCell<Environment>>;
+
 pub struct EnvironmentStack {
-    stack: Vec<Environment>,
+    stack: Vec<Scope>,
+}
+
+impl Default for EnvironmentStack {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EnvironmentStack {
     pub fn new() -> Self {
         Self {
-            stack: vec![Environment::new()],
+            stack: vec![Self::new_scope()],
         }
     }
 
+    fn new_scope() -> Scope {
+        Rc::new(RefCell::new(Environment::new()))
+    }
+
     pub fn enter_scope(&mut self) {
-        self.stack.push(Environment::new());
+        self.stack.push(Self::new_scope());
     }
 
     pub fn leave_scope(&mut self, loc: &dyn HasFileLocation) -> Result<(), RuntimeError> {
         if self.stack.len() == 1 {
-            return Err(RuntimeError::new(
-                "cannot leave global scope",
-                loc.get_line(),
-                loc.get_column(),
-            ));
+            return Err(RuntimeError::at("cannot leave global scope", loc));
         }
         self.stack.pop();
         Ok(())
     }
 
+    /**
+     * Every scope above the globals, for a function to capture when it's created.
+     */
+    pub fn capture(&self) -> Vec<Scope> {
+        self.stack[1..].to_vec()
+    }
+
+    /**
+     * Set aside every scope above the globals, replace them with the scopes the function
+     * captured, and start a fresh one for the call.
+     *
+     * Functions can't see the locals of whoever called them, only the globals and their own scopes.
+     */
+    pub fn enter_function(&mut self, closure: &[Scope]) -> Vec<Scope> {
+        let saved = self.stack.split_off(1);
+        self.stack.extend(closure.iter().cloned());
+        self.stack.push(Self::new_scope());
+        saved
+    }
+
+    /**
+     * Drop the function's scopes and put back the ones set aside by `enter_function`.
+     */
+    pub fn leave_function(&mut self, saved: Vec<Scope>) {
+        self.stack.truncate(1);
+        self.stack.extend(saved);
+    }
+
     pub fn define_global(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
+        value: Object,
+    ) -> Result<Object, RuntimeError> {
+        if let Some(env) = self.stack.first() {
+            return env.borrow_mut().define(loc, name, value);
+        }
+        Err(RuntimeError::at(
+            format!("cannot retrieve global environment for variable: {}", name).as_str(),
+            loc,
+        ))
+    }
+
+    /**
+     * Set a variable in the global scope, defining it first if needed.
+     */
+    pub fn set_global(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: Symbol,
         value: Object,
     ) -> Result<Object, RuntimeError> {
-        if let Some(env) = self.stack.first_mut() {
+        if let Some(env) = self.stack.first() {
+            let mut env = env.borrow_mut();
+            if env.is_defined(name) {
+                return env.assign(loc, name, value);
+            }
             return env.define(loc, name, value);
         }
-        Err(RuntimeError::new(
+        Err(RuntimeError::at(
             format!("cannot retrieve global environment for variable: {}", name).as_str(),
-            0,
-            0,
+            loc,
         ))
     }
 
     pub fn define(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
         value: Object,
     ) -> Result<Object, RuntimeError> {
         // Only define a variable in the top environment.
-        if let Some(env) = self.stack.last_mut() {
-            return env.define(loc, name, value);
+        if let Some(env) = self.stack.last() {
+            return env.borrow_mut().define(loc, name, value);
         }
-        Err(RuntimeError::new(
+        Err(RuntimeError::at(
             format!("cannot retrieve environment for variable: {}", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
+            loc,
         ))
     }
 
-    pub fn get(&self, loc: &dyn HasFileLocation, name: &str) -> Result<Object, RuntimeError> {
+    /**
+     * The environment `depth` scopes out from the innermost one.
+     */
+    fn env_at(&self, depth: usize) -> Option<&Scope> {
+        let index = self.stack.len().checked_sub(depth + 1)?;
+        self.stack.get(index)
+    }
+
+    fn undefined(loc: &dyn HasFileLocation, name: Symbol) -> RuntimeError {
+        RuntimeError::at(format!("undefined variable: {}", name).as_str(), loc)
+    }
+
+    /**
+     * Read a variable from the scope the resolver picked for it.
+     */
+    pub fn get_at(
+        &self,
+        loc: &dyn HasFileLocation,
+        depth: usize,
+        name: Symbol,
+    ) -> Result<Object, RuntimeError> {
+        match self.env_at(depth) {
+            Some(env) => env
+                .borrow()
+                .get(loc, name)
+                .map_err(|_| Self::undefined(loc, name)),
+            None => Err(Self::undefined(loc, name)),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        depth: usize,
+        name: Symbol,
+        value: Object,
+    ) -> Result<Object, RuntimeError> {
+        match self.env_at(depth) {
+            Some(env) => env
+                .borrow_mut()
+                .assign(loc, name, value)
+                .map_err(|_| Self::undefined(loc, name)),
+            None => Err(Self::undefined(loc, name)),
+        }
+    }
+
+    /**
+     * Every variable that can be seen from the current scope, sorted by name.  Where a name is
+     * shadowed, only the innermost value is included.
+     */
+    pub fn visible(&self) -> Vec<(String, Object)> {
+        let mut values: Vec<(String, Object)> = Vec::new();
+        for env in self.stack.iter().rev() {
+            for (name, value) in env.borrow().values() {
+                if !values.iter().any(|(seen, _)| *seen == name) {
+                    values.push((name, value));
+                }
+            }
+        }
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        values
+    }
+
+    pub fn get_global(
+        &self,
+        loc: &dyn HasFileLocation,
+        name: Symbol,
+    ) -> Result<Object, RuntimeError> {
+        self.get_at(loc, self.stack.len() - 1, name)
+    }
+
+    pub fn assign_global(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: Symbol,
+        value: Object,
+    ) -> Result<Object, RuntimeError> {
+        self.assign_at(loc, self.stack.len() - 1, name, value)
+    }
+
+    pub fn get(&self, loc: &dyn HasFileLocation, name: Symbol) -> Result<Object, RuntimeError> {
         // Starting from the last item in `stack`, work backwards looking for a definition of `name`
         for env in self.stack.iter().rev() {
-            match env.get(loc, name) {
+            match env.borrow().get(loc, name) {
                 Ok(value) => return Ok(value),
                 Err(_) => continue,
             }
         }
-        Err(RuntimeError::new(
+        Err(RuntimeError::at(
             format!("undefined variable: {}", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
+            loc,
         ))
     }
 
     pub fn assign(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
         value: Object,
     ) -> Result<Object, RuntimeError> {
-        for env in self.stack.iter_mut().rev() {
-            match env.assign(loc, name, value.clone()) {
+        for env in self.stack.iter().rev() {
+            match env.borrow_mut().assign(loc, name, value.clone()) {
                 Ok(_) => return Ok(value),
                 Err(_) => continue,
             }
         }
-        Err(RuntimeError::new(
+        Err(RuntimeError::at(
             format!("undefined variable: {}", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
+            loc,
         ))
     }
 
     pub fn delete(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &str,
+        name: Symbol,
     ) -> Result<Object, RuntimeError> {
         // Only delete the variable if it is defined in the top environment.
-        if let Some(env) = self.stack.last_mut() {
+        if let Some(env) = self.stack.last() {
+            let mut env = env.borrow_mut();
             if env.is_defined(name) {
                 return env.delete(loc, name);
             }
 
-            return Err(RuntimeError::new(
+            return Err(RuntimeError::at(
                 format!("undefined variable: {}", name).as_str(),
-                loc.get_line(),
-                loc.get_column(),
+                loc,
             ));
         }
 
-        Err(RuntimeError::new(
+        Err(RuntimeError::at(
             format!("cannot retrieve environment for variable: {}", name).as_str(),
-            loc.get_line(),
-            loc.get_column(),
+            loc,
         ))
     }
 
-    // pub fn is_locally_defined(&self, name: &str) -> bool {
+    // pub fn is_locally_defined(&self, name: Symbol) -> bool {
     //     self.stack.last().unwrap().is_defined(name)
     // }
 
-    pub fn is_defined(&self, name: &str) -> bool {
+    pub fn is_defined(&self, name: Symbol) -> bool {
         for env in self.stack.iter().rev() {
-            if env.is_defined(name) {
+            if env.borrow().is_defined(name) {
                 return true;
             }
         }
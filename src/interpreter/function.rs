@@ -0,0 +1,99 @@
+use std::{
+    fmt::{Debug, Display},
+    rc::Rc,
+};
+
+use crate::parser::FunctionDecl;
+
+use super::{Class, Object, Scope};
+
+/**
+ * A function declared in Lox code: a lambda, or a method taken from an instance.
+ */
+#[derive(Clone)]
+pub struct Function {
+    pub decl: Rc<FunctionDecl>,
+
+    // The instance a method was taken from, which `this` refers to inside it.
+    pub this: Option<Box<Object>>,
+
+    // The superclass of the class a method was declared in, which `super` refers to inside it.
+    pub superclass: Option<Rc<Class>>,
+
+    // The local scopes that were visible where a lambda was created.  Methods don't capture any.
+    pub closure: Vec<Scope>,
+}
+
+impl Function {
+    pub fn lambda(decl: Rc<FunctionDecl>, closure: Vec<Scope>) -> Self {
+        Self {
+            decl,
+            this: None,
+            superclass: None,
+            closure,
+        }
+    }
+
+    pub fn method(decl: Rc<FunctionDecl>, superclass: Option<Rc<Class>>) -> Self {
+        Self {
+            decl,
+            this: None,
+            superclass,
+            closure: Vec::new(),
+        }
+    }
+
+    /**
+     * A copy of this function with `this` bound to `instance`.
+     */
+    pub fn bind(&self, instance: Object) -> Self {
+        Self {
+            decl: self.decl.clone(),
+            this: Some(Box::new(instance)),
+            superclass: self.superclass.clone(),
+            closure: self.closure.clone(),
+        }
+    }
+
+    /**
+     * Whether `count` arguments is acceptable, given the parameters with defaults and any rest
+     * parameter.
+     */
+    pub fn accepts(&self, count: usize) -> bool {
+        let too_many = self.decl.rest.is_none() && count > self.decl.params.len();
+        count >= self.decl.required_params() && !too_many
+    }
+
+    /**
+     * The number of arguments expected, for error messages.
+     */
+    pub fn describe_arity(&self) -> String {
+        let min = self.decl.required_params();
+        let max = self.decl.params.len();
+        if self.decl.rest.is_some() {
+            format!("at least {}", min)
+        } else if min == max {
+            format!("{}", max)
+        } else {
+            format!("{} to {}", min, max)
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.decl, &other.decl) && self.this == other.this
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.decl.name)
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.decl.name)
+    }
+}
@@ -1,170 +1,263 @@
 use crate::{
-    debug::HasFileLocation,
-    parser::{BinaryOp, Expr, UnaryOp, Visitor},
+    debug::{Diagnostic, HasFileLocation, Stage},
+    lexer::{scan_tokens, Symbol},
+    parser::{parse, BinaryOp, Expr, FunctionDecl, Stmt, StmtVisitor, UnaryOp, Visitor},
 };
 
-use super::{runtime_error::Interrupt, EnvironmentStack, Object, RuntimeError};
+use std::{
+    cell::{Cell, OnceCell, RefCell},
+    cmp::Ordering,
+    collections::HashMap,
+    io::{self, Write},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{
+    builtins::{define_builtins, find_method},
+    format_number,
+    resolver::Resolver,
+    rng::Rng,
+    runtime_error::Interrupt,
+    Class, EnvironmentStack, Function, HasStopFlag, Instance, Object, RuntimeError, StepResult,
+};
 
 pub struct Interpreter {
     environments: EnvironmentStack,
+
+    // Where `print` writes to.
+    output: Box<dyn Write>,
+
+    // Raise an error on division by zero instead of producing NaN.
+    strict_math: bool,
+
+    // Treat `NaN == NaN` as true, rather than following IEEE.
+    nan_eq: bool,
+
+    // Let scripts read and write files.  Off by default, so an embedded interpreter can't touch
+    // the file system unless the host asks for it.
+    file_access: bool,
+
+    // How many times each kind of node has been visited, when tracing is on.
+    trace: Option<HashMap<&'static str, usize>>,
+
+    // The index of the statement `step` will run next.
+    step_cursor: usize,
+
+    // Where `random` and `randint` get their numbers from.
+    rng: Rng,
+
+    // When `clock` was first called, which later calls count from.  It's set lazily since
+    // `Instant::now` panics on wasm, where an interpreter that never asks the time should still run.
+    started: OnceCell<Instant>,
+
+    // Set from outside to cut a long-running builtin like `sleep` short.
+    stop_flag: Arc<AtomicBool>,
+
+    // The value given to the `break` being unwound, which the loop it breaks out of yields.  It's
+    // kept here rather than on the interrupt since errors have to be `Send`, and objects aren't.
+    break_value: Option<Object>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
         Self {
-            environments: EnvironmentStack::new(),
+            environments: Self::fresh_environments(),
+            output: Box::new(io::stdout()),
+            strict_math: false,
+            nan_eq: false,
+            file_access: false,
+            trace: None,
+            step_cursor: 0,
+            rng: Rng::from_time(),
+            started: OnceCell::new(),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            break_value: None,
         }
     }
 
-    fn store_result(
-        &mut self,
-        loc: &dyn HasFileLocation,
-        result: Object,
-    ) -> Result<(), RuntimeError> {
-        // TODO: Only store _ globally.
-        if !self.environments.is_defined("_") {
-            self.environments.define_global(loc, "_", result)?;
-        } else {
-            self.environments.assign(loc, "_", result)?;
+    fn fresh_environments() -> EnvironmentStack {
+        let mut environments = EnvironmentStack::new();
+        define_builtins(&mut environments).expect("builtins are only defined once");
+        environments
+    }
+
+    /**
+     * Forget every variable, `_` included, so the next program starts from nothing but the
+     * builtins.  The options the interpreter was built with are kept, and any trace counts start
+     * again from zero.
+     */
+    pub fn reset(&mut self) {
+        self.environments = Self::fresh_environments();
+        self.step_cursor = 0;
+        if let Some(counts) = &mut self.trace {
+            counts.clear();
         }
-        Ok(())
     }
 
-    pub fn eval(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
-        expr.accept(self)
+    /**
+     * Send the output of `print` somewhere other than stdout, e.g. a buffer to check in a test.
+     */
+    pub fn with_output(mut self, output: Box<dyn Write>) -> Self {
+        self.output = output;
+        self
     }
-}
 
-impl Visitor<Result<Object, RuntimeError>> for Interpreter {
-    fn visit_number(
-        &mut self,
-        _loc: &dyn HasFileLocation,
-        n: &f64,
-    ) -> Result<Object, RuntimeError> {
-        Ok(Object::Number(*n))
+    /**
+     * Where `print` writes, for builtins that write there too.
+     */
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
     }
 
-    fn visit_string(
-        &mut self,
-        _loc: &dyn HasFileLocation,
-        s: &String,
-    ) -> Result<Object, RuntimeError> {
-        Ok(Object::String(s.clone()))
+    pub fn with_strict_math(mut self, strict_math: bool) -> Self {
+        self.strict_math = strict_math;
+        self
     }
 
-    fn visit_boolean(
-        &mut self,
-        _loc: &dyn HasFileLocation,
-        b: &bool,
-    ) -> Result<Object, RuntimeError> {
-        Ok(Object::Boolean(*b))
+    pub fn with_nan_eq(mut self, nan_eq: bool) -> Self {
+        self.nan_eq = nan_eq;
+        self
     }
 
-    fn visit_nil(&mut self, _loc: &dyn HasFileLocation) -> Result<Object, RuntimeError> {
-        Ok(Object::Nil)
+    pub fn with_file_access(mut self, file_access: bool) -> Self {
+        self.file_access = file_access;
+        self
     }
 
-    fn visit_grouping(
-        &mut self,
-        _loc: &dyn HasFileLocation,
-        e: &Box<Expr>,
-    ) -> Result<Object, RuntimeError> {
-        e.accept(self)
+    pub fn has_file_access(&self) -> bool {
+        self.file_access
     }
 
-    fn visit_unary_op(
-        &mut self,
-        loc: &dyn HasFileLocation,
-        op: &UnaryOp,
-        e: &Box<Expr>,
-    ) -> Result<Object, RuntimeError> {
-        let e = e.accept(self)?;
+    /**
+     * Start `random` and `randint` from a fixed seed, so a run can be repeated exactly.  Scripts
+     * can do the same with `seed(n)`.
+     */
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
 
-        match op {
-            UnaryOp::Neg => {
-                if let Object::Number(n) = e {
-                    Ok(Object::Number(-n))
-                } else {
-                    Err(RuntimeError::new(
-                        "operand must be a number",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
-                }
-            }
-            UnaryOp::Not => Ok(Object::Boolean(e.is_falsy())),
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /**
+     * Share a stop flag with the host, e.g. one set by Ctrl+C, so setting it interrupts a sleeping
+     * script.
+     */
+    pub fn with_stop_flag(mut self, stop_flag: Arc<AtomicBool>) -> Self {
+        self.stop_flag = stop_flag;
+        self
+    }
+
+    /**
+     * How long it's been since this was first called.  This never goes backwards, unlike the
+     * wall clock.
+     */
+    pub fn elapsed(&self) -> Duration {
+        self.started.get_or_init(Instant::now).elapsed()
+    }
+
+    /**
+     * Count every node the interpreter visits, to see which constructs a program spends its time
+     * in.  The counts build up across calls to `execute` until tracing is turned off.
+     */
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = if trace { Some(HashMap::new()) } else { None };
+        self
+    }
+
+    /**
+     * The number of times each kind of node was visited, most frequent first.  This is empty
+     * unless tracing is on.
+     */
+    pub fn trace_report(&self) -> Vec<(&'static str, usize)> {
+        let mut report: Vec<(&'static str, usize)> = match &self.trace {
+            Some(counts) => counts.iter().map(|(node, count)| (*node, *count)).collect(),
+            None => Vec::new(),
+        };
+        report.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        report
+    }
+
+    fn trace(&mut self, node: &'static str) {
+        if let Some(counts) = &mut self.trace {
+            *counts.entry(node).or_insert(0) += 1;
         }
     }
 
-    fn visit_binary_op(
-        &mut self,
+    /**
+     * Apply an arithmetic, comparison, or membership operator to two evaluated operands.
+     */
+    fn apply_binary_op(
+        &self,
         loc: &dyn HasFileLocation,
         op: &BinaryOp,
-        e1: &Box<Expr>,
-        e2: &Box<Expr>,
+        left: Object,
+        right: Object,
     ) -> Result<Object, RuntimeError> {
-        let left = e1.accept(self)?;
-
-        match op {
-            BinaryOp::LogicalAnd => {
-                if !left.is_truthy() {
-                    return Ok(left);
-                }
-                return Ok(e2.accept(self)?);
-            }
-            BinaryOp::LogicalOr => {
-                if left.is_truthy() {
-                    return Ok(left);
-                }
-                return Ok(e2.accept(self)?);
-            }
-            _ => {}
-        }
-
-        let right = e2.accept(self)?;
-
         match op {
             BinaryOp::Add => {
                 if let (Object::Number(left), Object::Number(right)) = (left.clone(), right.clone())
                 {
-                    Ok(Object::Number(left + right))
+                    Ok(Object::number(left + right))
                 } else if let (Object::String(left), Object::String(right)) =
                     (left.clone(), right.clone())
                 {
                     Ok(Object::String(format!("{}{}", left, right)))
-                } else if let (Object::String(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::String(format!("{}{}", left, right)))
+                } else if let (Object::String(left), Object::Number(right)) =
+                    (left.clone(), right.clone())
+                {
+                    Ok(Object::String(format!("{}{}", left, format_number(right))))
+                } else if let (Object::Number(left), Object::String(right)) =
+                    (left.clone(), right.clone())
+                {
+                    Ok(Object::String(format!("{}{}", format_number(left), right)))
+                } else if let (Object::List(left), Object::List(right)) =
+                    (left.clone(), right.clone())
+                {
+                    let items: Vec<Object> = left.iter().chain(right.iter()).cloned().collect();
+                    Ok(Object::List(Rc::new(items)))
+                } else if matches!(left, Object::List(_)) || matches!(right, Object::List(_)) {
+                    Err(RuntimeError::at(
+                        "a list can only be added to another list",
+                        loc,
+                    ))
                 } else {
-                    Err(RuntimeError::new(
-                        "operand mismatch; second operand must be a number if the first one is",
-                        loc.get_line(),
-                        loc.get_column(),
+                    Err(RuntimeError::at(
+                        "operands must be two numbers, or a string and a string or number",
+                        loc,
                     ))
                 }
             }
             BinaryOp::Sub => {
                 if let (Object::Number(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::Number(left - right))
+                    Ok(Object::number(left - right))
                 } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
+                    Err(RuntimeError::at("operands must be numbers", loc))
                 }
             }
             BinaryOp::Mul => {
                 if let (Object::Number(left), Object::Number(right)) = (left.clone(), right.clone())
                 {
-                    Ok(Object::Number(left * right))
-                } else if let (Object::String(left), Object::Number(right)) = (left, right) {
+                    Ok(Object::number(left * right))
+                } else if let (Object::String(left), Object::Number(right)) =
+                    (left.clone(), right.clone())
+                {
                     // Raise a runtime error if the right operand is not an integer
                     if right.fract() != 0.0 {
-                        return Err(RuntimeError::new(
-                            "right operand must be an integer",
-                            loc.get_line(),
-                            loc.get_column(),
-                        ));
+                        return Err(RuntimeError::at("right operand must be an integer", loc));
                     }
 
                     let mut s = String::new();
@@ -172,209 +265,1874 @@ impl Visitor<Result<Object, RuntimeError>> for Interpreter {
                         s.push_str(&left);
                     }
                     Ok(Object::String(s))
+                } else if let (Object::List(left), Object::Number(right)) = (left, right) {
+                    if right.fract() != 0.0 {
+                        return Err(RuntimeError::at("right operand must be an integer", loc));
+                    }
+
+                    let mut items = Vec::new();
+                    for _ in 0..right as usize {
+                        items.extend(left.iter().cloned());
+                    }
+                    Ok(Object::List(Rc::new(items)))
                 } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
+                    Err(RuntimeError::at("operands must be numbers", loc))
                 }
             }
             BinaryOp::Div => {
                 if let (Object::Number(left), Object::Number(right)) = (left, right) {
                     if right == 0.0 {
+                        if self.strict_math {
+                            return Err(RuntimeError::at("division by zero", loc));
+                        }
                         Ok(Object::NaN)
                     } else {
-                        Ok(Object::Number(left / right))
+                        Ok(Object::number(left / right))
                     }
                 } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
-                }
-            }
-            BinaryOp::Eq => Ok(Object::Boolean(left.is_equal(&right))),
-            BinaryOp::Ne => Ok(Object::Boolean(left.is_not_equal(&right))),
-            BinaryOp::Lt => {
-                if let (Object::Number(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::Boolean(left < right))
-                } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
-                }
-            }
-            BinaryOp::Le => {
-                if let (Object::Number(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::Boolean(left <= right))
-                } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
+                    Err(RuntimeError::at("operands must be numbers", loc))
                 }
             }
-            BinaryOp::Gt => {
-                if let (Object::Number(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::Boolean(left > right))
-                } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
-                }
+            BinaryOp::Eq => Ok(Object::Boolean(left.is_equal_with(&right, self.nan_eq))),
+            BinaryOp::Ne => Ok(Object::Boolean(!left.is_equal_with(&right, self.nan_eq))),
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let ordering = left.compare(&right).ok_or_else(|| {
+                    RuntimeError::at(
+                        format!(
+                            "cannot compare {} with {}",
+                            left.type_name(),
+                            right.type_name()
+                        )
+                        .as_str(),
+                        loc,
+                    )
+                })?;
+                let result = match op {
+                    BinaryOp::Lt => ordering == Ordering::Less,
+                    BinaryOp::Le => ordering != Ordering::Greater,
+                    BinaryOp::Gt => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                };
+                Ok(Object::Boolean(result))
             }
-            BinaryOp::Ge => {
-                if let (Object::Number(left), Object::Number(right)) = (left, right) {
-                    Ok(Object::Boolean(left >= right))
-                } else {
-                    Err(RuntimeError::new(
-                        "operands must be numbers",
-                        loc.get_line(),
-                        loc.get_column(),
-                    ))
+            BinaryOp::In => match (&left, &right) {
+                (_, Object::List(items)) => Ok(Object::Boolean(
+                    items
+                        .iter()
+                        .any(|item| item.is_equal_with(&left, self.nan_eq)),
+                )),
+                (Object::String(needle), Object::String(haystack)) => {
+                    Ok(Object::Boolean(haystack.contains(needle.as_str())))
                 }
-            }
-            _ => Err(RuntimeError::new(
-                "binary operation expected",
-                loc.get_line(),
-                loc.get_column(),
-            )),
+                (_, Object::String(_)) => Err(RuntimeError::at(
+                    format!("cannot look for a {} in a string", left.type_name()).as_str(),
+                    loc,
+                )),
+                _ => Err(RuntimeError::at(
+                    format!("cannot look for anything in a {}", right.type_name()).as_str(),
+                    loc,
+                )),
+            },
+            _ => Err(RuntimeError::at("binary operation expected", loc)),
         }
     }
 
-    fn visit_print(
+    /**
+     * Evaluate the bounds of a `start..end` range, which must both be integers.
+     */
+    fn range_bounds(
         &mut self,
-        _loc: &dyn HasFileLocation,
-        expr: &Box<Expr>,
-    ) -> Result<Object, RuntimeError> {
-        let value = expr.accept(self)?;
-        print!("{}", value);
-        Ok(Object::Nil)
+        loc: &dyn HasFileLocation,
+        start: &Expr,
+        end: &Expr,
+    ) -> Result<(i64, i64), RuntimeError> {
+        self.trace("Range");
+        let start = start.accept(self)?;
+        let end = end.accept(self)?;
+        match (start, end) {
+            (Object::Number(start), Object::Number(end))
+                if start.fract() == 0.0 && end.fract() == 0.0 =>
+            {
+                Ok((start as i64, end as i64))
+            }
+            _ => Err(RuntimeError::at("range bounds must be integers", loc)),
+        }
     }
 
-    fn visit_if(
+    fn store_result(
         &mut self,
-        _loc: &dyn HasFileLocation,
-        cond: &Box<Expr>,
-        then: &Box<Expr>,
-        else_: &Option<Box<Expr>>,
-    ) -> Result<Object, RuntimeError> {
-        let cond = cond.accept(self)?;
-        if cond.is_truthy() {
-            then.accept(self)
-        } else if let Some(else_) = else_ {
-            else_.accept(self)
-        } else {
-            Ok(Object::Nil)
+        loc: &dyn HasFileLocation,
+        result: Object,
+    ) -> Result<(), RuntimeError> {
+        // `_` always lives in the global scope, even if a local `_` shadows it.
+        self.environments
+            .set_global(loc, Symbol::intern("_"), result)?;
+        Ok(())
+    }
+
+    pub fn eval(&mut self, expr: &Expr) -> Result<Object, RuntimeError> {
+        expr.accept(self)
+    }
+
+    /**
+     * Run each top-level statement of a program, returning the value of the last one.
+     */
+    pub fn execute(&mut self, program: &Vec<Stmt>) -> Result<Object, RuntimeError> {
+        Resolver::new().resolve(program)?;
+
+        let mut last = Object::Nil;
+        for stmt in program {
+            last = stmt.accept(self)?;
+            // Only top-level statements update `_`.
+            self.store_result(stmt.loc(), last.clone())?;
+        }
+        Ok(last)
+    }
+
+    /**
+     * Run the next top-level statement of `program`, handing control back to the caller between
+     * statements so a debugger can inspect the variables.
+     *
+     * The interpreter remembers where it got to, so pass the same program until `Done` comes back.
+     * A statement that fails still counts as run, so the next step moves on to the one after it.
+     */
+    pub fn step(&mut self, program: &Vec<Stmt>) -> Result<StepResult, RuntimeError> {
+        if self.step_cursor == 0 {
+            Resolver::new().resolve(program)?;
         }
+        let stmt = match program.get(self.step_cursor) {
+            Some(stmt) => stmt,
+            None => {
+                self.step_cursor = 0;
+                return Ok(StepResult::Done);
+            }
+        };
+        self.step_cursor += 1;
+
+        let value = stmt.accept(self)?;
+        self.store_result(stmt.loc(), value.clone())?;
+        let variables = self.variables();
+        Ok(StepResult::Stepped { value, variables })
     }
 
-    fn visit_let(
+    /**
+     * The variables that can be seen from where the interpreter is, sorted by name.  The built-in
+     * functions are left out.
+     */
+    pub fn variables(&self) -> Vec<(String, Object)> {
+        self.environments
+            .visible()
+            .into_iter()
+            .filter(|(_, value)| !matches!(value, Object::NativeFunction(_)))
+            .collect()
+    }
+
+    /**
+     * Scan, parse, and evaluate `source`, returning the final value.
+     *
+     * Variables defined by one call are still there for the next, the same as lines typed into the REPL.
+     * Whichever stage fails reports its problems as diagnostics.
+     */
+    pub fn eval_source(&mut self, source: &str) -> Result<Object, Vec<Diagnostic>> {
+        let tokens = scan_tokens(source).map_err(|err| vec![Diagnostic::from(err)])?;
+        let program = parse(&tokens).map_err(|errs| Diagnostic::from_set(Stage::Parser, &errs))?;
+        let value = self
+            .execute(&program)
+            .map_err(|err| vec![Diagnostic::from(err)])?;
+        Ok(value)
+    }
+
+    /**
+     * Call any callable value: a native, a Lox function, or a class.
+     *
+     * Natives that take a function, like `map`, use this to call back into Lox code.
+     */
+    pub fn call_value(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &String,
+        callee: Object,
+        args: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
-        self.environments.define(loc, name, Object::Nil)
+        match callee {
+            Object::NativeFunction(native) => native.call(self, loc, args),
+            Object::Function(function) => self.call_function(loc, &function, args),
+            Object::Class(class) => self.instantiate(loc, class, args),
+            _ => Err(RuntimeError::at("can only call functions", loc)),
+        }
     }
 
-    fn visit_let_init(
+    /**
+     * Run a function's body in a fresh scope holding its parameters, returning the value of the
+     * last statement.
+     */
+    fn call_function(
         &mut self,
         loc: &dyn HasFileLocation,
-        name: &String,
-        expr: &Box<Expr>,
+        function: &Function,
+        args: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
-        let value: Object = expr.accept(self)?;
-        self.environments.define(loc, &name, value)
+        if !function.accepts(args.len()) {
+            return Err(RuntimeError::at(
+                format!(
+                    "{} expects {} argument(s) but got {}",
+                    function.decl.name,
+                    function.describe_arity(),
+                    args.len()
+                )
+                .as_str(),
+                loc,
+            ));
+        }
+
+        let saved = self.environments.enter_function(&function.closure);
+        let result = self.run_function_body(function, args);
+        self.environments.leave_function(saved);
+
+        match result {
+            // A stray `break` mustn't escape into a loop in the caller.  The error still points at
+            // the `break` itself.
+            Err(e)
+                if matches!(
+                    e.interrupt,
+                    Some(Interrupt::Break(_) | Interrupt::Continue(_))
+                ) =>
+            {
+                Err(RuntimeError::at(e.msg.as_str(), &e))
+            }
+            result => result,
+        }
     }
 
-    fn visit_assign(
+    fn run_function_body(
         &mut self,
-        loc: &dyn HasFileLocation,
-        name: &String,
-        expr: &Box<Expr>,
+        function: &Function,
+        args: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
-        let value = expr.accept(self)?;
-        self.environments.assign(loc, name, value)
+        let decl = &function.decl;
+        if let Some(this) = &function.this {
+            self.environments
+                .define(&decl.loc, Symbol::intern("this"), this.as_ref().clone())?;
+        }
+        if let Some(superclass) = &function.superclass {
+            let superclass = Object::Class(superclass.clone());
+            self.environments
+                .define(&decl.loc, Symbol::intern("super"), superclass)?;
+        }
+        // Defaults are evaluated in the function's scope, so they can refer to earlier parameters.
+        let mut args = args.into_iter();
+        for (param, default) in decl.params.iter().zip(&decl.defaults) {
+            let value = match (args.next(), default) {
+                (Some(arg), _) => arg,
+                (None, Some(default)) => default.accept(self)?,
+                (None, None) => unreachable!("the arity was checked by the caller"),
+            };
+            self.environments.define(&decl.loc, *param, value)?;
+        }
+        if let Some(rest) = &decl.rest {
+            let rest_args = Object::List(Rc::new(args.collect()));
+            self.environments.define(&decl.loc, *rest, rest_args)?;
+        }
+        self.eval_sequence(&decl.body)
     }
 
-    fn visit_variable(
-        &mut self,
+    /**
+     * Check that `value` can be used as a position in a list or string: a non-negative integer.
+     */
+    fn expect_position(
+        &self,
         loc: &dyn HasFileLocation,
-        name: &String,
-    ) -> Result<Object, RuntimeError> {
-        self.environments.get(loc, name)
+        value: &Object,
+        what: &str,
+    ) -> Result<usize, RuntimeError> {
+        match value {
+            Object::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+            _ => Err(RuntimeError::at(
+                format!("{} must be a non-negative integer", what).as_str(),
+                loc,
+            )),
+        }
     }
 
-    fn visit_program(
+    /**
+     * Create an instance of `class`, passing `args` to its `init` method if it has one.
+     */
+    fn instantiate(
         &mut self,
         loc: &dyn HasFileLocation,
-        exprs: &Vec<Expr>,
+        class: Rc<Class>,
+        args: Vec<Object>,
     ) -> Result<Object, RuntimeError> {
-        let mut last = Object::Nil;
-        for expr in exprs {
-            last = expr.accept(self)?;
-            self.store_result(loc, last.clone())?;
+        let instance = Object::Instance(Rc::new(RefCell::new(Instance::new(class.clone()))));
+        match class.find_method("init") {
+            Some(init) => {
+                let init = init.bind(instance.clone());
+                self.call_function(loc, &init, args)?;
+            }
+            None if !args.is_empty() => {
+                return Err(RuntimeError::at(
+                    format!(
+                        "{} expects 0 argument(s) but got {}",
+                        class.name,
+                        args.len()
+                    )
+                    .as_str(),
+                    loc,
+                ));
+            }
+            None => {}
         }
-        Ok(last)
+        Ok(instance)
     }
 
-    fn visit_block(
-        &mut self,
+    /**
+     * Read a field from an instance, falling back to its class's methods.
+     */
+    fn get_property(
+        &self,
         loc: &dyn HasFileLocation,
-        exprs: &Vec<Expr>,
+        object: &Object,
+        name: &str,
     ) -> Result<Object, RuntimeError> {
-        self.environments.enter_scope();
+        let instance = match object {
+            Object::Instance(instance) => instance.borrow(),
+            _ => {
+                return Err(RuntimeError::at(
+                    format!("{} has no property '{}'", object.type_name(), name).as_str(),
+                    loc,
+                ))
+            }
+        };
+        if let Some(value) = instance.get_field(name) {
+            return Ok(value);
+        }
+        match instance.class.find_method(name) {
+            Some(method) => Ok(Object::Function(method.bind(object.clone()))),
+            None => Err(RuntimeError::at(
+                format!("undefined property '{}'", name).as_str(),
+                loc,
+            )),
+        }
+    }
+
+    /**
+     * Evaluate each expression in turn, returning the value of the last one.
+     */
+    fn eval_sequence(&mut self, stmts: &[Stmt]) -> Result<Object, RuntimeError> {
         let mut last = Object::Nil;
-        for expr in exprs {
-            last = expr.accept(self)?;
-            self.store_result(loc, last.clone())?;
+        for stmt in stmts {
+            last = stmt.accept(self)?;
         }
-        self.environments.leave_scope(loc)?;
         Ok(last)
     }
+}
 
-    fn visit_while(
+impl HasStopFlag for Interpreter {
+    fn trigger_stop(&mut self) {
+        self.stop_flag.store(true, AtomicOrdering::Relaxed);
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop_flag.load(AtomicOrdering::Relaxed)
+    }
+}
+
+impl Visitor<Result<Object, RuntimeError>> for Interpreter {
+    fn visit_number(
         &mut self,
-        loc: &dyn HasFileLocation,
-        cond: &Box<Expr>,
-        body: &Box<Expr>,
+        _loc: &dyn HasFileLocation,
+        n: &f64,
     ) -> Result<Object, RuntimeError> {
-        let mut last = Object::Nil;
-        // The `cond`-ition needs to be re-accepted / re-evaluated at the end of each iteration.
-        while cond.accept(self)?.is_truthy() {
-            match body.accept(self) {
-                Ok(value) => last = value,
-                Err(e) => {
-                    if let Some(int) = e.interrupt {
-                        match int {
-                            Interrupt::Break => break,
-                            Interrupt::Continue => continue,
-                        }
-                    } else {
-                        return Err(e);
-                    }
-                }
-            }
+        self.trace("Number");
+        Ok(Object::Number(*n))
+    }
 
-            self.store_result(loc, last.clone())?;
-        }
+    fn visit_string(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        s: &String,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("String");
+        Ok(Object::String(s.clone()))
+    }
 
-        // Return the final result.
-        Ok(last)
+    fn visit_boolean(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        b: &bool,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Boolean");
+        Ok(Object::Boolean(*b))
+    }
+
+    fn visit_nil(&mut self, _loc: &dyn HasFileLocation) -> Result<Object, RuntimeError> {
+        self.trace("Nil");
+        Ok(Object::Nil)
+    }
+
+    fn visit_call(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        callee: &Expr,
+        args: &[Expr],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Call");
+        let callee = callee.accept(self)?;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(arg.accept(self)?);
+        }
+
+        self.call_value(loc, callee, values)
+    }
+
+    fn visit_method_call(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("MethodCall");
+        let receiver = receiver.accept(self)?;
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(arg.accept(self)?);
+        }
+
+        if let Object::Instance(_) = receiver {
+            let method = self.get_property(loc, &receiver, name)?;
+            return self.call_value(loc, method, values);
+        }
+
+        match find_method(&receiver, name) {
+            Some(method) => method.call_method(self, loc, receiver, values),
+            None => Err(RuntimeError::at(
+                format!("{} has no method '{}'", receiver.type_name(), name).as_str(),
+                loc,
+            )),
+        }
+    }
+
+    fn visit_get(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Get");
+        let object = object.accept(self)?;
+        self.get_property(loc, &object, name)
+    }
+
+    fn visit_index(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        index: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Index");
+        let object = object.accept(self)?;
+        let index = index.accept(self)?;
+        let i = self.expect_position(loc, &index, "index")?;
+        let item = match &object {
+            Object::List(items) => items.get(i).cloned(),
+            Object::String(s) => s.chars().nth(i).map(|c| Object::String(c.to_string())),
+            Object::Bytes(bytes) => bytes.get(i).map(|byte| Object::Number(*byte as f64)),
+            _ => {
+                return Err(RuntimeError::at(
+                    format!("cannot index into a {}", object.type_name()).as_str(),
+                    loc,
+                ))
+            }
+        };
+        item.ok_or_else(|| {
+            RuntimeError::at(
+                format!("index {} is out of range", format_number(i as f64)).as_str(),
+                loc,
+            )
+        })
+    }
+
+    fn visit_slice(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Slice");
+        let object = object.accept(self)?;
+        let len = match &object {
+            Object::List(items) => items.len(),
+            Object::String(s) => s.chars().count(),
+            Object::Bytes(bytes) => bytes.len(),
+            _ => {
+                return Err(RuntimeError::at(
+                    format!("cannot slice a {}", object.type_name()).as_str(),
+                    loc,
+                ))
+            }
+        };
+
+        // Bounds past the end are clamped, like Python, so `s[0:100]` is the whole string.
+        let start = match start {
+            Some(start) => {
+                let start = start.accept(self)?;
+                self.expect_position(loc, &start, "slice start")?.min(len)
+            }
+            None => 0,
+        };
+        let end = match end {
+            Some(end) => {
+                let end = end.accept(self)?;
+                self.expect_position(loc, &end, "slice end")?.min(len)
+            }
+            None => len,
+        };
+        let end = end.max(start);
+
+        match &object {
+            Object::List(items) => Ok(Object::List(Rc::new(items[start..end].to_vec()))),
+            Object::String(s) => Ok(Object::String(
+                s.chars().skip(start).take(end - start).collect(),
+            )),
+            Object::Bytes(bytes) => Ok(Object::Bytes(Rc::new(bytes[start..end].to_vec()))),
+            _ => unreachable!("only lists, strings, and bytes get this far"),
+        }
+    }
+
+    fn visit_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Set");
+        let object = object.accept(self)?;
+        let instance = match object {
+            Object::Instance(instance) => instance,
+            _ => {
+                return Err(RuntimeError::at(
+                    format!("cannot set a property on a {}", object.type_name()).as_str(),
+                    loc,
+                ))
+            }
+        };
+        let value = value.accept(self)?;
+        instance.borrow_mut().set_field(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("CompoundSet");
+        let object = object.accept(self)?;
+        let instance = match &object {
+            Object::Instance(instance) => instance.clone(),
+            _ => {
+                return Err(RuntimeError::at(
+                    format!("cannot set a property on a {}", object.type_name()).as_str(),
+                    loc,
+                ))
+            }
+        };
+        let current = self.get_property(loc, &object, name)?;
+        let value = value.accept(self)?;
+        let value = self.apply_binary_op(loc, op, current, value)?;
+        instance.borrow_mut().set_field(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_list(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        items: &[Expr],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("List");
+        let mut values = Vec::with_capacity(items.len());
+        for item in items {
+            values.push(item.accept(self)?);
+        }
+        Ok(Object::List(Rc::new(values)))
+    }
+
+    fn visit_interpolation(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        parts: &[Expr],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Interpolation");
+        let mut s = String::new();
+        for part in parts {
+            s.push_str(&part.accept(self)?.to_string());
+        }
+        Ok(Object::String(s))
+    }
+
+    fn visit_range(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        start: &Expr,
+        end: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        let (start, end) = self.range_bounds(loc, start, end)?;
+        if start.abs_diff(end) > MAX_RANGE_LIST {
+            return Err(RuntimeError::at(
+                "range is too large to build as a list; loop over it with for-in instead",
+                loc,
+            ));
+        }
+        Ok(Object::List(Rc::new(range_numbers(start, end).collect())))
+    }
+
+    fn visit_grouping(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        e: &Box<Expr>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Grouping");
+        e.accept(self)
+    }
+
+    fn visit_unary_op(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        op: &UnaryOp,
+        e: &Box<Expr>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("UnaryOp");
+        let e = e.accept(self)?;
+
+        match op {
+            UnaryOp::Neg => {
+                if let Object::Number(n) = e {
+                    Ok(Object::Number(-n))
+                } else {
+                    Err(RuntimeError::at("operand must be a number", loc))
+                }
+            }
+            UnaryOp::Pos => {
+                if let Object::Number(_) = e {
+                    Ok(e)
+                } else {
+                    Err(RuntimeError::at("operand must be a number", loc))
+                }
+            }
+            UnaryOp::Not => Ok(Object::Boolean(e.is_falsy())),
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Ternary");
+        if cond.accept(self)?.is_truthy() {
+            then.accept(self)
+        } else {
+            else_.accept(self)
+        }
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        op: &BinaryOp,
+        e1: &Box<Expr>,
+        e2: &Box<Expr>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("BinaryOp");
+        let left = e1.accept(self)?;
+
+        match op {
+            BinaryOp::LogicalAnd => {
+                if !left.is_truthy() {
+                    return Ok(left);
+                }
+                return Ok(e2.accept(self)?);
+            }
+            BinaryOp::LogicalOr => {
+                if left.is_truthy() {
+                    return Ok(left);
+                }
+                return Ok(e2.accept(self)?);
+            }
+            _ => {}
+        }
+
+        let right = e2.accept(self)?;
+        self.apply_binary_op(loc, op, left, right)
+    }
+
+    fn visit_assign(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        depth: &Cell<Option<usize>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Assign");
+        let value = expr.accept(self)?;
+        match depth.get() {
+            Some(depth) => self.environments.assign_at(loc, depth, *name, value),
+            None => self.environments.assign_global(loc, *name, value),
+        }
+    }
+
+    fn visit_variable(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        depth: &Cell<Option<usize>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Variable");
+        match depth.get() {
+            Some(depth) => self.environments.get_at(loc, depth, *name),
+            None => self.environments.get_global(loc, *name),
+        }
+    }
+
+    fn visit_super(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        method: &str,
+        depth: &Cell<Option<usize>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Super");
+        let depth = match depth.get() {
+            Some(depth) => depth,
+            None => {
+                return Err(RuntimeError::at(
+                    "cannot use 'super' outside of a method",
+                    loc,
+                ))
+            }
+        };
+        let superclass = match self
+            .environments
+            .get_at(loc, depth, Symbol::intern("super"))?
+        {
+            Object::Class(class) => class,
+            _ => return Err(RuntimeError::at("superclass must be a class", loc)),
+        };
+        let this = self
+            .environments
+            .get_at(loc, depth, Symbol::intern("this"))?;
+        match superclass.find_method(method) {
+            Some(function) => Ok(Object::Function(function.bind(this))),
+            None => Err(RuntimeError::at(
+                format!("undefined property '{}'", method).as_str(),
+                loc,
+            )),
+        }
+    }
+
+    fn visit_lambda(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        decl: &Rc<FunctionDecl>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Lambda");
+        let closure = self.environments.capture();
+        Ok(Object::Function(Function::lambda(decl.clone(), closure)))
+    }
+
+    fn visit_loop_expr(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        stmt: &Stmt,
+    ) -> Result<Object, RuntimeError> {
+        stmt.accept(self)
+    }
+
+    fn visit_this(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        depth: &Cell<Option<usize>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("This");
+        match depth.get() {
+            Some(depth) => self.environments.get_at(loc, depth, Symbol::intern("this")),
+            None => Err(RuntimeError::at(
+                "cannot use 'this' outside of a method",
+                loc,
+            )),
+        }
+    }
+}
+
+impl StmtVisitor<Result<Object, RuntimeError>> for Interpreter {
+    fn visit_expr_stmt(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        expr: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("ExprStmt");
+        expr.accept(self)
+    }
+
+    fn visit_print(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        expr: &Expr,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Print");
+        let value = expr.accept(self)?;
+        write!(self.output, "{}", value)
+            .map_err(|e| RuntimeError::at(format!("print failed: {}", e).as_str(), loc))?;
+        Ok(Object::Nil)
+    }
+
+    fn visit_if(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("If");
+        let cond = cond.accept(self)?;
+        if cond.is_truthy() {
+            then.accept(self)
+        } else if let Some(else_) = else_ {
+            else_.accept(self)
+        } else {
+            Ok(Object::Nil)
+        }
+    }
+
+    fn visit_let(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        init: &Option<Box<Expr>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Let");
+        let value = match init {
+            Some(init) => init.accept(self)?,
+            None => Object::Nil,
+        };
+        self.environments.define(loc, *name, value)
+    }
+
+    fn visit_delete(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Delete");
+        self.environments.delete(loc, *name)
+    }
+
+    fn visit_block(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        stmts: &[Stmt],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Block");
+        self.environments.enter_scope();
+        let result = self.eval_sequence(stmts);
+        // Leave the scope even if the block failed, or the next evaluation would run inside it.
+        self.environments.leave_scope(loc)?;
+        result
+    }
+
+    fn visit_while(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("While");
+        let mut last = Object::Nil;
+        // The `cond`-ition needs to be re-accepted / re-evaluated at the end of each iteration.
+        while cond.accept(self)?.is_truthy() {
+            match body.accept(self) {
+                Ok(value) => last = value,
+                Err(e) => match e.interrupt {
+                    Some(Interrupt::Break(target)) if stops_at(target, *label) => {
+                        // A bare `break` leaves the value of the last full iteration.
+                        if let Some(value) = self.break_value.take() {
+                            last = value;
+                        }
+                        break;
+                    }
+                    Some(Interrupt::Continue(target)) if stops_at(target, *label) => {}
+                    _ => return Err(e),
+                },
+            }
+            if let Some(increment) = increment {
+                increment.accept(self)?;
+            }
+        }
+
+        // Return the final result.
+        Ok(last)
+    }
+
+    fn visit_loop(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        body: &Stmt,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Loop");
+        loop {
+            if let Err(e) = body.accept(self) {
+                match e.interrupt {
+                    // The loop only ends by breaking out of it, so its value is whatever the
+                    // `break` gave.
+                    Some(Interrupt::Break(target)) if stops_at(target, *label) => {
+                        return Ok(self.break_value.take().unwrap_or(Object::Nil))
+                    }
+                    Some(Interrupt::Continue(target)) if stops_at(target, *label) => continue,
+                    _ => return Err(e),
+                }
+            }
+        }
+    }
+
+    fn visit_for_in(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("ForIn");
+        let items: Box<dyn Iterator<Item = Object>> = match iterable {
+            // A range is counted off as the loop goes, rather than built as a list first.
+            Expr::Range(range_loc, start, end) => {
+                let (start, end) = self.range_bounds(range_loc, start, end)?;
+                Box::new(range_numbers(start, end))
+            }
+            _ => match iterable.accept(self)? {
+                Object::List(items) => Box::new((0..items.len()).map(move |i| items[i].clone())),
+                Object::String(s) => Box::new(
+                    s.chars()
+                        .map(|c| Object::String(c.to_string()))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                ),
+                Object::Bytes(bytes) => {
+                    Box::new((0..bytes.len()).map(move |i| Object::Number(bytes[i] as f64)))
+                }
+                _ => {
+                    return Err(RuntimeError::at(
+                        "can only iterate over a list, a string, or bytes",
+                        loc,
+                    ))
+                }
+            },
+        };
+
+        let mut last = Object::Nil;
+        for item in items {
+            // Each iteration gets a fresh scope holding the loop variable.
+            self.environments.enter_scope();
+            let result = self
+                .environments
+                .define(loc, *name, item)
+                .and_then(|_| body.accept(self));
+            self.environments.leave_scope(loc)?;
+
+            match result {
+                Ok(value) => last = value,
+                Err(e) => match e.interrupt {
+                    Some(Interrupt::Break(target)) if stops_at(target, *label) => {
+                        if let Some(value) = self.break_value.take() {
+                            last = value;
+                        }
+                        break;
+                    }
+                    Some(Interrupt::Continue(target)) if stops_at(target, *label) => continue,
+                    _ => return Err(e),
+                },
+            }
+        }
+
+        Ok(last)
+    }
+
+    fn visit_class(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Class");
+        let superclass = match superclass {
+            Some(expr) => match expr.accept(self)? {
+                Object::Class(class) => Some(class),
+                _ => return Err(RuntimeError::at("superclass must be a class", loc)),
+            },
+            None => None,
+        };
+        let class = Object::Class(Rc::new(Class::new(name, superclass, methods)));
+        self.environments.define(loc, Symbol::intern(name), class)
+    }
+
+    fn visit_function_stmt(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        decl: &Rc<FunctionDecl>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Function");
+        // The closure shares the current scope, so the function can see itself once it's defined.
+        let closure = self.environments.capture();
+        let function = Object::Function(Function::lambda(decl.clone(), closure));
+        self.environments
+            .define(loc, Symbol::intern(&decl.name), function)
+    }
+
+    fn visit_break(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Break");
+        // Always overwritten, so a value left by a stray `break` can't leak into a later loop.
+        self.break_value = match value {
+            Some(value) => Some(value.accept(self)?),
+            None => None,
+        };
+        Err(RuntimeError::break_loop(loc, *label))
+    }
+
+    fn visit_continue(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+    ) -> Result<Object, RuntimeError> {
+        self.trace("Continue");
+        Err(RuntimeError::continue_loop(loc, *label))
+    }
+}
+
+/**
+ * The most numbers a range may hold when it's built as a list.
+ */
+const MAX_RANGE_LIST: u64 = 1 << 24;
+
+/**
+ * The numbers in `start..end`.  The upper bound is exclusive; a descending range counts down
+ * towards it.
+ */
+fn range_numbers(start: i64, end: i64) -> impl Iterator<Item = Object> {
+    let step = if start <= end { 1 } else { -1 };
+    (0..start.abs_diff(end)).map(move |i| Object::Number((start + step * i as i64) as f64))
+}
+
+/**
+ * Whether a `break` or `continue` aimed at `target` stops at a loop with `label`.  One without a
+ * target stops at the innermost loop.
+ */
+fn stops_at(target: Option<Symbol>, label: Option<Symbol>) -> bool {
+    target.is_none() || target == label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{debug::LocatableError, interpreter::OutputBuffer};
+
+    /**
+     * An interpreter whose output is captured, so tests don't print.
+     */
+    fn quiet() -> (Interpreter, OutputBuffer) {
+        let output = OutputBuffer::new();
+        let interpreter = Interpreter::new().with_output(Box::new(output.clone()));
+        (interpreter, output)
+    }
+
+    /**
+     * The value `source` ends with, as the REPL would show it.
+     */
+    fn value(source: &str) -> String {
+        let (mut interpreter, _) = quiet();
+        match interpreter.eval_source(source) {
+            Ok(value) => value.to_string(),
+            Err(errors) => panic!("{} failed: {}", source, errors[0].msg),
+        }
+    }
+
+    /**
+     * Everything `source` prints.
+     */
+    fn printed(source: &str) -> String {
+        let (mut interpreter, output) = quiet();
+        if let Err(errors) = interpreter.eval_source(source) {
+            panic!("{} failed: {}", source, errors[0].msg);
+        }
+        output.contents()
+    }
+
+    /**
+     * The message of the first error `source` raises.
+     */
+    fn error(source: &str) -> String {
+        let (mut interpreter, _) = quiet();
+        match interpreter.eval_source(source) {
+            Ok(value) => panic!("{} gave {} instead of failing", source, value),
+            Err(errors) => errors[0].msg.clone(),
+        }
+    }
+
+    fn has_variable(interpreter: &Interpreter, name: &str) -> bool {
+        interpreter
+            .variables()
+            .iter()
+            .any(|(variable, _)| variable == name)
+    }
+
+    #[test]
+    fn block_scope_is_left_when_the_body_errors() {
+        let (mut interpreter, _) = quiet();
+        assert!(interpreter
+            .eval_source("{ let inner = 1; nil + 1; }")
+            .is_err());
+
+        // Had the block's scope been left on the stack, `after` would be defined in it.
+        interpreter.eval_source("let after = 2;").unwrap();
+        assert!(has_variable(&interpreter, "after"));
+        assert!(!has_variable(&interpreter, "inner"));
+    }
+
+    #[test]
+    fn division_by_zero_is_nan_by_default() {
+        let (mut interpreter, _) = quiet();
+        assert!(matches!(interpreter.eval_source("1 / 0;"), Ok(Object::NaN)));
+    }
+
+    #[test]
+    fn strict_math_reports_division_by_zero_where_it_happened() {
+        let (interpreter, _) = quiet();
+        let mut interpreter = interpreter.with_strict_math(true);
+        let errors = interpreter
+            .eval_source("let a = 1;\nprint a / 0;")
+            .unwrap_err();
+        assert_eq!(errors[0].msg, "division by zero");
+        assert_eq!(
+            (errors[0].loc.get_line(), errors[0].loc.get_column()),
+            (2, 9)
+        );
+    }
+
+    #[test]
+    fn errors_are_reported_at_the_failing_statement() {
+        let source = "let a = 1;\n\n\nprint a + missing;\nprint a;";
+        let (mut interpreter, _) = quiet();
+        let errors = interpreter.eval_source(source).unwrap_err();
+        assert_eq!(errors[0].loc.get_line(), 4);
+        assert!(errors[0]
+            .format_report(source)
+            .contains("4 | print a + missing;"));
+    }
+
+    #[test]
+    fn instances_hold_fields_and_call_methods() {
+        let (mut interpreter, output) = quiet();
+        let value = interpreter
+            .eval_source(
+                "class Point {
+                    init(x, y) { this.x = x; this.y = y; }
+                    sum() { this.x + this.y }
+                }
+                let p = Point(1, 2);
+                p.y = 5;
+                print p.x;
+                p.sum()",
+            )
+            .unwrap();
+        assert_eq!(output.contents(), "1");
+        assert!(matches!(value, Object::Number(n) if n == 6.0));
+    }
+
+    #[test]
+    fn defaults_fill_in_missing_arguments() {
+        let (mut interpreter, output) = quiet();
+        interpreter
+            .eval_source("fun f(a, b = 10) { a + b } print f(1); print \" \"; print f(1, 2);")
+            .unwrap();
+        assert_eq!(output.contents(), "11 3");
+    }
+
+    #[test]
+    fn named_functions_can_call_themselves_in_a_block() {
+        let (mut interpreter, _) = quiet();
+        let value = interpreter
+            .eval_source("{ fun fact(n) { n <= 1 ? 1 : n * fact(n - 1) } fact(5) }")
+            .unwrap();
+        assert!(matches!(value, Object::Number(n) if n == 120.0));
+    }
+
+    fn equalities(interpreter: Interpreter) -> Vec<bool> {
+        let mut interpreter = interpreter;
+        ["let n = 0 / 0; n == n", "nil == nil", "1 == \"1\""]
+            .iter()
+            .map(|source| match interpreter.eval_source(source) {
+                Ok(Object::Boolean(b)) => b,
+                other => panic!("{} gave {:?}", source, other.map_err(|_| ())),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nan_is_not_equal_to_itself_by_default() {
+        let (interpreter, _) = quiet();
+        assert_eq!(equalities(interpreter), vec![false, true, false]);
+    }
+
+    #[test]
+    fn nan_eq_makes_nan_equal_to_itself() {
+        let (interpreter, _) = quiet();
+        assert_eq!(
+            equalities(interpreter.with_nan_eq(true)),
+            vec![true, true, false]
+        );
+    }
+
+    #[test]
+    fn print_writes_to_the_output_sink() {
+        let (mut interpreter, output) = quiet();
+        interpreter
+            .eval_source("print \"a\"; print 1 + 2; print [nil];")
+            .unwrap();
+        assert_eq!(output.contents(), "a3[nil]");
+    }
+
+    #[test]
+    fn loops_give_the_value_they_break_with() {
+        assert_eq!(value("let x = loop { break 42; }; x;"), "42");
+        assert_eq!(
+            value("let i = 0; let y = loop { i += 1; if (i == 3) break; }; y;"),
+            "nil"
+        );
+        assert_eq!(
+            value("let z = 'outer: loop { loop { break 'outer \"out\"; } } + \"!\"; z;"),
+            "out!"
+        );
+        assert_eq!(
+            value("let n = 0; while (n < 5) { n += 1; if (n == 2) break n * 10; }"),
+            "20"
+        );
+        assert_eq!(
+            value("let m = 0; while (m < 5) { m += 1; if (m == 2) break; m; }"),
+            "1"
+        );
+    }
+
+    #[test]
+    fn loop_repeats_until_break() {
+        assert_eq!(
+            printed("let i = 0; loop { i += 1; if (i > 3) { break; } print i; }"),
+            "123"
+        );
+        // `continue` skips the rest of the body but keeps looping.
+        assert_eq!(
+            printed("let i = 0; loop { i += 1; if (i == 2) { continue; } if (i > 3) { break; } print i; }"),
+            "13"
+        );
+    }
+
+    #[test]
+    fn for_in_visits_each_item() {
+        assert_eq!(printed("for (x in [1, \"two\", 3]) print x;"), "1two3");
+        assert_eq!(printed("for (c in \"abc\") print c;"), "abc");
+        assert_eq!(printed("for (x in []) print x;"), "");
+        assert_eq!(
+            error("for (x in 5) print x;"),
+            "can only iterate over a list, a string, or bytes"
+        );
+    }
+
+    #[test]
+    fn strings_interpolate_expressions() {
+        assert_eq!(
+            value("let a = 2; \"a=${a}, next=${a + 1}, ${\"in${a}\"}\";"),
+            "a=2, next=3, in2"
+        );
+        // Braces inside the expression don't end it early.
+        assert_eq!(value("\"v: ${ fun () { 5 }() }!\";"), "v: 5!");
+        // Without the `$`, braces are just text.
+        assert_eq!(value("\"{}\";"), "{}");
+        assert_eq!(error("\"a ${1 + \";"), "unterminated string");
+    }
+
+    #[test]
+    fn compound_assignment_updates_in_place() {
+        assert_eq!(value("let a = 10; a += 5; a -= 3; a *= 2; a /= 4; a;"), "6");
+        assert_eq!(value("let s = \"a\"; s += \"b\"; s;"), "ab");
+        assert_eq!(
+            value("class Box { init() { this.v = 1; } } let b = Box(); b.v += 2; b.v;"),
+            "3"
+        );
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_the_object_once() {
+        let source = "
+            class Counter { init() { this.x = 0; } }
+            let counter = Counter();
+            let calls = 0;
+            fun obj() { calls += 1; counter }
+            obj().x += 1;
+            [calls, counter.x];";
+        assert_eq!(value(source), "[1, 1]");
+    }
+
+    #[test]
+    fn unary_plus_only_takes_numbers() {
+        assert_eq!(value("+3;"), "3");
+        assert_eq!(value("-+-2;"), "2");
+        assert_eq!(error("+\"a\";"), "operand must be a number");
+    }
+
+    #[test]
+    fn ranges_count_towards_their_exclusive_end() {
+        assert_eq!(value("0..4"), "[0, 1, 2, 3]");
+        assert_eq!(value("3..0"), "[3, 2, 1]");
+        assert_eq!(value("2..2"), "[]");
+        assert_eq!(error("0..1.5"), "range bounds must be integers");
+    }
+
+    #[test]
+    fn huge_ranges_are_only_counted_off_in_loops() {
+        // Looping over a range never builds it, so it can end early without running out of memory.
+        assert_eq!(
+            value("let n = 0; for (i in 0..1_000_000_000_000) { n += 1; if (i == 99) break; } n;"),
+            "100"
+        );
+        assert_eq!(
+            error("let r = 0..1_000_000_000_000;"),
+            "range is too large to build as a list; loop over it with for-in instead"
+        );
+    }
+
+    #[test]
+    fn strings_have_methods_and_matching_builtins() {
+        assert_eq!(
+            value("let s = \"Hello\"; [s.len(), s.upper(), s.lower(), s.substring(1, 3)];"),
+            "[5, \"HELLO\", \"hello\", \"el\"]"
+        );
+        assert_eq!(
+            value("[len(\"abc\"), upper(\"a\"), lower(\"B\")];"),
+            "[3, \"A\", \"b\"]"
+        );
+        assert_eq!(
+            error("\"ab\".substring(1, 9);"),
+            "substring range 1..9 is out of bounds for a string of length 2"
+        );
+    }
+
+    #[test]
+    fn type_names_each_kind_of_value() {
+        assert_eq!(
+            value("[type(1), type(\"a\"), type(nil), type(true), type([]), type(fun () {}), type(type)];"),
+            "[\"number\", \"string\", \"nil\", \"boolean\", \"list\", \"function\", \"function\"]"
+        );
+    }
+
+    #[test]
+    fn number_and_string_convert_explicitly() {
+        assert_eq!(
+            value("[number(\"3.5\"), number(\" 7 \"), number(true), number(false), number(4)];"),
+            "[3.5, 7, 1, 0, 4]"
+        );
+        assert_eq!(
+            value("[string(12), string(2.5), string(nil), string(\"s\")];"),
+            "[\"12\", \"2.5\", \"nil\", \"s\"]"
+        );
+        assert_eq!(error("number(\"x\");"), "cannot convert \"x\" to a number");
+        assert_eq!(error("number([]);"), "cannot convert a list to a number");
+    }
+
+    #[test]
+    fn only_top_level_statements_update_the_result() {
+        // The statements inside `f` don't overwrite `_`, so it still holds the `5`.
+        assert_eq!(value("fun f() { 6; _ } 5; f();"), "5");
+        // A local `_` is left alone.
+        assert_eq!(
+            value("let seen = nil; { let _ = \"local\"; 9; seen = _; } seen;"),
+            "local"
+        );
+    }
+
+    #[test]
+    fn overflow_gives_infinity() {
+        let big = "let big = 1; for (i in 0..400) { big *= 10; }";
+        assert_eq!(
+            value(&format!("{} [big, -big];", big)),
+            "[Infinity, -Infinity]"
+        );
+        assert_eq!(
+            value(&format!("{} [big > 1, -big < 0, big == big, !!big];", big)),
+            "[true, true, true, true]"
+        );
+        // Infinity minus itself has no value, so it's NaN.
+        assert_eq!(value(&format!("{} type(big - big);", big)), "nan");
+    }
+
+    #[test]
+    fn assert_fails_with_its_message() {
+        assert_eq!(
+            printed("assert(1 < 2); assert(true, \"fine\"); print \"ok\";"),
+            "ok"
+        );
+        assert_eq!(error("assert(1 > 2);"), "assertion failed");
+        assert_eq!(
+            error("assert(1 > 2, \"one is not more than two\");"),
+            "assertion failed: one is not more than two"
+        );
+    }
+
+    #[test]
+    fn delete_removes_a_variable() {
+        let (mut interpreter, _) = quiet();
+        interpreter
+            .eval_source("let gone = 1; delete gone;")
+            .unwrap();
+        assert!(!has_variable(&interpreter, "gone"));
+        assert_eq!(error("let a = 1; delete a; a;"), "undefined variable: a");
+        assert_eq!(error("delete nope;"), "undefined variable: nope");
+
+        // Deleting a local uncovers the variable it shadowed.
+        assert_eq!(printed("let a = 1; { let a = 2; delete a; print a; }"), "1");
+    }
+
+    #[test]
+    fn ternary_picks_one_branch() {
+        assert_eq!(
+            value("[1 > 2 ? \"a\" : \"b\", true ? false ? 1 : 2 : 3, nil ? 1 : 0];"),
+            "[\"b\", 2, 0]"
+        );
+        // Only the chosen branch is evaluated.
+        assert_eq!(
+            printed("fun say(s) { print s; } true ? say(\"yes\") : say(\"no\");"),
+            "yes"
+        );
+    }
+
+    #[test]
+    fn methods_dispatch_on_the_receiver_type() {
+        assert_eq!(
+            value("[[1, 2, 3].len(), \"ab\".len(), [\"a\", \"b\"].join(\"-\")];"),
+            "[3, 2, \"a-b\"]"
+        );
+        assert_eq!(value("\"a,b\".split(\",\");"), "[\"a\", \"b\"]");
+        assert_eq!(error("[1, 2].upper();"), "list has no method 'upper'");
+        assert_eq!(error("5.len();"), "number has no method 'len'");
+        assert_eq!(
+            error("\"ab\".len(1);"),
+            "len expects 0 argument(s) but got 1"
+        );
+    }
+
+    #[test]
+    fn classes_make_instances() {
+        assert_eq!(
+            value("class A {} let a = A(); [A, a, type(A), type(a)];"),
+            "[<class A>, <A instance>, \"class\", \"instance\"]"
+        );
+        assert_eq!(
+            error("class A { init(x) {} } A();"),
+            "init expects 1 argument(s) but got 0"
+        );
+        assert_eq!(error("class A {} A().nope;"), "undefined property 'nope'");
+    }
+
+    #[test]
+    fn methods_keep_this_when_taken_off_the_instance() {
+        let source = "
+            class Counter {
+                init(n) { this.n = n; }
+                get() { this.n }
+                bump() { this.n += 1; this }
+            }
+            let c = Counter(7);
+            let get = c.get;
+            c.bump().bump();
+            [get(), Counter(1).get()];";
+        assert_eq!(value(source), "[9, 1]");
+        assert_eq!(error("this;"), "cannot use 'this' outside of a method");
+    }
+
+    #[test]
+    fn subclasses_inherit_and_call_super() {
+        let source = "
+            class Animal {
+                init(name) { this.name = name; }
+                speak() { this.name + \" makes a sound\" }
+                kind() { \"animal\" }
+            }
+            class Dog < Animal {
+                speak() { super.speak() + \", woof\" }
+            }
+            let d = Dog(\"Rex\");
+            [d.speak(), d.kind(), d.name];";
+        assert_eq!(
+            value(source),
+            "[\"Rex makes a sound, woof\", \"animal\", \"Rex\"]"
+        );
+        assert_eq!(
+            error("let B = 1; class A < B {}"),
+            "superclass must be a class"
+        );
+    }
+
+    #[test]
+    fn lambdas_are_values_that_close_over_their_scope() {
+        assert_eq!(
+            value("let add = fun (a, b) { a + b }; [add(1, 2), add, type(add)];"),
+            "[3, <fn lambda>, \"function\"]"
+        );
+        // Each call to `make` gives a lambda with its own `n`.
+        assert_eq!(
+            value("let make = fun (n) { fun (x) { x + n } }; let a = make(10); let b = make(20); [a(1), b(1)];"),
+            "[11, 21]"
+        );
+        assert_eq!(value("fun (x) { x * 2 }(4);"), "8");
+    }
+
+    #[test]
+    fn map_filter_and_reduce_call_back_into_lox() {
+        assert_eq!(
+            value("[map([1, 2, 3], fun (x) { x * 2 }), filter([1, 2, 3, 4], fun (x) { x > 2 }), reduce([1, 2, 3], fun (a, x) { a + x }, 10)];"),
+            "[[2, 4, 6], [3, 4], 16]"
+        );
+        assert_eq!(value("map([], fun (x) { x });"), "[]");
+        assert_eq!(error("map(1, fun (x) { x });"), "map expects a list");
+        assert_eq!(error("map([1], 1);"), "can only call functions");
+    }
+
+    #[test]
+    fn rest_parameters_collect_the_extra_arguments() {
+        assert_eq!(
+            value("fun f(a, ...rest) { [a, rest] } [f(1), f(1, 2, 3)];"),
+            "[[1, []], [1, [2, 3]]]"
+        );
+        assert_eq!(value("fun f(a = 1, ...rest) { [a, rest] } f();"), "[1, []]");
+        assert_eq!(
+            error("fun f(a, ...rest) { a } f();"),
+            "f expects at least 1 argument(s) but got 0"
+        );
+        assert_eq!(
+            error("fun f(...r, a) { a }"),
+            "a rest parameter must be the last one"
+        );
+    }
+
+    #[test]
+    fn files_can_be_written_and_read_back_with_access() {
+        let dir = std::env::temp_dir().join(format!("rlox-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.txt");
+        let path = path.to_str().unwrap();
+
+        let (interpreter, _) = quiet();
+        let mut interpreter = interpreter.with_file_access(true);
+        let value = interpreter
+            .eval_source(&format!(
+                "write_file(\"{0}\", \"hello\"); read_file(\"{0}\");",
+                path
+            ))
+            .unwrap();
+        assert_eq!(value.to_string(), "hello");
+
+        // A path that can't be written is an error, not a quiet `false`.
+        let missing = dir.join("no-such-dir").join("out.txt");
+        let missing = missing.to_str().unwrap();
+        let errors = interpreter
+            .eval_source(&format!("write_file(\"{}\", \"x\");", missing))
+            .unwrap_err();
+        assert!(
+            errors[0]
+                .msg
+                .starts_with(&format!("cannot write '{}': ", missing)),
+            "{}",
+            errors[0].msg
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Without access, neither is allowed at all.
+        assert_eq!(
+            error("read_file(\"x\");"),
+            "read_file needs file access, which isn't enabled"
+        );
+    }
+
+    #[test]
+    fn trace_counts_each_kind_of_node() {
+        let (interpreter, _) = quiet();
+        let mut interpreter = interpreter.with_trace(true);
+        interpreter
+            .eval_source("let i = 0; while (i < 3) { i = i + 1; }")
+            .unwrap();
+        let count = |node| {
+            interpreter
+                .trace_report()
+                .iter()
+                .find(|(name, _)| *name == node)
+                .map_or(0, |(_, count)| *count)
+        };
+        assert_eq!(count("While"), 1);
+        // `i < 3` four times, and `i + 1` three times.
+        assert_eq!(count("BinaryOp"), 7);
+        // The most frequent comes first: `0`, then `3` four times, and `1` three times.
+        assert_eq!(interpreter.trace_report()[0], ("Number", 8));
+
+        let (mut untraced, _) = quiet();
+        untraced.eval_source("1 + 2;").unwrap();
+        assert!(untraced.trace_report().is_empty());
+    }
+
+    #[test]
+    fn step_runs_one_statement_at_a_time() {
+        let program =
+            crate::parse(&scan_tokens("let a = 1;\nlet b = a + 1;\na + b;").unwrap()).unwrap();
+        let (mut interpreter, _) = quiet();
+        let mut steps = Vec::new();
+        loop {
+            match interpreter.step(&program).unwrap() {
+                StepResult::Stepped { value, variables } => {
+                    let names: Vec<String> = variables
+                        .iter()
+                        .filter(|(name, _)| name == "a" || name == "b")
+                        .map(|(name, value)| format!("{}={}", name, value))
+                        .collect();
+                    steps.push(format!("{} [{}]", value, names.join(" ")));
+                }
+                StepResult::Done => break,
+            }
+        }
+        assert_eq!(steps, ["1 [a=1]", "2 [a=1 b=2]", "3 [a=1 b=2]"]);
+
+        // Once done, stepping starts the program over, which here redefines `a`.
+        let err = interpreter.step(&program).unwrap_err();
+        assert_eq!(err.msg, "variable a already defined");
+    }
+
+    #[test]
+    fn dump_env_lists_the_visible_variables() {
+        assert_eq!(
+            value("let s = \"x\"; let n = 2; dump_env();"),
+            "PI = 3.14159265358979\n_ = 2\nn = 2\ns = \"x\""
+        );
+        // Locals are listed alongside the globals, and builtins like `clock` are left out.
+        let listing = value("fun f() { let local = 1; dump_env() } f();");
+        assert!(listing.ends_with("\nlocal = 1"), "{}", listing);
+        assert!(!listing.contains("clock"), "{}", listing);
+    }
+
+    #[test]
+    fn booleans_and_nil_are_ordered() {
+        assert_eq!(value("false < true"), "true");
+        assert_eq!(value("true <= true"), "true");
+        assert_eq!(value("true > false"), "true");
+        assert_eq!(value("nil <= nil"), "true");
+        assert_eq!(value("nil < nil"), "false");
+        assert_eq!(error("true < 1"), "cannot compare boolean with number");
+    }
+
+    #[test]
+    fn numbers_and_strings_add_in_either_order() {
+        assert_eq!(value("1 + \"x\""), "1x");
+        assert_eq!(value("\"x\" + 1"), "x1");
+        assert_eq!(value("1 + 2"), "3");
+    }
+
+    #[test]
+    fn lists_concatenate_and_repeat() {
+        assert_eq!(value("[1, 2] + [3]"), "[1, 2, 3]");
+        assert_eq!(value("[0] * 3"), "[0, 0, 0]");
+        assert_eq!(error("[0] * 1.5"), "right operand must be an integer");
+        assert_eq!(error("[1] + 1"), "a list can only be added to another list");
+        assert_eq!(error("1 + [1]"), "a list can only be added to another list");
+    }
+
+    #[test]
+    fn in_checks_lists_and_strings() {
+        assert_eq!(value("2 in [1, 2]"), "true");
+        assert_eq!(value("3 in [1, 2]"), "false");
+        assert_eq!(value("\"ell\" in \"hello\""), "true");
+        assert_eq!(value("\"elk\" in \"hello\""), "false");
+        assert_eq!(error("1 in 2"), "cannot look for anything in a number");
+    }
+
+    #[test]
+    fn slices_clamp_to_the_ends() {
+        assert_eq!(value("[1, 2, 3, 4][1:3]"), "[2, 3]");
+        assert_eq!(value("\"hello\"[1:3]"), "el");
+        assert_eq!(value("\"hello\"[:2]"), "he");
+        assert_eq!(value("\"hello\"[1:]"), "ello");
+        assert_eq!(value("[1, 2][1:10]"), "[2]");
+        assert_eq!(value("[1, 2][5:10]"), "[]");
+    }
+
+    #[test]
+    fn reset_forgets_variables_but_keeps_builtins() {
+        let (mut interpreter, _) = quiet();
+        interpreter.eval_source("let a = 1; a").unwrap();
+        assert!(has_variable(&interpreter, "a"));
+        assert!(has_variable(&interpreter, "_"));
+
+        interpreter.reset();
+        assert!(!has_variable(&interpreter, "a"));
+        assert!(!has_variable(&interpreter, "_"));
+        assert_eq!(
+            interpreter.eval_source("sqrt(4) + len(\"ab\")").unwrap(),
+            Object::Number(4.0)
+        );
+        // `a` can be defined again.
+        interpreter.eval_source("let a = 2;").unwrap();
+    }
+
+    #[test]
+    fn math_builtins_work_on_numbers() {
+        assert_eq!(value("floor(PI * 10000)"), "31415");
+        assert_eq!(value("sqrt(16)"), "4");
+        assert_eq!(
+            value("[abs(-2), floor(1.5), ceil(1.2), round(2.5)]"),
+            "[2, 1, 2, 3]"
+        );
+        assert_eq!(value("[min(3, 1, 2), max(3, 1, 2)]"), "[1, 3]");
+        assert_eq!(value("pow(2, 10)"), "1024");
+        assert_eq!(error("sqrt(-1)"), "sqrt expects a non-negative number");
+    }
+
+    #[test]
+    fn seeded_random_numbers_repeat() {
+        let draw =
+            "seed(3); let xs = []; for (let i = 0; i < 50; i += 1) { xs += [randint(1, 6)]; } xs";
+        let first = value(draw);
+        assert_eq!(value(draw), first);
+        for n in first.trim_matches(['[', ']']).split(", ") {
+            let n: u32 = n.parse().unwrap();
+            assert!((1..=6).contains(&n), "{}", first);
+        }
+    }
+
+    #[test]
+    fn sleep_waits_and_can_be_interrupted() {
+        let (interpreter, _) = quiet();
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut interpreter = interpreter.with_stop_flag(Arc::clone(&stop_flag));
+
+        let slept = interpreter
+            .eval_source("let start = clock(); sleep(30); clock() - start")
+            .unwrap();
+        assert!(matches!(slept, Object::Number(n) if n >= 0.03), "{}", slept);
+
+        // Set the flag partway through a long sleep, the way a Ctrl+C handler would.
+        let setter = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(30));
+            stop_flag.store(true, AtomicOrdering::Relaxed);
+        });
+        let started = Instant::now();
+        let errors = interpreter.eval_source("sleep(10000);").unwrap_err();
+        setter.join().unwrap();
+        assert_eq!(errors[0].msg, "sleep was interrupted");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn version_is_the_package_version() {
+        assert_eq!(value("version()"), env!("CARGO_PKG_VERSION"));
+        assert_eq!(value("version_info().version"), env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn labeled_break_and_continue_act_on_the_outer_loop() {
+        let nested = |inner: &str| {
+            format!(
+                "let n = 0; 'outer: for (let i = 0; i < 3; i += 1) {{ \
+                 for (let j = 0; j < 3; j += 1) {{ if (j == 1) {{ {} }} n += 1; }} }} n",
+                inner
+            )
+        };
+        assert_eq!(value(&nested("break 'outer;")), "1");
+        assert_eq!(value(&nested("continue 'outer;")), "3");
+        assert_eq!(value(&nested("break;")), "3");
+
+        assert_eq!(value("let x = 'a: loop { loop { break 'a 42; } }; x"), "42");
+        assert_eq!(
+            error("while (true) { break 'nope; }"),
+            "no enclosing loop labeled 'nope"
+        );
+    }
+
+    #[test]
+    fn continue_still_runs_a_for_loops_increment() {
+        assert_eq!(
+            value(
+                "let n = 0; for (let i = 0; i < 4; i += 1) { if (i == 1) { continue; } n += 1; } n"
+            ),
+            "3"
+        );
+    }
+
+    #[test]
+    fn strings_round_trip_through_bytes_and_base64() {
+        assert_eq!(value("to_utf8(\"h\u{e9}\")"), "<bytes 68c3a9>");
+        assert_eq!(value("to_utf8(\"h\u{e9}\")[1]"), "195");
+        assert_eq!(value("to_utf8(\"ab\") == to_utf8(\"ab\")"), "true");
+        assert_eq!(value("from_utf8(to_utf8(\"h\u{e9}llo\"))"), "h\u{e9}llo");
+        assert_eq!(value("base64_encode(to_utf8(\"h\u{e9}llo\"))"), "aMOpbGxv");
+        assert_eq!(
+            value("from_utf8(base64_decode(base64_encode(to_utf8(\"h\u{e9}llo\"))))"),
+            "h\u{e9}llo"
+        );
+        assert_eq!(
+            error("base64_decode(\"!!\")"),
+            "base64_decode got invalid base64"
+        );
+        assert_eq!(
+            error("from_utf8(base64_decode(\"aOlsbG8=\"))"),
+            "from_utf8 got invalid UTF-8 at byte 1"
+        );
     }
 
-    fn visit_break(&mut self, loc: &dyn HasFileLocation) -> Result<Object, RuntimeError> {
-        Err(RuntimeError::break_loop())
+    #[test]
+    fn format_fills_placeholders_in_order() {
+        assert_eq!(value("format(\"{} + {} = {}\", 1, 2, 3)"), "1 + 2 = 3");
+        assert_eq!(
+            value("format(\"{}: {}\", \"xs\", [1, nil])"),
+            "xs: [1, nil]"
+        );
+        assert_eq!(value("format(\"{{}} {}\", \"x\")"), "{} x");
+        assert_eq!(
+            error("format(\"{} {}\", 1)"),
+            "format has more placeholders than the 1 argument(s) given"
+        );
+        assert_eq!(
+            error("format(\"{}\", 1, 2)"),
+            "format was given 2 argument(s) but has only 1 placeholder(s)"
+        );
     }
 
-    fn visit_continue(&mut self, loc: &dyn HasFileLocation) -> Result<Object, RuntimeError> {
-        Err(RuntimeError::continue_loop())
+    #[test]
+    fn join_and_split_round_trip() {
+        assert_eq!(value("split(\"a,b,c\", \",\")"), "[\"a\", \"b\", \"c\"]");
+        assert_eq!(value("join(split(\"a,b,c\", \",\"), \",\")"), "a,b,c");
+        assert_eq!(value("join([1, \"a\", nil], \"-\")"), "1-a-nil");
+        assert_eq!(value("join([], \",\")"), "");
+        // An empty separator splits into characters.
+        assert_eq!(value("split(\"abc\", \"\")"), "[\"a\", \"b\", \"c\"]");
+        assert_eq!(value("join(split(\"abc\", \"\"), \"\")"), "abc");
     }
 }
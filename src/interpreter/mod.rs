@@ -1,13 +1,27 @@
+mod builtins;
+mod class;
 mod environment;
 mod environment_stack;
+mod function;
 mod has_stop_flag;
 mod interpreter;
+mod native_function;
 mod object;
+mod output_buffer;
+mod resolver;
+mod rng;
 mod runtime_error;
+mod step_result;
 
+pub use class::{Class, Instance};
 pub use environment::Environment;
-pub use environment_stack::EnvironmentStack;
+pub use environment_stack::{EnvironmentStack, Scope};
+pub use function::Function;
 pub use has_stop_flag::HasStopFlag;
 pub use interpreter::Interpreter;
-pub use object::Object;
+pub use native_function::{NativeFn, NativeFunction};
+pub use object::{format_number, json_string, Object};
+pub use output_buffer::OutputBuffer;
+pub use resolver::Resolver;
 pub use runtime_error::RuntimeError;
+pub use step_result::StepResult;
@@ -0,0 +1,114 @@
+use std::fmt::{Debug, Display};
+
+use crate::debug::HasFileLocation;
+
+use super::{Interpreter, Object, RuntimeError};
+
+pub type NativeFn =
+    fn(&mut Interpreter, &dyn HasFileLocation, Vec<Object>) -> Result<Object, RuntimeError>;
+
+/**
+ * A function implemented in Rust and exposed to Lox code as a global.
+ */
+#[derive(Clone)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub min_arity: usize,
+
+    // `None` accepts any number of arguments past `min_arity`.
+    pub max_arity: Option<usize>,
+
+    func: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: usize, func: NativeFn) -> Self {
+        Self::with_arity(name, arity, Some(arity), func)
+    }
+
+    pub fn with_arity(
+        name: &'static str,
+        min_arity: usize,
+        max_arity: Option<usize>,
+        func: NativeFn,
+    ) -> Self {
+        Self {
+            name,
+            min_arity,
+            max_arity,
+            func,
+        }
+    }
+
+    fn describe_arity(&self) -> String {
+        match self.max_arity {
+            Some(max) if max == self.min_arity => format!("{}", max),
+            Some(max) => format!("{} to {}", self.min_arity, max),
+            None => format!("at least {}", self.min_arity),
+        }
+    }
+
+    fn check_arity(&self, loc: &dyn HasFileLocation, count: usize) -> Result<(), RuntimeError> {
+        let too_many = self.max_arity.is_some_and(|max| count > max);
+        if count < self.min_arity || too_many {
+            return Err(RuntimeError::at(
+                format!(
+                    "{} expects {} argument(s) but got {}",
+                    self.name,
+                    self.describe_arity(),
+                    count
+                )
+                .as_str(),
+                loc,
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        loc: &dyn HasFileLocation,
+        args: Vec<Object>,
+    ) -> Result<Object, RuntimeError> {
+        self.check_arity(loc, args.len())?;
+        (self.func)(interpreter, loc, args)
+    }
+
+    /**
+     * Call a built-in method.  The receiver is passed as the first argument, but doesn't count
+     * towards the arity.
+     */
+    pub fn call_method(
+        &self,
+        interpreter: &mut Interpreter,
+        loc: &dyn HasFileLocation,
+        receiver: Object,
+        args: Vec<Object>,
+    ) -> Result<Object, RuntimeError> {
+        self.check_arity(loc, args.len())?;
+        let mut values = Vec::with_capacity(args.len() + 1);
+        values.push(receiver);
+        values.extend(args);
+        (self.func)(interpreter, loc, values)
+    }
+}
+
+// Natives are identified by name; comparing function pointers isn't reliable.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
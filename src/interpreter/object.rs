@@ -1,35 +1,98 @@
-use std::fmt::Display;
+use std::{cell::RefCell, cmp::Ordering, fmt::Display, rc::Rc};
+
+use super::{Class, Function, Instance, NativeFunction};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
     String(String),
     Number(f64),
     Boolean(bool),
+    List(Rc<Vec<Object>>),
+    Bytes(Rc<Vec<u8>>),
+    NativeFunction(NativeFunction),
+    Function(Function),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
     NaN,
     Nil,
 }
 
 impl Object {
+    /**
+     * Wrap the result of arithmetic, e.g. `Infinity - Infinity`, so that NaN is always `Object::NaN`.
+     */
+    pub fn number(n: f64) -> Self {
+        if n.is_nan() {
+            Object::NaN
+        } else {
+            Object::Number(n)
+        }
+    }
+
     pub fn is_truthy(&self) -> bool {
         match self {
             Object::String(s) => !s.is_empty(),
             Object::Number(n) => *n != 0.0,
             Object::Boolean(b) => *b,
+            Object::List(items) => !items.is_empty(),
+            Object::Bytes(bytes) => !bytes.is_empty(),
+            Object::NativeFunction(_) => true,
+            Object::Function(_) => true,
+            Object::Class(_) => true,
+            Object::Instance(_) => true,
             Object::NaN => false,
             Object::Nil => false,
         }
     }
 
+    /**
+     * The name of the value's type, as reported by `type(x)`.
+     */
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::String(_) => "string",
+            Object::Number(_) => "number",
+            Object::Boolean(_) => "boolean",
+            Object::List(_) => "list",
+            Object::Bytes(_) => "bytes",
+            Object::NativeFunction(_) | Object::Function(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::NaN => "nan",
+            Object::Nil => "nil",
+        }
+    }
+
     pub fn is_falsy(&self) -> bool {
         !self.is_truthy()
     }
 
     pub fn is_equal(&self, other: &Object) -> bool {
+        self.is_equal_with(other, false)
+    }
+
+    /**
+     * Compare two values, choosing whether `NaN == NaN`.  IEEE says it isn't, but a script that
+     * checks a value against itself may expect it to be.
+     */
+    pub fn is_equal_with(&self, other: &Object, nan_eq: bool) -> bool {
         match (self, other) {
             (Object::String(s1), Object::String(s2)) => s1 == s2,
             (Object::Number(n1), Object::Number(n2)) => n1 == n2,
             (Object::Boolean(b1), Object::Boolean(b2)) => b1 == b2,
-            (Object::NaN, Object::NaN) => false,
+            (Object::List(l1), Object::List(l2)) => {
+                l1.len() == l2.len()
+                    && l1
+                        .iter()
+                        .zip(l2.iter())
+                        .all(|(a, b)| a.is_equal_with(b, nan_eq))
+            }
+            (Object::Bytes(b1), Object::Bytes(b2)) => b1 == b2,
+            (Object::NativeFunction(f1), Object::NativeFunction(f2)) => f1 == f2,
+            (Object::Function(f1), Object::Function(f2)) => f1 == f2,
+            (Object::Class(c1), Object::Class(c2)) => Rc::ptr_eq(c1, c2),
+            (Object::Instance(i1), Object::Instance(i2)) => Rc::ptr_eq(i1, i2),
+            (Object::NaN, Object::NaN) => nan_eq,
             (Object::Nil, Object::Nil) => true,
             _ => false,
         }
@@ -38,16 +101,227 @@ impl Object {
     pub fn is_not_equal(&self, other: &Object) -> bool {
         !self.is_equal(other)
     }
+
+    /**
+     * Order two values of the same type.  Numbers compare numerically, `false` comes before `true`,
+     * and `nil` only compares with itself.  Anything else, including values of different types,
+     * has no ordering.
+     */
+    pub fn compare(&self, other: &Object) -> Option<Ordering> {
+        match (self, other) {
+            (Object::Number(n1), Object::Number(n2)) => n1.partial_cmp(n2),
+            (Object::Boolean(b1), Object::Boolean(b2)) => Some(b1.cmp(b2)),
+            (Object::Nil, Object::Nil) => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /**
+     * Render the value the way it would be written in source, e.g. strings are quoted and escaped.
+     *
+     * The REPL echoes results this way so that `"1"` and `1` can be told apart; `print` uses `Display`.
+     */
+    pub fn repr(&self) -> String {
+        match self {
+            Object::String(s) => repr_string(s),
+            _ => self.to_string(),
+        }
+    }
+
+    /**
+     * Render the value as JSON.
+     *
+     * JSON has no NaN or infinity, so those are rendered as `null` just like `nil`.
+     */
+    pub fn to_json(&self) -> String {
+        match self {
+            Object::String(s) => json_string(s),
+            Object::Number(n) if !n.is_finite() => "null".to_string(),
+            Object::Number(n) => format!("{}", n),
+            Object::Boolean(b) => format!("{}", b),
+            Object::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_json()).collect();
+                format!("[{}]", items.join(","))
+            }
+            // JSON has no binary type, so bytes become a list of their values.
+            Object::Bytes(bytes) => {
+                let items: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+                format!("[{}]", items.join(","))
+            }
+            Object::NativeFunction(_)
+            | Object::Function(_)
+            | Object::Class(_)
+            | Object::Instance(_) => json_string(&self.to_string()),
+            Object::NaN => "null".to_string(),
+            Object::Nil => "null".to_string(),
+        }
+    }
+}
+
+/**
+ * Format a number for display.
+ *
+ * Infinities print as `Infinity` and `-Infinity`.  Whole numbers print without a decimal point,
+ * and everything else is rounded to 15 significant digits so `0.1 + 0.2` prints as `0.3`.  Very
+ * large and very small magnitudes use exponent notation.
+ */
+pub fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if n == 0.0 {
+        // Don't show the sign of -0.
+        return "0".to_string();
+    }
+
+    // 15 significant digits in exponent notation, e.g. "1.50000000000000e-7".
+    let precise = format!("{:.14e}", n);
+    let (mantissa, exponent) = precise.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+
+    if !(-6..21).contains(&exponent) {
+        return format!("{}e{}", trim_fraction(mantissa), exponent);
+    }
+    if n.fract() == 0.0 {
+        // Whole numbers are printed exactly.
+        return format!("{:.0}", n);
+    }
+    let decimals = (14 - exponent).max(0) as usize;
+    trim_fraction(&format!("{:.*}", decimals, n)).to_string()
+}
+
+/**
+ * Remove trailing zeros after the decimal point, and the point itself if nothing is left.
+ */
+fn trim_fraction(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+fn repr_string(s: &str) -> String {
+    let mut repr = String::with_capacity(s.len() + 2);
+    repr.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => repr.push_str("\\\""),
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            // Keep the result valid source by not starting an interpolation.
+            '$' if chars.peek() == Some(&'{') => repr.push_str("\\$"),
+            c if c.is_control() => repr.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+            c => repr.push(c),
+        }
+    }
+    repr.push('"');
+    repr
+}
+
+/**
+ * Quote and escape a string for JSON.
+ */
+pub fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
 }
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Object::String(s) => write!(f, "{}", s),
-            Object::Number(n) => write!(f, "{}", n),
+            Object::Number(n) => write!(f, "{}", format_number(*n)),
             Object::Boolean(b) => write!(f, "{}", b),
+            Object::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item.repr())?;
+                }
+                write!(f, "]")
+            }
+            Object::Bytes(bytes) => {
+                write!(f, "<bytes ")?;
+                for byte in bytes.iter() {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, ">")
+            }
+            Object::NativeFunction(native) => write!(f, "{}", native),
+            Object::Function(function) => write!(f, "{}", function),
+            Object::Class(class) => write!(f, "{}", class),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
             Object::NaN => write!(f, "NaN"),
             Object::Nil => write!(f, "nil"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_of(source: &str) -> String {
+        crate::run(source).unwrap().to_json()
+    }
+
+    #[test]
+    fn every_kind_of_value_renders_as_json() {
+        assert_eq!(Object::Nil.to_json(), "null");
+        assert_eq!(Object::Boolean(true).to_json(), "true");
+        assert_eq!(Object::Number(3.0).to_json(), "3");
+        assert_eq!(Object::Number(-1.5).to_json(), "-1.5");
+        assert_eq!(
+            Object::String("say \"hi\"\n".to_string()).to_json(),
+            r#""say \"hi\"\n""#
+        );
+        assert_eq!(json_of("[1, \"a\", nil, [true]]"), r#"[1,"a",null,[true]]"#);
+        assert_eq!(json_of("to_utf8(\"hi\")"), "[104,105]");
+        assert_eq!(json_of("clock"), r#""<native fn clock>""#);
+        assert_eq!(json_of("fun f(x) { x } f"), r#""<fn f>""#);
+        assert_eq!(json_of("class A {} A"), r#""<class A>""#);
+        assert_eq!(json_of("class A {} A()"), r#""<A instance>""#);
+    }
+
+    #[test]
+    fn values_json_cant_hold_become_null() {
+        assert_eq!(Object::NaN.to_json(), "null");
+        assert_eq!(json_of("0 / 0"), "null");
+        assert_eq!(Object::Number(f64::INFINITY).to_json(), "null");
+        assert_eq!(Object::Number(f64::NEG_INFINITY).to_json(), "null");
+    }
+
+    #[test]
+    fn numbers_display_without_float_noise() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(2.5), "2.5");
+        assert_eq!(format_number(-0.0), "0");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(1.0 / 3.0), "0.333333333333333");
+        assert_eq!(format_number(123456789012.0), "123456789012");
+        assert_eq!(format_number(1.5e-7), "1.5e-7");
+        assert_eq!(format_number(1e21), "1e21");
+    }
+}
@@ -0,0 +1,354 @@
+use std::{cell::Cell, collections::HashSet, rc::Rc};
+
+use crate::{
+    debug::HasFileLocation,
+    lexer::Symbol,
+    parser::{DefaultVisitor, Expr, FunctionDecl, Stmt},
+};
+
+use super::RuntimeError;
+
+/**
+ * Works out which scope each variable reference points at before the program runs.
+ *
+ * The depth is stored on each `Variable` and `Assign` node, so the interpreter can go straight to
+ * the right environment instead of searching outwards.  Names that aren't found in any enclosing
+ * block are globals, which are looked up by name at runtime since the REPL keeps adding to them.
+ *
+ * The scopes here must mirror the ones the interpreter pushes: one per block, and one per
+ * iteration of a `for-in` loop.
+ */
+pub struct Resolver {
+    scopes: Vec<HashSet<Symbol>>,
+
+    // Whether we're inside a method, where `this` is allowed.
+    in_method: bool,
+
+    // Whether that method belongs to a class with a superclass, where `super` is allowed.
+    in_subclass: bool,
+
+    // The labels of the loops we're inside, innermost last, or `None` for the unlabeled ones.
+    labels: Vec<Option<Symbol>>,
+
+    // The first problem found, e.g. `this` outside of a method.
+    error: Option<RuntimeError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            in_method: false,
+            in_subclass: false,
+            labels: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn resolve(&mut self, program: &Vec<Stmt>) -> Result<(), RuntimeError> {
+        for stmt in program {
+            stmt.accept(self);
+        }
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&mut self, msg: &str, loc: &dyn HasFileLocation) {
+        if self.error.is_none() {
+            self.error = Some(RuntimeError::at(msg, loc));
+        }
+    }
+
+    fn declare(&mut self, name: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name);
+        }
+    }
+
+    /**
+     * Methods only see the globals and their own scopes, so the enclosing scopes are set aside
+     * while the body is resolved.
+     */
+    fn resolve_method(&mut self, method: &FunctionDecl, in_subclass: bool) {
+        let enclosing = std::mem::take(&mut self.scopes);
+        let was_in_method = std::mem::replace(&mut self.in_method, true);
+        let was_in_subclass = std::mem::replace(&mut self.in_subclass, in_subclass);
+
+        // `this` and `super` live alongside the parameters.
+        let mut scope = HashSet::new();
+        scope.insert(Symbol::intern("this"));
+        if in_subclass {
+            scope.insert(Symbol::intern("super"));
+        }
+        self.scopes.push(scope);
+        self.resolve_function_body(method);
+
+        self.in_method = was_in_method;
+        self.in_subclass = was_in_subclass;
+        self.scopes = enclosing;
+    }
+
+    /**
+     * Resolve the parameters and body of a function, in the scope that's already been pushed for
+     * it.  Each default can see the parameters before it, but not the ones after.
+     */
+    fn resolve_function_body(&mut self, decl: &FunctionDecl) {
+        // A `break` in a function can't reach a loop in the code that called it.
+        let enclosing_labels = std::mem::take(&mut self.labels);
+        for (param, default) in decl.params.iter().zip(&decl.defaults) {
+            if let Some(default) = default {
+                default.accept(self);
+            }
+            self.declare(*param);
+        }
+        if let Some(rest) = &decl.rest {
+            self.declare(*rest);
+        }
+        for stmt in &decl.body {
+            stmt.accept(self);
+        }
+        self.labels = enclosing_labels;
+    }
+
+    /**
+     * Resolve the body of a loop, where a `break` or `continue` can name its label.
+     */
+    fn resolve_loop_body(&mut self, label: &Option<Symbol>, body: &Stmt) {
+        self.labels.push(*label);
+        body.accept(self);
+        self.labels.pop();
+    }
+
+    fn check_label(&mut self, label: &Option<Symbol>, loc: &dyn HasFileLocation) {
+        if let Some(label) = label {
+            if !self.labels.contains(&Some(*label)) {
+                self.error(&format!("no enclosing loop labeled '{}", label), loc);
+            }
+        }
+    }
+
+    fn resolve_local(&self, name: Symbol, depth: &Cell<Option<usize>>) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains(&name));
+        depth.set(found);
+    }
+}
+
+impl DefaultVisitor for Resolver {
+    fn visit_assign(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        depth: &Cell<Option<usize>>,
+    ) {
+        expr.accept(self);
+        self.resolve_local(*name, depth);
+    }
+
+    fn visit_variable(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        depth: &Cell<Option<usize>>,
+    ) {
+        self.resolve_local(*name, depth);
+    }
+
+    fn visit_super(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        _method: &str,
+        depth: &Cell<Option<usize>>,
+    ) {
+        if !self.in_method {
+            self.error("cannot use 'super' outside of a method", loc);
+            return;
+        }
+        if !self.in_subclass {
+            self.error("cannot use 'super' in a class with no superclass", loc);
+            return;
+        }
+        self.resolve_local(Symbol::intern("super"), depth);
+    }
+
+    fn visit_lambda(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        // Unlike methods, lambdas can see the scopes around them.
+        self.scopes.push(HashSet::new());
+        self.resolve_function_body(decl);
+        self.scopes.pop();
+    }
+
+    fn visit_this(&mut self, loc: &dyn HasFileLocation, depth: &Cell<Option<usize>>) {
+        if !self.in_method {
+            self.error("cannot use 'this' outside of a method", loc);
+            return;
+        }
+        self.resolve_local(Symbol::intern("this"), depth);
+    }
+
+    fn visit_let(&mut self, _loc: &dyn HasFileLocation, name: &Symbol, init: &Option<Box<Expr>>) {
+        // The initializer runs before the name exists, so `let a = a;` reads the outer `a`.
+        if let Some(init) = init {
+            init.accept(self);
+        }
+        self.declare(*name);
+    }
+
+    fn visit_delete(&mut self, _loc: &dyn HasFileLocation, name: &Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.remove(name);
+        }
+    }
+
+    fn visit_block(&mut self, _loc: &dyn HasFileLocation, stmts: &[Stmt]) {
+        self.scopes.push(HashSet::new());
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+        self.scopes.pop();
+    }
+
+    fn visit_while(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) {
+        cond.accept(self);
+        self.resolve_loop_body(label, body);
+        if let Some(increment) = increment {
+            increment.accept(self);
+        }
+    }
+
+    fn visit_loop(&mut self, _loc: &dyn HasFileLocation, label: &Option<Symbol>, body: &Stmt) {
+        self.resolve_loop_body(label, body);
+    }
+
+    fn visit_for_in(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        iterable.accept(self);
+        self.scopes.push(HashSet::new());
+        self.declare(*name);
+        self.resolve_loop_body(label, body);
+        self.scopes.pop();
+    }
+
+    fn visit_break(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) {
+        self.check_label(label, loc);
+        if let Some(value) = value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_continue(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>) {
+        self.check_label(label, loc);
+    }
+
+    fn visit_class(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) {
+        if let Some(superclass) = superclass {
+            superclass.accept(self);
+        }
+        self.declare(Symbol::intern(name));
+        for method in methods {
+            self.resolve_method(method, superclass.is_some());
+        }
+    }
+
+    fn visit_function_stmt(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        // Declared before the body is resolved, so the function can call itself.
+        self.declare(Symbol::intern(&decl.name));
+        self.scopes.push(HashSet::new());
+        self.resolve_function_body(decl);
+        self.scopes.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, scan_tokens};
+
+    fn resolve(source: &str) -> (Vec<Stmt>, Result<(), RuntimeError>) {
+        let program = parse(&scan_tokens(source).unwrap()).unwrap();
+        let result = Resolver::new().resolve(&program);
+        (program, result)
+    }
+
+    /**
+     * The depth stored on the variable read by the expression statement `stmt`.
+     */
+    fn depth_of(stmt: &Stmt) -> Option<usize> {
+        match stmt {
+            Stmt::Expr(_, expr) => match expr.as_ref() {
+                Expr::Variable(_, _, depth) => depth.get(),
+                other => panic!("expected a variable, got {:?}", other),
+            },
+            Stmt::Block(_, stmts) => depth_of(stmts.last().unwrap()),
+            other => panic!("expected an expression or block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn variables_point_at_the_scope_they_were_declared_in() {
+        let (program, result) = resolve("let g = 1; g; { let a = 1; { a; } { let a = 2; a; } g; }");
+        result.unwrap();
+        assert_eq!(depth_of(&program[1]), None);
+
+        let Stmt::Block(_, inner) = &program[2] else {
+            panic!("expected a block");
+        };
+        assert_eq!(depth_of(&inner[1]), Some(1));
+        assert_eq!(depth_of(&inner[2]), Some(0));
+        assert_eq!(depth_of(&inner[3]), None);
+    }
+
+    #[test]
+    fn misplaced_this_super_and_labels_are_errors() {
+        let message = |source| resolve(source).1.unwrap_err().msg;
+        assert_eq!(message("this;"), "cannot use 'this' outside of a method");
+        assert_eq!(
+            message("super.f();"),
+            "cannot use 'super' outside of a method"
+        );
+        assert_eq!(
+            message("class A { f() { super.f(); } }"),
+            "cannot use 'super' in a class with no superclass"
+        );
+        assert_eq!(
+            message("'outer: loop { break 'inner; }"),
+            "no enclosing loop labeled 'inner"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * A small xorshift64* generator behind `random`, `randint`, and `seed`.  It's not fit for
+ * cryptography, but it's fast, and the same seed always gives the same sequence.
+ */
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.seed(seed);
+        rng
+    }
+
+    /**
+     * A generator seeded from the clock, so each run sees different numbers.
+     */
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0);
+        Self::new(nanos)
+    }
+
+    // `SystemTime::now` panics on wasm, so the browser starts from a fixed seed instead.
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_time() -> Self {
+        Self::new(0)
+    }
+
+    pub fn seed(&mut self, seed: u64) {
+        // Scramble the seed with a round of splitmix64, since xorshift gets stuck on a zero state
+        // and nearby seeds would otherwise start out looking alike.
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        self.state = if z == 0 { 1 } else { z };
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /**
+     * A number in `[0, 1)`.
+     */
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill an f64's mantissa exactly.
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(rng: &mut Rng) -> Vec<f64> {
+        (0..5).map(|_| rng.next_f64()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_sequence() {
+        let first = sequence(&mut Rng::new(42));
+        assert_eq!(sequence(&mut Rng::new(42)), first);
+        assert_ne!(sequence(&mut Rng::new(43)), first);
+
+        // Reseeding starts the sequence over.
+        let mut rng = Rng::new(7);
+        rng.next_f64();
+        rng.seed(42);
+        assert_eq!(sequence(&mut rng), first);
+        assert!(first.iter().all(|n| (0.0..1.0).contains(n)));
+    }
+}
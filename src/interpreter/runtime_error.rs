@@ -3,7 +3,7 @@ use std::{
     fmt::{Debug, Display},
 };
 
-use crate::debug::HasFileLocation;
+use crate::{debug::HasFileLocation, lexer::Symbol};
 
 /**
  * Indicates that the interpreter should stop executing code.
@@ -12,18 +12,23 @@ use crate::debug::HasFileLocation;
  */
 #[derive(Debug, Copy, Clone)]
 pub enum Interrupt {
-    // Indicates that a loop should be broken out of.
-    Break,
+    // Indicates that a loop should be broken out of: the one with this label, or else the innermost.
+    Break(Option<Symbol>),
 
-    // Indicates that the remaining code in the current scope should be skipped.
-    Continue,
+    // Indicates that the remaining code in the current scope should be skipped, up to the loop with
+    // this label, or else the innermost.
+    Continue(Option<Symbol>),
+
+    // Indicates that the program called `exit`, and the process should end with this status.
+    Exit(i32),
 }
 
 impl Display for Interrupt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Interrupt::Break => write!(f, "break"),
-            Interrupt::Continue => write!(f, "continue"),
+            Interrupt::Break(_) => write!(f, "break"),
+            Interrupt::Continue(_) => write!(f, "continue"),
+            Interrupt::Exit(code) => write!(f, "exit({})", code),
         }
     }
 }
@@ -32,6 +37,8 @@ pub struct RuntimeError {
     pub msg: String,
     line: usize,
     column: usize,
+    end_line: usize,
+    end_column: usize,
     pub interrupt: Option<Interrupt>,
 }
 
@@ -41,25 +48,67 @@ impl RuntimeError {
             msg: msg.to_string(),
             line,
             column,
+            end_line: line,
+            end_column: column,
             interrupt: None,
         }
     }
 
-    pub fn break_loop() -> Self {
+    /**
+     * Create an error covering the full span of `loc`.
+     */
+    pub fn at(msg: &str, loc: &dyn HasFileLocation) -> Self {
         Self {
-            msg: "break outside of a loop".to_string(),
-            line: 0,
-            column: 0,
-            interrupt: Some(Interrupt::Break),
+            msg: msg.to_string(),
+            line: loc.get_line(),
+            column: loc.get_column(),
+            end_line: loc.get_end_line(),
+            end_column: loc.get_end_column(),
+            interrupt: None,
+        }
+    }
+
+    /**
+     * A `break` at `loc`, aimed at the loop with `label` if there is one.  It only becomes an
+     * error if no loop catches it.
+     */
+    pub fn break_loop(loc: &dyn HasFileLocation, label: Option<Symbol>) -> Self {
+        Self {
+            interrupt: Some(Interrupt::Break(label)),
+            ..Self::at("break outside of a loop", loc)
+        }
+    }
+
+    /**
+     * A `continue` at `loc`, aimed at the loop with `label` if there is one.  It only becomes an
+     * error if no loop catches it.
+     */
+    pub fn continue_loop(loc: &dyn HasFileLocation, label: Option<Symbol>) -> Self {
+        Self {
+            interrupt: Some(Interrupt::Continue(label)),
+            ..Self::at("continue outside of a loop", loc)
         }
     }
 
-    pub fn continue_loop() -> Self {
+    pub fn exit(code: i32) -> Self {
         Self {
-            msg: "continue outside of a loop".to_string(),
+            msg: format!("exit with status {}", code),
             line: 0,
             column: 0,
-            interrupt: Some(Interrupt::Continue),
+            end_line: 0,
+            end_column: 0,
+            interrupt: Some(Interrupt::Exit(code)),
+        }
+    }
+
+    /**
+     * The status the program asked to exit with, if this isn't really an error but a call to
+     * `exit`.
+     */
+    pub fn exit_code(&self) -> Option<i32> {
+        match self.interrupt {
+            Some(Interrupt::Exit(code)) => Some(code),
+            _ => None,
         }
     }
 }
@@ -74,6 +123,14 @@ impl HasFileLocation for RuntimeError {
     fn get_column(&self) -> usize {
         self.column
     }
+
+    fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.end_line
+    }
 }
 
 impl Display for RuntimeError {
@@ -0,0 +1,16 @@
+use super::Object;
+
+/**
+ * What happened when the interpreter was asked to run the next statement of a program.
+ */
+#[derive(Debug, Clone)]
+pub enum StepResult {
+    // A statement ran.  `variables` holds the globals afterwards, as `Interpreter::variables` does.
+    Stepped {
+        value: Object,
+        variables: Vec<(String, Object)>,
+    },
+
+    // Every statement has run.  The next step starts the program again from the top.
+    Done,
+}
@@ -9,6 +9,8 @@ pub struct LexerError {
     pub msg: String,
     line: usize,
     column: usize,
+    end_line: usize,
+    end_column: usize,
 }
 
 impl LexerError {
@@ -17,6 +19,21 @@ impl LexerError {
             msg: msg.to_string(),
             line,
             column,
+            end_line: line,
+            end_column: column,
+        }
+    }
+
+    /**
+     * Create an error covering the full span of `loc`.
+     */
+    pub fn at(msg: &str, loc: &dyn HasFileLocation) -> Self {
+        Self {
+            msg: msg.to_string(),
+            line: loc.get_line(),
+            column: loc.get_column(),
+            end_line: loc.get_end_line(),
+            end_column: loc.get_end_column(),
         }
     }
 }
@@ -31,6 +48,14 @@ impl HasFileLocation for LexerError {
     fn get_column(&self) -> usize {
         self.column
     }
+
+    fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.end_line
+    }
 }
 
 impl Display for LexerError {
@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
+use super::Symbol;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
-    Identifier(String),
+    Identifier(Symbol),
     String(String),
     Number(f64),
     Boolean(bool),
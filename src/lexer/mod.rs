@@ -1,11 +1,13 @@
+mod lexer_error;
 mod literal;
 mod scanner;
-mod lexer_error;
+mod symbol;
 mod token;
 mod token_type;
 
-pub use literal::Literal;
 pub use lexer_error::LexerError;
-pub use scanner::scan_tokens;
+pub use literal::Literal;
+pub use scanner::{scan_tokens, TokenIter};
+pub use symbol::Symbol;
 pub use token::Token;
 pub use token_type::TokenType;
@@ -1,13 +1,17 @@
-use super::{LexerError, Literal, Token, TokenType};
+use std::collections::VecDeque;
 
-const TOKENIZE_COMMENTS: bool = false;
-const TOKENIZE_NEWLINE: bool = false;
-const TOKENIZE_SPACE: bool = false;
+use crate::debug::{FileLocation, HasFileLocation};
+
+use super::{LexerError, Literal, Symbol, Token, TokenType};
 
 struct Scanner {
-    source: String,
+    source: Vec<char>,
     start: usize,
 
+    // Where the current token starts.
+    start_line: usize,
+    start_column: usize,
+
     // The index into the source string.
     current: usize,
 
@@ -17,42 +21,52 @@ struct Scanner {
     // The current column.
     column: usize,
 
-    // This is the output.
-    pub tokens: Vec<Token>,
+    // The open `${` of each string interpolation being scanned, with the depth of braces inside it.
+    interpolations: Vec<(FileLocation, usize)>,
+
+    // Tokens that have been scanned but not yet handed out.
+    pub tokens: VecDeque<Token>,
+
+    // Add tokens for whitespace, line breaks, and comments, rather than skipping them.
+    keep_trivia: bool,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
         Self {
-            source,
+            source: source.chars().collect(),
             start: 0,
+            start_line: 1,
+            start_column: 1,
             current: 0,
             line: 1,
             column: 0,
-            tokens: Vec::new(), // let value = &self.source[self.start..self.current];
-                                // self.tokens.push(Token::new(
-                                //     TokenType::Whitespace,
-                                //     value,
-                                //     Literal::Nil,
-                                //     self.line,
-                                //     self.column,
-                                // ));
+            interpolations: Vec::new(),
+            tokens: VecDeque::new(),
+            keep_trivia: false,
         }
     }
 
-    fn scan_tokens(&mut self) -> Result<(), LexerError> {
-        while !self.is_at_end() {
+    /**
+     * Scan the next token, or add the closing `EOF` once the source runs out, in which case this
+     * returns false.  Whitespace and comments only add a token when trivia is kept.
+     */
+    fn scan_next(&mut self) -> Result<bool, LexerError> {
+        if !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column + 1;
             self.scan_token()?;
+            return Ok(true);
         }
-        self.tokens.push(Token::new(
-            TokenType::EOF,
-            "",
-            Literal::Nil,
-            self.line,
-            self.column,
-        ));
-        Ok(())
+        if let Some((loc, _)) = self.interpolations.last() {
+            return Err(LexerError::at("unterminated string interpolation", loc));
+        }
+        self.start_line = self.line;
+        self.start_column = self.column;
+        self.tokens
+            .push_back(self.make_token(TokenType::EOF, "", Literal::Nil));
+        Ok(false)
     }
 
     fn scan_token(&mut self) -> Result<(), LexerError> {
@@ -60,14 +74,79 @@ impl Scanner {
         match c {
             '(' => Ok(self.add_token(TokenType::LeftParen)),
             ')' => Ok(self.add_token(TokenType::RightParen)),
-            '{' => Ok(self.add_token(TokenType::LeftBrace)),
-            '}' => Ok(self.add_token(TokenType::RightBrace)),
+            '{' => {
+                if let Some((_, depth)) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+                Ok(())
+            }
+            '}' => match self.interpolations.last_mut() {
+                // This brace closes a `${`, so pick the string back up.
+                Some((_, 0)) => {
+                    self.interpolations.pop();
+                    self.string()
+                }
+                Some((_, depth)) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RightBrace);
+                    Ok(())
+                }
+                None => {
+                    self.add_token(TokenType::RightBrace);
+                    Ok(())
+                }
+            },
+            '[' => {
+                self.add_token(TokenType::LeftBracket);
+                Ok(())
+            }
+            ']' => {
+                self.add_token(TokenType::RightBracket);
+                Ok(())
+            }
             ',' => Ok(self.add_token(TokenType::Comma)),
-            '.' => Ok(self.add_token(TokenType::Dot)),
-            '-' => Ok(self.add_token(TokenType::Minus)),
-            '+' => Ok(self.add_token(TokenType::Plus)),
+            '.' => {
+                let token_type = if self.match_next('.') {
+                    if self.match_next('.') {
+                        TokenType::Ellipsis
+                    } else {
+                        TokenType::DotDot
+                    }
+                } else {
+                    TokenType::Dot
+                };
+                self.add_token(token_type);
+                Ok(())
+            }
+            '-' => {
+                let token_type = if self.match_next('=') {
+                    TokenType::MinusEqual
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(token_type);
+                Ok(())
+            }
+            '+' => {
+                let token_type = if self.match_next('=') {
+                    TokenType::PlusEqual
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(token_type);
+                Ok(())
+            }
             ';' => Ok(self.add_token(TokenType::Semicolon)),
-            '*' => Ok(self.add_token(TokenType::Star)),
+            '*' => {
+                let token_type = if self.match_next('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(token_type);
+                Ok(())
+            }
             ':' => Ok(self.add_token(TokenType::Colon)),
             '?' => {
                 let token_type = if self.match_next('?') {
@@ -114,6 +193,9 @@ impl Scanner {
                     self.line_comment()
                 } else if self.match_next('*') {
                     self.block_comment()
+                } else if self.match_next('=') {
+                    self.add_token(TokenType::SlashEqual);
+                    Ok(())
                 } else {
                     Ok(self.add_token(TokenType::Slash))
                 }
@@ -135,10 +217,11 @@ impl Scanner {
                 Ok(self.add_token(token_type))
             }
             '"' => self.string(),
+            '\'' => self.label(),
             '0'..='9' => self.number(),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
             ' ' | '\t' => self.whitespace(),
-            '\r' | '\n' => self.newline(),
+            '\r' | '\n' => self.newline(c),
             _ => Err(LexerError::new(
                 "unknown token type",
                 self.line,
@@ -161,23 +244,24 @@ impl Scanner {
                 self.advance();
                 self.advance();
             } else if self.peek() == '\n' {
-                self.line += 1;
-                self.column = 0;
                 self.advance();
+                self.next_line();
             } else {
                 self.advance();
             }
         }
 
-        if TOKENIZE_COMMENTS {
-            let value = &self.source[self.start..self.current];
-            self.tokens.push(Token::new(
-                TokenType::Comment,
-                value,
-                Literal::Nil,
-                self.line,
-                self.column,
-            ));
+        if depth > 0 {
+            // Point at the opening "/*".
+            let opening =
+                FileLocation::span(self.start_line, self.start_column, self.start_column + 1);
+            return Err(LexerError::at("unterminated block comment", &opening));
+        }
+
+        if self.keep_trivia {
+            let value = &self.lexeme();
+            self.tokens
+                .push_back(self.make_token(TokenType::Comment, value, Literal::Nil));
         }
         Ok(())
     }
@@ -187,46 +271,28 @@ impl Scanner {
             self.advance();
         }
 
-        if TOKENIZE_COMMENTS {
-            let value = &self.source[self.start..self.current];
-            self.tokens.push(Token::new(
-                TokenType::Comment,
-                value,
-                Literal::Nil,
-                self.line,
-                self.column,
-            ));
+        if self.keep_trivia {
+            let value = &self.lexeme();
+            self.tokens
+                .push_back(self.make_token(TokenType::Comment, value, Literal::Nil));
         }
         Ok(())
     }
 
-    fn newline(&mut self) -> Result<(), LexerError> {
-        // If peek isn't \n, then the current char should be \n.  The next one might still be \n.
-
-        if self.peek() == '\n' {
-            if TOKENIZE_NEWLINE {
-                self.tokens.push(Token::new(
-                    TokenType::NewLine,
-                    "\r\n",
-                    Literal::Nil,
-                    self.line,
-                    self.column,
-                ));
-            }
+    /**
+     * A line break, which is `\n`, `\r\n`, or a lone `\r`.  Only the two characters of `\r\n` are
+     * taken as one break; every other `\n` starts a new line, blank or not.
+     */
+    fn newline(&mut self, c: char) -> Result<(), LexerError> {
+        if c == '\r' && self.peek() == '\n' {
             self.advance();
-        } else {
-            if TOKENIZE_NEWLINE {
-                self.tokens.push(Token::new(
-                    TokenType::NewLine,
-                    "\n",
-                    Literal::Nil,
-                    self.line,
-                    self.column,
-                ));
-            }
         }
-        self.line += 1;
-        self.column = 0;
+        if self.keep_trivia {
+            let value = &self.lexeme();
+            self.tokens
+                .push_back(self.make_token(TokenType::NewLine, value, Literal::Nil));
+        }
+        self.next_line();
         Ok(())
     }
 
@@ -235,176 +301,238 @@ impl Scanner {
             self.advance();
         }
 
-        if TOKENIZE_SPACE {
-            let value = &self.source[self.start..self.current];
-            self.tokens.push(Token::new(
-                TokenType::Whitespace,
-                value,
-                Literal::Nil,
-                self.line,
-                self.column,
-            ));
+        if self.keep_trivia {
+            let value = &self.lexeme();
+            self.tokens
+                .push_back(self.make_token(TokenType::Whitespace, value, Literal::Nil));
         }
         Ok(())
     }
 
+    /**
+     * Scan the rest of a string literal, from just after its opening quote or after the `}` that
+     * closed an interpolated expression.
+     *
+     * A `${` ends the current piece with an `Interpolation` token, and scanning carries on with the
+     * embedded expression until its closing `}`.
+     */
     fn string(&mut self) -> Result<(), LexerError> {
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-                self.column = 0;
-            }
-            if self.peek() == '\\' {
-                // Skip over the escape character.
-                self.advance();
+            match self.advance() {
+                '\\' if !self.is_at_end() => self.escape(&mut value),
+                '$' if self.peek() == '{' => {
+                    self.advance();
+                    self.interpolations.push((self.location(), 0));
+                    self.tokens.push_back(self.make_token(
+                        TokenType::Interpolation,
+                        value.as_str(),
+                        Literal::String(value.clone()),
+                    ));
+                    return Ok(());
+                }
+                '\n' => {
+                    self.next_line();
+                    value.push('\n');
+                }
+                c => value.push(c),
             }
-            self.advance();
         }
         if self.is_at_end() {
-            return Err(LexerError::new(
-                "unterminated string",
-                self.line,
-                self.column,
-            ));
+            return Err(LexerError::at("unterminated string", &self.location()));
         }
 
         // The closing ".
         self.advance();
 
-        let value = &self.source[self.start + 1..self.current - 1];
-        // Trim the surrounding quotes.
-        let value = value
-            .replace("\\t", "\t")
-            .replace("\\n", "\n")
-            .replace("\\r", "\r")
-            .replace("\\\\", "\\")
-            .replace("\\", "\"");
-
-        self.tokens.push(Token::new(
+        self.tokens.push_back(self.make_token(
             TokenType::String,
             value.as_str(),
-            Literal::String(value.to_string()),
-            self.line,
-            self.column,
+            Literal::String(value.clone()),
         ));
         Ok(())
     }
 
-    fn number(&mut self) -> Result<(), LexerError> {
-        while self.is_digit(self.peek()) {
-            self.advance();
+    /**
+     * Decode the character after a backslash.  Unknown escapes are kept as they were written.
+     */
+    fn escape(&mut self, value: &mut String) {
+        match self.advance() {
+            't' => value.push('\t'),
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            '\\' => value.push('\\'),
+            '"' => value.push('"'),
+            '$' => value.push('$'),
+            c => {
+                if c == '\n' {
+                    self.next_line();
+                }
+                value.push('\\');
+                value.push(c);
+            }
         }
+    }
+
+    fn number(&mut self) -> Result<(), LexerError> {
+        self.digits()?;
 
         // Look for a fractional part.
         if self.peek() == '.' && self.is_digit(self.peek_next()) {
             // Consume the "."
             self.advance();
 
-            while self.is_digit(self.peek()) {
-                self.advance();
-            }
+            self.digits()?;
         }
 
-        let value = &self.source[self.start..self.current];
-        self.tokens.push(Token::new(
-            TokenType::Number,
-            value,
-            Literal::Number(value.parse().unwrap()),
-            self.line,
-            self.column,
-        ));
+        let value = &self.lexeme();
+        // Underscores are only visual separators.
+        let digits = value.replace('_', "");
+        let number = match digits.parse::<f64>() {
+            // Literals too large for an f64 parse to infinity rather than failing.
+            Ok(number) if number.is_finite() => number,
+            _ => return Err(LexerError::at("invalid number literal", &self.location())),
+        };
+        self.tokens
+            .push_back(self.make_token(TokenType::Number, value, Literal::Number(number)));
         Ok(())
     }
 
+    /**
+     * Consume a run of digits, allowing single underscores between them (e.g. `1_000_000`).
+     */
+    fn digits(&mut self) -> Result<(), LexerError> {
+        loop {
+            if self.is_digit(self.peek()) {
+                self.advance();
+            } else if self.peek() == '_' {
+                // A separator must sit between two digits.
+                let is_separator = self.is_digit(self.peek_next());
+                self.advance();
+                if !is_separator {
+                    return Err(LexerError::new(
+                        "invalid numeric separator",
+                        self.line,
+                        self.column,
+                    ));
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
     fn identifier(&mut self) -> Result<(), LexerError> {
         while self.is_alphanumeric(self.peek()) {
             self.advance();
         }
 
-        let text = &self.source[self.start..self.current];
-        let token_type = match text {
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "fun" => TokenType::Fun,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "let" => TokenType::Let,
-            "while" => TokenType::While,
-            "break" => TokenType::Break,
-            "continue" => TokenType::Continue,
-            _ => TokenType::Identifier,
-        };
+        let text = self.lexeme();
+        let token_type = TokenType::keyword(&text).unwrap_or(TokenType::Identifier);
 
         match token_type {
             TokenType::True => {
-                self.tokens.push(Token::new(
+                self.tokens.push_back(self.make_token(
                     TokenType::True,
                     "true",
                     Literal::Boolean(true),
-                    self.line,
-                    self.column,
                 ));
             }
             TokenType::False => {
-                self.tokens.push(Token::new(
+                self.tokens.push_back(self.make_token(
                     TokenType::False,
                     "false",
                     Literal::Boolean(false),
-                    self.line,
-                    self.column,
                 ));
             }
             TokenType::Nil => {
-                self.tokens.push(Token::new(
-                    TokenType::Nil,
-                    "nil",
-                    Literal::Nil,
-                    self.line,
-                    self.column,
-                ));
+                self.tokens
+                    .push_back(self.make_token(TokenType::Nil, "nil", Literal::Nil));
             }
             _ => {
-                self.tokens.push(Token::new(
+                self.tokens.push_back(self.make_token(
                     token_type,
-                    text,
-                    Literal::Identifier(text.to_string()),
-                    self.line,
-                    self.column,
+                    &text,
+                    Literal::Identifier(Symbol::intern(&text)),
                 ));
             }
         }
         Ok(())
     }
 
-    fn add_token(&mut self, token_type: TokenType) {
-        self.tokens.push(Token::new(
+    /**
+     * A loop label, like `'outer`.  The literal is the name without the quote.
+     */
+    fn label(&mut self) -> Result<(), LexerError> {
+        if !self.is_alpha(self.peek()) {
+            return Err(LexerError::at(
+                "expected a label name after '",
+                &self.location(),
+            ));
+        }
+        while self.is_alphanumeric(self.peek()) {
+            self.advance();
+        }
+
+        let text = self.lexeme();
+        let name = Symbol::intern(&text[1..]);
+        self.tokens
+            .push_back(self.make_token(TokenType::Label, &text, Literal::Identifier(name)));
+        Ok(())
+    }
+
+    /**
+     * The span of the token currently being scanned.
+     */
+    fn location(&self) -> FileLocation {
+        FileLocation::range(self.start_line, self.start_column, self.line, self.column)
+    }
+
+    /**
+     * The source text of the token being scanned.
+     */
+    fn lexeme(&self) -> String {
+        self.source[self.start..self.current].iter().collect()
+    }
+
+    fn make_token(&self, token_type: TokenType, lexeme: &str, literal: Literal) -> Token {
+        let loc = self.location();
+        Token::new(
             token_type,
-            "",
-            Literal::Nil,
-            self.line,
-            self.column,
-        ))
+            lexeme,
+            literal,
+            loc.get_line(),
+            loc.get_column(),
+            loc.get_end_line(),
+            loc.get_end_column(),
+        )
+    }
+
+    fn add_token(&mut self, token_type: TokenType) {
+        self.tokens
+            .push_back(self.make_token(token_type, "", Literal::Nil))
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
+        let c = self.source[self.current];
         self.current += 1;
         self.column += 1;
         c
     }
 
+    /**
+     * Call after consuming a line break so the next character starts at column 1.
+     */
+    fn next_line(&mut self) {
+        self.line += 1;
+        self.column = 0;
+    }
+
     fn match_next(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
         }
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.source[self.current] != expected {
             return false;
         }
         self.current += 1;
@@ -432,14 +560,14 @@ impl Scanner {
         if self.is_at_end() {
             return '\0';
         }
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current]
     }
 
     fn peek_next(&self) -> char {
         if self.current + 1 >= self.source.len() {
             return '\0';
         }
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current + 1]
     }
 
     fn is_at_end(&self) -> bool {
@@ -447,8 +575,200 @@ impl Scanner {
     }
 }
 
+/**
+ * Scans tokens one at a time, so a caller can handle each one as it comes, or stop early, without
+ * building the whole list.  The last token is `EOF`.  Iteration ends after `EOF` or the first
+ * error.
+ */
+pub struct TokenIter {
+    scanner: Scanner,
+    finished: bool,
+}
+
+impl TokenIter {
+    pub fn new(source: &str) -> Self {
+        Self {
+            scanner: Scanner::new(source.to_string()),
+            finished: false,
+        }
+    }
+
+    /**
+     * Also hand out `Whitespace`, `NewLine`, and `Comment` tokens, for tools that need the whole
+     * source back.  The parser skips them.
+     */
+    pub fn with_trivia(mut self, keep_trivia: bool) -> Self {
+        self.scanner.keep_trivia = keep_trivia;
+        self
+    }
+}
+
+impl Iterator for TokenIter {
+    type Item = Result<Token, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.scanner.tokens.pop_front() {
+                return Some(Ok(token));
+            }
+            if self.finished {
+                return None;
+            }
+            match self.scanner.scan_next() {
+                Ok(more) => self.finished = !more,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
 pub fn scan_tokens(source: &str) -> Result<Vec<Token>, LexerError> {
-    let mut scanner = Scanner::new(source.to_string());
-    scanner.scan_tokens()?;
-    Ok(scanner.tokens)
+    TokenIter::new(source).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_literal(source: &str) -> Literal {
+        scan_tokens(source).unwrap().remove(0).literal
+    }
+
+    fn position(token: &Token) -> (usize, usize) {
+        (token.get_line(), token.get_column())
+    }
+
+    #[test]
+    fn tokens_start_where_their_lexeme_starts() {
+        let tokens = scan_tokens("let   x").unwrap();
+        assert_eq!(position(&tokens[0]), (1, 1));
+        assert_eq!(position(&tokens[1]), (1, 7));
+    }
+
+    #[test]
+    fn columns_restart_after_a_newline() {
+        let tokens = scan_tokens("x;\n  yy").unwrap();
+        assert_eq!(position(&tokens[2]), (2, 3));
+        assert_eq!(tokens[2].get_end_column(), 4);
+    }
+
+    #[test]
+    fn every_newline_counts_as_a_line() {
+        let tokens = scan_tokens("a\n\n\nb").unwrap();
+        assert_eq!(position(&tokens[1]), (4, 1));
+
+        // A `\r\n` pair is a single line break.
+        let tokens = scan_tokens("a\r\n\r\nb").unwrap();
+        assert_eq!(position(&tokens[1]), (3, 1));
+    }
+
+    #[test]
+    fn number_separators_are_stripped() {
+        assert_eq!(first_literal("1_000.000_5"), Literal::Number(1000.0005));
+        assert_eq!(first_literal("1_000_000"), Literal::Number(1000000.0));
+    }
+
+    #[test]
+    fn trailing_separator_is_an_error() {
+        let err = scan_tokens("1_").unwrap_err();
+        assert_eq!(err.msg, "invalid numeric separator");
+        assert_eq!((err.get_line(), err.get_column()), (1, 2));
+    }
+
+    #[test]
+    fn doubled_separator_is_an_error() {
+        let err = scan_tokens("1__0").unwrap_err();
+        assert_eq!(err.msg, "invalid numeric separator");
+        assert_eq!((err.get_line(), err.get_column()), (1, 2));
+    }
+
+    #[test]
+    fn overflowing_number_is_an_error() {
+        let source = format!("let x = {};", "9".repeat(400));
+        let err = scan_tokens(&source).unwrap_err();
+        assert_eq!(err.msg, "invalid number literal");
+        assert_eq!((err.get_line(), err.get_column()), (1, 9));
+        assert_eq!(err.get_end_column(), 408);
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let tokens = scan_tokens("a /* outer /* inner */\n still outer */ b").unwrap();
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["a", "b", ""]);
+        assert_eq!(position(&tokens[1]), (2, 17));
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_an_error() {
+        let err = scan_tokens("a\n  /* open /* closed */ never closed").unwrap_err();
+        assert_eq!(err.msg, "unterminated block comment");
+        // The error points at the opening "/*".
+        assert_eq!((err.get_line(), err.get_column()), (2, 3));
+        assert_eq!(err.get_end_column(), 4);
+    }
+
+    #[test]
+    fn tokens_span_from_their_first_to_last_character() {
+        let tokens = scan_tokens("let name = \"ab\ncd\";").unwrap();
+        let span = |token: &Token| {
+            (
+                token.get_line(),
+                token.get_column(),
+                token.get_end_line(),
+                token.get_end_column(),
+            )
+        };
+        assert_eq!(span(&tokens[0]), (1, 1, 1, 3));
+        assert_eq!(span(&tokens[1]), (1, 5, 1, 8));
+        // A string that runs onto the next line ends there.
+        assert_eq!(span(&tokens[3]), (1, 12, 2, 3));
+        assert_eq!(span(&tokens[4]), (2, 4, 2, 4));
+    }
+
+    #[test]
+    fn trivia_is_kept_when_asked_for() {
+        let source = "let x = 1; // one\n/* two */ x";
+        let tokens = TokenIter::new(source)
+            .with_trivia(true)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let trivia = tokens
+            .iter()
+            .filter(|token| token.is_trivia())
+            .map(|token| token.lexeme.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trivia,
+            vec![" ", " ", " ", " ", "// one", "\n", "/* two */", " "]
+        );
+
+        // Without the trivia, the tokens are the usual ones.
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| !token.is_trivia())
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, scan_tokens(source).unwrap());
+    }
+
+    #[test]
+    fn token_iter_matches_scan_tokens() {
+        let source = "fun add(a, b) {\n  a + b // sum\n}\nprint add(1, 2.5) + \"x${1}\";";
+        let mut iter = TokenIter::new(source);
+        for expected in scan_tokens(source).unwrap() {
+            assert_eq!(iter.next().unwrap().unwrap(), expected);
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn token_iter_stops_after_an_error() {
+        let mut iter = TokenIter::new("let a = @;");
+        assert_eq!(iter.next().unwrap().unwrap().token_type, TokenType::Let);
+        assert!(iter.by_ref().any(|token| token.is_err()));
+        assert!(iter.next().is_none());
+    }
 }
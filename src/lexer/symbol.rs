@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display},
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+/**
+ * An interned identifier.  Every occurrence of the same name maps to the same symbol, so names
+ * can be copied and compared as integers instead of as strings.
+ *
+ * There's one interner for the whole process, so a symbol can be sent to another thread and still
+ * resolve to the same name there.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/**
+ * The names seen so far.  They're kept for the life of the process, so the interner only grows
+ * with the number of distinct identifiers, however many programs use them.
+ */
+#[derive(Default)]
+struct Interner {
+    names: Vec<Arc<str>>,
+    symbols: HashMap<Arc<str>, Symbol>,
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| RwLock::new(Interner::default()))
+}
+
+impl Symbol {
+    /**
+     * The symbol for `name`, adding it to the interner if it's new.
+     */
+    pub fn intern(name: &str) -> Self {
+        // Most names have been seen before, which only needs a read lock.
+        let symbols = interner().read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(symbol) = symbols.symbols.get(name) {
+            return *symbol;
+        }
+        drop(symbols);
+
+        let mut interner = interner().write().unwrap_or_else(PoisonError::into_inner);
+        // Another thread may have added it between the two locks.
+        if let Some(symbol) = interner.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(interner.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        interner.names.push(Arc::clone(&name));
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    /**
+     * The name this symbol was interned from.
+     */
+    pub fn resolve(self) -> Arc<str> {
+        let interner = interner().read().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&interner.names[self.0 as usize])
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.resolve())
+    }
+}
+
+// Show the name rather than the number, which means nothing outside the interner.
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.resolve())
+    }
+}
+
+// Symbols are only meaningful within one interner, so they're serialized by name.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.resolve())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Symbol::intern(&name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_names_share_a_symbol() {
+        assert_eq!(Symbol::intern("count"), Symbol::intern("count"));
+        assert_ne!(Symbol::intern("count"), Symbol::intern("total"));
+        assert_eq!(&*Symbol::intern("count").resolve(), "count");
+    }
+
+    #[test]
+    fn symbols_resolve_the_same_on_another_thread() {
+        let symbol = Symbol::intern("shared_name");
+        let (resolved, interned) =
+            std::thread::spawn(move || (symbol.resolve(), Symbol::intern("shared_name")))
+                .join()
+                .unwrap();
+        assert_eq!(&*resolved, "shared_name");
+        assert_eq!(interned, symbol);
+    }
+
+    #[test]
+    fn variables_are_found_by_symbol() {
+        let (_, result) =
+            crate::run_captured("let total = 1; { let total = 2; } total = total + 1; total");
+        assert!(matches!(result, Ok(crate::Object::Number(n)) if n == 2.0));
+    }
+}
@@ -9,6 +9,8 @@ pub struct Token {
     pub lexeme: String,
     line: usize,
     column: usize,
+    end_line: usize,
+    end_column: usize,
     pub literal: Literal,
 }
 
@@ -19,6 +21,8 @@ impl Token {
         literal: Literal,
         line: usize,
         column: usize,
+        end_line: usize,
+        end_column: usize,
     ) -> Token {
         Token {
             token_type,
@@ -26,8 +30,21 @@ impl Token {
             literal,
             line,
             column,
+            end_line,
+            end_column,
         }
     }
+
+    /**
+     * Whether this token is whitespace, a line break, or a comment, which the parser has no use
+     * for.
+     */
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.token_type,
+            TokenType::Whitespace | TokenType::NewLine | TokenType::Comment
+        )
+    }
 }
 
 impl HasFileLocation for Token {
@@ -38,6 +55,14 @@ impl HasFileLocation for Token {
     fn get_column(&self) -> usize {
         self.column
     }
+
+    fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.end_line
+    }
 }
 
 impl Display for Token {
@@ -14,13 +14,21 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
+    DotDot,
+    Ellipsis,
     Minus,
+    MinusEqual,
     Plus,
+    PlusEqual,
     Semicolon,
     Slash,
+    SlashEqual,
     Star,
+    StarEqual,
     Colon,
 
     // One or two character tokens.
@@ -42,7 +50,9 @@ pub enum TokenType {
     // Literals.
     Identifier,
     String,
+    Interpolation,
     Number,
+    Label,
 
     // Keywords.
     Class,
@@ -57,14 +67,55 @@ pub enum TokenType {
     Super,
     This,
     True,
-    Let, // Original spec used "var" here.
+    Let, // Original spec used "var" here, which is still accepted as a synonym.
+    Delete,
     While,
+    Loop,
+    In,
     Break,
     Continue,
 
     EOF,
 }
 
+impl TokenType {
+    /**
+     * The keyword spelled by `text`, if it is one.  Only the keywords sharing its first letter are
+     * compared, so most identifiers are ruled out by a single byte.
+     */
+    pub fn keyword(text: &str) -> Option<TokenType> {
+        let candidates: &[(&str, TokenType)] = match text.as_bytes().first()? {
+            b'b' => &[("break", TokenType::Break)],
+            b'c' => &[
+                ("class", TokenType::Class),
+                ("continue", TokenType::Continue),
+            ],
+            b'd' => &[("delete", TokenType::Delete)],
+            b'e' => &[("else", TokenType::Else)],
+            b'f' => &[
+                ("false", TokenType::False),
+                ("for", TokenType::For),
+                ("fun", TokenType::Fun),
+            ],
+            b'i' => &[("if", TokenType::If), ("in", TokenType::In)],
+            b'l' => &[("let", TokenType::Let), ("loop", TokenType::Loop)],
+            b'n' => &[("nil", TokenType::Nil)],
+            b'p' => &[("print", TokenType::Print)],
+            b'r' => &[("return", TokenType::Return)],
+            b's' => &[("super", TokenType::Super)],
+            b't' => &[("this", TokenType::This), ("true", TokenType::True)],
+            // `var` is the original spelling of `let`, and means the same thing.
+            b'v' => &[("var", TokenType::Let)],
+            b'w' => &[("while", TokenType::While)],
+            _ => return None,
+        };
+        candidates
+            .iter()
+            .find(|(keyword, _)| *keyword == text)
+            .map(|(_, token_type)| *token_type)
+    }
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
@@ -75,13 +126,21 @@ impl Display for TokenType {
             TokenType::RightParen => "RightParen",
             TokenType::LeftBrace => "LeftBrace",
             TokenType::RightBrace => "RightBrace",
+            TokenType::LeftBracket => "LeftBracket",
+            TokenType::RightBracket => "RightBracket",
             TokenType::Comma => "Comma",
             TokenType::Dot => "Dot",
+            TokenType::DotDot => "DotDot",
+            TokenType::Ellipsis => "Ellipsis",
             TokenType::Minus => "Minus",
+            TokenType::MinusEqual => "MinusEqual",
             TokenType::Plus => "Plus",
+            TokenType::PlusEqual => "PlusEqual",
             TokenType::Semicolon => "Semicolon",
             TokenType::Slash => "Slash",
+            TokenType::SlashEqual => "SlashEqual",
             TokenType::Star => "Star",
+            TokenType::StarEqual => "StarEqual",
             TokenType::QuestionMark => "QuestionMark",
             TokenType::DoubleQuestionMark => "DoubleQuestionMark",
             TokenType::Colon => "Colon",
@@ -95,7 +154,9 @@ impl Display for TokenType {
             TokenType::LessEqual => "LessEqual",
             TokenType::Identifier => "Identifier",
             TokenType::String => "String",
+            TokenType::Interpolation => "Interpolation",
             TokenType::Number => "Number",
+            TokenType::Label => "Label",
             TokenType::LogicalAnd => "LogicalAnd",
             TokenType::BitwiseAnd => "BitwiseAnd",
             TokenType::LogicalOr => "LogicalOr",
@@ -113,7 +174,10 @@ impl Display for TokenType {
             TokenType::This => "This",
             TokenType::True => "True",
             TokenType::Let => "Let",
+            TokenType::Delete => "Delete",
             TokenType::While => "While",
+            TokenType::Loop => "Loop",
+            TokenType::In => "In",
             TokenType::Break => "Break",
             TokenType::Continue => "Continue",
             TokenType::EOF => "EOF",
@@ -121,3 +185,99 @@ impl Display for TokenType {
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use TokenType::*;
+
+    const ALL: &[TokenType] = &[
+        Whitespace,
+        Comment,
+        NewLine,
+        LeftParen,
+        RightParen,
+        LeftBrace,
+        RightBrace,
+        LeftBracket,
+        RightBracket,
+        Comma,
+        Dot,
+        DotDot,
+        Ellipsis,
+        Minus,
+        MinusEqual,
+        Plus,
+        PlusEqual,
+        Semicolon,
+        Slash,
+        SlashEqual,
+        Star,
+        StarEqual,
+        Colon,
+        Bang,
+        BangEqual,
+        Equal,
+        EqualEqual,
+        Greater,
+        GreaterEqual,
+        Less,
+        LessEqual,
+        QuestionMark,
+        DoubleQuestionMark,
+        LogicalAnd,
+        BitwiseAnd,
+        LogicalOr,
+        BitwiseOr,
+        Identifier,
+        String,
+        Interpolation,
+        Number,
+        Label,
+        Class,
+        Else,
+        False,
+        Fun,
+        For,
+        If,
+        Nil,
+        Print,
+        Return,
+        Super,
+        This,
+        True,
+        Let,
+        Delete,
+        While,
+        Loop,
+        In,
+        Break,
+        Continue,
+        EOF,
+    ];
+
+    const KEYWORDS: &[TokenType] = &[
+        Class, Else, False, Fun, For, If, Nil, Print, Return, Super, This, True, Let, Delete,
+        While, Loop, In, Break, Continue,
+    ];
+
+    #[test]
+    fn every_token_type_displays_its_own_name() {
+        for token_type in ALL {
+            assert_eq!(token_type.to_string(), format!("{:?}", token_type));
+        }
+    }
+
+    #[test]
+    fn every_keyword_scans_back_to_its_token_type() {
+        for token_type in KEYWORDS {
+            assert!(ALL.contains(token_type));
+            let text = token_type.to_string().to_lowercase();
+            assert_eq!(TokenType::keyword(&text), Some(*token_type), "{}", text);
+        }
+        assert_eq!(TokenType::keyword("var"), Some(Let));
+        assert_eq!(TokenType::keyword("and"), None);
+        assert_eq!(TokenType::keyword("or"), None);
+    }
+}
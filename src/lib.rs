@@ -0,0 +1,43 @@
+/**
+ * An implementation of the Lox language from Crafting Interpreters.
+ *
+ * The binary is a thin command-line wrapper around this library, so the same scanner, parser, and
+ * interpreter can be embedded elsewhere.
+ */
+pub mod app_info;
+pub mod debug;
+pub mod interpreter;
+pub mod lexer;
+pub mod linter;
+pub mod optimizer;
+pub mod parser;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use debug::{Diagnostic, ErrorSet, LocatableError, Stage};
+pub use interpreter::{Interpreter, Object, OutputBuffer, RuntimeError};
+pub use lexer::{scan_tokens, LexerError, TokenIter};
+pub use parser::{parse, ParserError};
+
+/**
+ * Scan, parse, and evaluate `source` in a fresh interpreter, returning the final value.  The error
+ * is the `LexerError`, `ErrorSet`, or `RuntimeError` that stopped it, and can be downcast to tell
+ * which stage failed.
+ */
+pub fn run(source: &str) -> Result<Object, anyhow::Error> {
+    let tokens = scan_tokens(source)?;
+    let program = parse(&tokens)?;
+    Ok(Interpreter::new().execute(&program)?)
+}
+
+/**
+ * Like `run`, but capture what the program prints instead of writing it to stdout.  Returns the
+ * printed text along with the result, with any errors as diagnostics.
+ */
+pub fn run_captured(source: &str) -> (String, Result<Object, Vec<Diagnostic>>) {
+    let output = OutputBuffer::new();
+    let result = Interpreter::new()
+        .with_output(Box::new(output.clone()))
+        .eval_source(source);
+    (output.contents(), result)
+}
@@ -0,0 +1,339 @@
+use std::{cell::Cell, collections::HashSet, rc::Rc};
+
+use crate::{
+    debug::{Diagnostic, FileLocation, HasFileLocation, Stage},
+    lexer::Symbol,
+    parser::{DefaultVisitor, Expr, FunctionDecl, Stmt},
+};
+
+/**
+ * A local variable, and whether anything has read it yet.
+ */
+struct Binding {
+    name: Symbol,
+    loc: FileLocation,
+    used: bool,
+}
+
+/**
+ * Warns about suspicious code: `let` bindings that are never read, `let`s that shadow a variable
+ * from an enclosing scope, and assignments used as conditions, e.g. `if (x = 1)`.
+ *
+ * Scopes are tracked the same way the resolver does it.  Only locals are reported as unused, since
+ * a global might still be read by a later line in the REPL.  Names starting with `_` are never
+ * reported as unused, and wrapping an assignment in parentheses, e.g. `while ((x = next()))`, marks
+ * it as intended.
+ */
+pub struct Linter {
+    // The globals declared so far, which a local can shadow.
+    globals: HashSet<Symbol>,
+
+    scopes: Vec<Vec<Binding>>,
+
+    warnings: Vec<Diagnostic>,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Self {
+            globals: HashSet::new(),
+            scopes: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /**
+     * The warnings for `program`, in the order they appear in the source.
+     */
+    pub fn lint(&mut self, program: &[Stmt]) -> Vec<Diagnostic> {
+        for stmt in program {
+            stmt.accept(self);
+        }
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.sort_by_key(|warning| (warning.get_line(), warning.get_column()));
+        warnings
+    }
+
+    fn warn(&mut self, msg: &str, loc: &dyn HasFileLocation) {
+        self.warnings.push(Diagnostic::new(Stage::Lint, msg, loc));
+    }
+
+    /**
+     * Declare a `let` binding, which is checked for shadowing and later for being read.
+     */
+    fn declare_let(&mut self, name: Symbol, loc: &dyn HasFileLocation) {
+        let shadows = match self.scopes.split_last() {
+            Some((_, enclosing)) => {
+                self.globals.contains(&name)
+                    || enclosing
+                        .iter()
+                        .flatten()
+                        .any(|binding| binding.name == name)
+            }
+            None => false,
+        };
+        if shadows {
+            self.warn(
+                &format!("'{}' shadows a variable in an enclosing scope", name),
+                loc,
+            );
+        }
+        self.declare(name, loc, false);
+    }
+
+    /**
+     * Declare a name in the innermost scope.  Parameters, loop variables, classes, and functions
+     * are declared as already used, since they're only worth checking for being shadowed.
+     */
+    fn declare(&mut self, name: Symbol, loc: &dyn HasFileLocation, used: bool) {
+        match self.scopes.last_mut() {
+            Some(scope) => scope.push(Binding {
+                name,
+                loc: FileLocation::from_loc(loc),
+                used,
+            }),
+            None => {
+                self.globals.insert(name);
+            }
+        }
+    }
+
+    fn mark_used(&mut self, name: Symbol) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name));
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    fn report_unused(&mut self, binding: Binding) {
+        if !binding.used && !binding.name.resolve().starts_with('_') {
+            self.warn(&format!("unused variable '{}'", binding.name), &binding.loc);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn end_scope(&mut self) {
+        if let Some(scope) = self.scopes.pop() {
+            for binding in scope {
+                self.report_unused(binding);
+            }
+        }
+    }
+
+    /**
+     * The parentheses around an `if` or `while` condition are kept as a grouping, so it takes a
+     * second pair to mark an assignment as intended.
+     */
+    fn check_condition(&mut self, cond: &Expr) {
+        if let Expr::Grouping(_, inner) = cond {
+            if let Expr::Assign(loc, ..) = inner.as_ref() {
+                self.warn("assignment used as a condition; did you mean '=='?", loc);
+            }
+        }
+    }
+
+    /**
+     * Lint the parameters and body of a function in a new scope.  Each default is checked before
+     * its parameter is declared, as in the resolver.
+     */
+    fn lint_function(&mut self, decl: &FunctionDecl) {
+        self.begin_scope();
+        for (param, default) in decl.params.iter().zip(&decl.defaults) {
+            if let Some(default) = default {
+                default.accept(self);
+            }
+            self.declare(*param, &decl.loc, true);
+        }
+        if let Some(rest) = &decl.rest {
+            self.declare(*rest, &decl.loc, true);
+        }
+        for stmt in &decl.body {
+            stmt.accept(self);
+        }
+        self.end_scope();
+    }
+}
+
+impl DefaultVisitor for Linter {
+    fn visit_variable(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        name: &Symbol,
+        _depth: &Cell<Option<usize>>,
+    ) {
+        self.mark_used(*name);
+    }
+
+    fn visit_lambda(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        self.lint_function(decl);
+    }
+
+    fn visit_let(&mut self, loc: &dyn HasFileLocation, name: &Symbol, init: &Option<Box<Expr>>) {
+        if let Some(init) = init {
+            init.accept(self);
+        }
+        self.declare_let(*name, loc);
+    }
+
+    fn visit_delete(&mut self, _loc: &dyn HasFileLocation, name: &Symbol) {
+        let Some(scope) = self.scopes.last_mut() else {
+            self.globals.remove(name);
+            return;
+        };
+        if let Some(index) = scope.iter().rposition(|binding| binding.name == *name) {
+            let binding = scope.remove(index);
+            self.report_unused(binding);
+        }
+    }
+
+    fn visit_block(&mut self, _loc: &dyn HasFileLocation, stmts: &[Stmt]) {
+        self.begin_scope();
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+        self.end_scope();
+    }
+
+    fn visit_if(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) {
+        self.check_condition(cond);
+        cond.accept(self);
+        then.accept(self);
+        if let Some(else_) = else_ {
+            else_.accept(self);
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) {
+        self.check_condition(cond);
+        cond.accept(self);
+        body.accept(self);
+        if let Some(increment) = increment {
+            increment.accept(self);
+        }
+    }
+
+    fn visit_for_in(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        _label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        iterable.accept(self);
+        self.begin_scope();
+        self.declare(*name, loc, true);
+        body.accept(self);
+        self.end_scope();
+    }
+
+    fn visit_class(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) {
+        if let Some(superclass) = superclass {
+            superclass.accept(self);
+        }
+        self.declare(Symbol::intern(name), loc, true);
+
+        // Methods only see the globals, so the enclosing scopes are set aside.
+        let enclosing = std::mem::take(&mut self.scopes);
+        for method in methods {
+            self.lint_function(method);
+        }
+        self.scopes = enclosing;
+    }
+
+    fn visit_function_stmt(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        self.declare(Symbol::intern(&decl.name), loc, true);
+        self.lint_function(decl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{debug::HasFileLocation, linter::lint, parse, scan_tokens};
+
+    /**
+     * Each warning for `source`, with the line and column it points at.
+     */
+    fn warnings(source: &str) -> Vec<(String, usize, usize)> {
+        let program = parse(&scan_tokens(source).unwrap()).unwrap();
+        lint(&program)
+            .into_iter()
+            .map(|warning| {
+                (
+                    warning.msg.clone(),
+                    warning.get_line(),
+                    warning.get_column(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_locals_are_reported() {
+        assert_eq!(
+            warnings("fun f() {\n  let a = 1;\n  let b = 2;\n  b\n}"),
+            vec![("unused variable 'a'".to_string(), 2, 3)]
+        );
+        // Globals and names starting with `_` are left alone.
+        assert!(warnings("let a = 1;\n{ let _b = 2; }").is_empty());
+    }
+
+    #[test]
+    fn shadowing_is_reported() {
+        assert_eq!(
+            warnings("let a = 1;\n{\n  let a = 2;\n  print a;\n}"),
+            vec![(
+                "'a' shadows a variable in an enclosing scope".to_string(),
+                3,
+                3
+            )]
+        );
+    }
+
+    #[test]
+    fn assignments_used_as_conditions_are_reported() {
+        let msg = "assignment used as a condition; did you mean '=='?".to_string();
+        assert_eq!(
+            warnings("let a = 1;\nif (a = 2) { print a; }"),
+            vec![(msg.clone(), 2, 5)]
+        );
+        assert_eq!(
+            warnings("let a = 1;\nwhile (a = 0) { print a; }"),
+            vec![(msg, 2, 8)]
+        );
+        // Parentheses mark the assignment as meant.
+        assert!(warnings("let a = 1;\nif ((a = 2)) { print a; }").is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+mod lint_visitor;
+
+pub use lint_visitor::Linter;
+
+use crate::{debug::Diagnostic, parser::Stmt};
+
+/**
+ * Look for code that's allowed but probably not what was meant, like a variable that's never
+ * read.  The warnings don't stop the program from running.
+ */
+pub fn lint(program: &[Stmt]) -> Vec<Diagnostic> {
+    Linter::new().lint(program)
+}
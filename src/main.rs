@@ -1,29 +1,60 @@
-mod app_info;
-mod debug;
-mod interpreter;
-mod lexer;
-mod parser;
 mod repl;
 
-use app_info::AppInfo;
 use atty::Stream;
-use clap::{Arg, Command};
-use debug::{AstPrinter, LocatableError};
-use interpreter::{HasStopFlag, Interpreter};
-use lexer::scan_tokens;
-use parser::{parse, Expr};
-use std::fs::File;
-use std::io::{self, BufReader, Read};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use rlox::app_info::AppInfo;
+use rlox::debug::{AstPrinter, Diagnostic, DotPrinter, LocatableError, Stage};
+use rlox::interpreter::{HasStopFlag, Interpreter, Object, RuntimeError};
+use rlox::lexer::{self, scan_tokens};
+use rlox::linter::lint;
+use rlox::optimizer::optimize;
+use rlox::parser::{parse, Stmt};
+use signal_hook::consts::SIGINT;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-const REPORT_TOKENS: bool = false;
-const REPORT_AST: bool = false;
+// Exit codes follow `sysexits.h`, as in the reference Lox implementation.
+const EXIT_DATA_ERR: i32 = 65;
+const EXIT_SOFTWARE: i32 = 70;
+const EXIT_IO_ERR: i32 = 74;
 
 // Define a struct to represent the REPL state
 struct LoxState {
     interpreter: Interpreter,
+
+    // Set to leave the REPL.
     stop_flag: Arc<AtomicBool>,
+
+    // Set by Ctrl+C in the REPL, to cut a running `sleep` short.  Kept apart from `stop_flag`, so
+    // interrupting a program doesn't also quit.
+    interrupt_flag: Arc<AtomicBool>,
+
+    // Print the scanned tokens before evaluating.
+    report_tokens: bool,
+
+    // Print the parsed syntax tree before evaluating.
+    report_ast: bool,
+
+    // Print the syntax tree as Graphviz DOT instead of evaluating.
+    dot: bool,
+
+    // Fold constant expressions before evaluating.
+    optimize: bool,
+
+    // Warn about suspicious code, like unused variables, before evaluating.
+    lint: bool,
+
+    // Print results as JSON rather than as display text.
+    json: bool,
+
+    // Print errors as a JSON array of diagnostics on stderr, rather than as annotated source.
+    diagnostics_json: bool,
+
+    // The status passed to `exit` in the REPL, to end the process with once the terminal is restored.
+    exit_status: Option<i32>,
 }
 
 impl HasStopFlag for LoxState {
@@ -43,60 +74,239 @@ fn print_tokens(tokens: &Vec<lexer::Token>) {
     }
 }
 
-fn parse_line(input: &str) -> Result<Expr, anyhow::Error> {
+/**
+ * Print diagnostics to stderr as a JSON array.
+ */
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    let items: Vec<String> = diagnostics.iter().map(|d| d.to_json()).collect();
+    eprint!("[{}]\r\n", items.join(","));
+}
+
+/**
+ * Print warnings the same way errors are printed, without failing.
+ */
+fn report_warnings(warnings: &[Diagnostic], input: &str, state: &LoxState) {
+    if warnings.is_empty() {
+        return;
+    }
+    if state.diagnostics_json {
+        print_diagnostics(warnings);
+    } else {
+        for warning in warnings {
+            warning.warn(input);
+        }
+    }
+}
+
+fn parse_line(input: &str, state: &LoxState) -> Result<Vec<Stmt>, anyhow::Error> {
     let tokens = scan_tokens(input);
     match tokens {
         Ok(tokens) => {
-            if REPORT_TOKENS {
+            if state.report_tokens {
                 print_tokens(&tokens);
             }
-            let expr = parse(&tokens);
-            match expr {
-                Ok(expr) => {
-                    if REPORT_AST {
-                        print!("\r\nexpr: {}\r\n", AstPrinter::new().print(&expr));
+            let program = parse(&tokens);
+            match program {
+                Ok(program) => {
+                    // Lint before optimizing, since the optimizer can drop the code in question.
+                    if state.lint {
+                        report_warnings(&lint(&program), input, state);
+                    }
+                    let program = if state.optimize {
+                        optimize(&program)
+                    } else {
+                        program
+                    };
+                    if state.report_ast {
+                        print!(
+                            "\r\nexpr: {}\r\n",
+                            AstPrinter::new().print_program(&program)
+                        );
                     }
-                    return Ok(expr);
+                    return Ok(program);
                 }
                 Err(err) => {
-                    err.report(input);
+                    if state.diagnostics_json {
+                        print_diagnostics(&Diagnostic::from_set(Stage::Parser, &err));
+                    } else {
+                        err.report(input);
+                    }
                     return Err(anyhow::Error::new(err));
                     //).context("parsing error"));
                 }
             }
         }
         Err(err) => {
-            err.report(input);
+            if state.diagnostics_json {
+                print_diagnostics(&[Diagnostic::new(Stage::Lexer, &err.msg, &err)]);
+            } else {
+                err.report(input);
+            }
             return Err(anyhow::Error::new(err).context("lexing error"));
         }
     }
 }
 
+/**
+ * Scan, parse, and evaluate `input`, reporting any errors against the source text.
+ */
+fn run_source(input: &str, state: &mut LoxState) -> Result<Object, anyhow::Error> {
+    // The lexing/parsing errors are reported in `parse_line`.
+    let program = parse_line(input, state)?;
+    if state.dot {
+        print!("{}\r\n", DotPrinter::new().print_program(&program));
+        return Ok(Object::Nil);
+    }
+    match state.interpreter.execute(&program) {
+        Ok(value) => Ok(value),
+        Err(err) => {
+            // A call to `exit` isn't a failure worth reporting.
+            if err.exit_code().is_none() {
+                if state.diagnostics_json {
+                    print_diagnostics(&[Diagnostic::new(Stage::Runtime, &err.msg, &err)]);
+                } else {
+                    err.report(input);
+                }
+            }
+            Err(anyhow::Error::new(err).context("runtime error"))
+        }
+    }
+}
+
+/**
+ * Print the value a program ended with.  Statements like `let` and `print` evaluate to nil, which
+ * isn't worth printing unless the output is JSON.
+ */
+fn print_result(value: &Object, state: &LoxState) {
+    if matches!(value, Object::Nil) && !state.json {
+        return;
+    }
+    if state.json {
+        print!("{}\r\n", value.to_json());
+    } else {
+        print!("\r\n{}\r\n", value.repr());
+    }
+}
+
+/**
+ * Evaluate a line of REPL input.  It's parsed as a whole program, so a line like
+ * `let x = 1; let y = 2; print x + y;` runs every statement in turn, and the last one's value is
+ * what gets echoed.
+ */
 fn exec_line(input: &str, state: &mut LoxState) {
     if input.trim().is_empty() {
         return;
     }
 
-    let expr = parse_line(input);
-    match expr {
-        Ok(expr) => {
-            let result = state.interpreter.eval(&expr);
-            match result {
-                Ok(value) => {
-                    // print!("result: {}\r\n", value);
-                    print!("\r\n{}\r\n", value);
-                }
-                Err(err) => {
-                    err.report(input);
-                }
+    match run_source(input, state) {
+        Ok(value) => print_result(&value, state),
+        Err(err) => {
+            if let Some(code) = err
+                .downcast_ref::<RuntimeError>()
+                .and_then(|e| e.exit_code())
+            {
+                state.exit_status = Some(code);
+                state.trigger_stop();
             }
         }
-        Err(_err) => {
-            // The lexing/parsing errors were reported in the previous stages.
-            // eprint!("error: {}\r\n", err);
-            return;
+    }
+}
+
+/**
+ * Print how often each kind of node was visited, if tracing is on.
+ */
+fn print_trace(state: &LoxState) {
+    let report = state.interpreter.trace_report();
+    if report.is_empty() {
+        return;
+    }
+    eprint!("\r\ntrace:\r\n");
+    for (node, count) in report {
+        eprint!("\t{}: {}\r\n", node, count);
+    }
+}
+
+/**
+ * Handle a line typed into the REPL.  Lines starting with `.` can be meta-commands; anything
+ * else, including a `.` line that isn't one, is evaluated as Lox.
+ */
+fn repl_line(input: &str, state: &mut LoxState) {
+    // A Ctrl+C from before this line was entered shouldn't interrupt it.
+    state.interrupt_flag.store(false, Ordering::Relaxed);
+
+    let trimmed = input.trim();
+    let (command, arg) = match trimmed.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (trimmed, ""),
+    };
+    match command {
+        ".help" => {
+            print!("\r\n.help         show this message\r\n");
+            print!(".exit         leave the REPL\r\n");
+            print!(".clear        forget every variable that's been defined\r\n");
+            print!(".load <file>  run a file in the current session\r\n");
+        }
+        ".exit" => state.trigger_stop(),
+        ".clear" => state.interpreter.reset(),
+        ".load" if !arg.is_empty() => match fs::read_to_string(arg) {
+            Ok(source) => exec_line(&source, state),
+            Err(err) => eprint!("\r\nerror: cannot read {}: {}\r\n", arg, err),
+        },
+        _ => exec_line(input, state),
+    }
+}
+
+/**
+ * Run a complete script, printing the final result.
+ */
+fn run_script(input: &str, state: &mut LoxState) -> Result<(), anyhow::Error> {
+    let result = run_source(input, state);
+    if let Ok(value) = &result {
+        if !state.dot {
+            print_result(value, state);
         }
     }
+    print_trace(state);
+    result.map(|_| ())
+}
+
+/**
+ * Map a failed run to a process exit code: the status passed to `exit`, 70 for runtime errors,
+ * 74 for I/O errors, and 65 for anything caught while lexing or parsing.
+ */
+fn exit_code(err: &anyhow::Error) -> i32 {
+    if let Some(err) = err.downcast_ref::<RuntimeError>() {
+        err.exit_code().unwrap_or(EXIT_SOFTWARE)
+    } else if err.downcast_ref::<io::Error>().is_some() {
+        EXIT_IO_ERR
+    } else {
+        EXIT_DATA_ERR
+    }
+}
+
+/**
+ * End the process, making sure anything the program printed isn't lost in the buffer.
+ */
+fn exit(code: i32) -> ! {
+    let _ = io::stdout().flush();
+    process::exit(code);
+}
+
+fn run_file(file_path: &str, state: &mut LoxState) -> Result<(), anyhow::Error> {
+    let file = File::open(file_path)?;
+    let mut reader = BufReader::new(file);
+    let mut input = String::new();
+    reader.read_to_string(&mut input)?;
+    run_script(&input, state)
+}
+
+fn new_interpreter(matches: &ArgMatches, interrupt_flag: &Arc<AtomicBool>) -> Interpreter {
+    Interpreter::new()
+        .with_stop_flag(Arc::clone(interrupt_flag))
+        .with_strict_math(matches.get_flag("strict-math"))
+        .with_nan_eq(matches.get_flag("nan-eq"))
+        .with_file_access(matches.get_flag("allow-files"))
+        .with_trace(matches.get_flag("trace"))
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -111,30 +321,233 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .help("Path to a file with commands to execute line-by-line")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("eval")
+                .short('e')
+                .long("eval")
+                .value_name("CODE")
+                .help("Evaluate the given code and exit")
+                .num_args(1)
+                .conflicts_with("file"),
+        )
+        .arg(
+            Arg::new("tokens")
+                .long("tokens")
+                .help("Print the scanned tokens before evaluating")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ast")
+                .long("ast")
+                .help("Print the parsed syntax tree before evaluating")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dot")
+                .long("dot")
+                .help("Print the parsed syntax tree as Graphviz DOT instead of evaluating")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("optimize")
+                .long("optimize")
+                .help("Fold constant expressions before evaluating")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("lint")
+                .long("lint")
+                .help("Warn about unused variables, shadowing, and assignments used as conditions")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("strict-math")
+                .long("strict-math")
+                .help("Raise an error on division by zero instead of producing NaN")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("nan-eq")
+                .long("nan-eq")
+                .help("Make NaN == NaN true instead of following IEEE")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("allow-files")
+                .long("allow-files")
+                .help("Let scripts read and write files with read_file and write_file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .help("Count the syntax tree nodes visited, and print the counts when done")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("no-raw")
+                .long("no-raw")
+                .help("Read REPL input a line at a time, without raw mode or key handling")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("diagnostics")
+                .long("diagnostics")
+                .value_name("FORMAT")
+                .help("How to print errors: annotated source text, or a JSON array on stderr")
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print the final result as JSON")
+                .action(ArgAction::SetTrue),
+        )
         .get_matches();
 
+    let interrupt_flag = Arc::new(AtomicBool::new(false));
     let mut state = LoxState {
-        interpreter: Interpreter::new(),
+        interpreter: new_interpreter(&matches, &interrupt_flag),
         stop_flag: Arc::new(AtomicBool::new(false)),
+        interrupt_flag,
+        report_tokens: matches.get_flag("tokens"),
+        report_ast: matches.get_flag("ast"),
+        dot: matches.get_flag("dot"),
+        optimize: matches.get_flag("optimize"),
+        lint: matches.get_flag("lint"),
+        json: matches.get_flag("json"),
+        diagnostics_json: matches
+            .get_one::<String>("diagnostics")
+            .is_some_and(|format| format == "json"),
+        exit_status: None,
     };
 
-    if let Some(file_path) = matches.get_one::<String>("file") {
-        // If a file path is provided, read and process each line from the file
-        let file = File::open(file_path)?;
-        let mut reader = BufReader::new(file);
-        let mut input = String::new();
-        reader.read_to_string(&mut input)?;
-        exec_line(&input, &mut state);
+    if let Some(code) = matches.get_one::<String>("eval") {
+        // Evaluate a one-liner passed on the command line.
+        if let Err(err) = run_script(code, &mut state) {
+            exit(exit_code(&err));
+        }
+    } else if let Some(file_path) = matches.get_one::<String>("file") {
+        // If a file path is provided, run it as a single program.  Stdin is left alone, for the
+        // script's own `input()` calls.
+        if let Err(err) = run_file(file_path, &mut state) {
+            if let Some(err) = err.downcast_ref::<io::Error>() {
+                eprint!("error: cannot read {}: {}\r\n", file_path, err);
+            }
+            exit(exit_code(&err));
+        }
     } else if atty::is(Stream::Stdin) {
-        // If stdin is a terminal and no file is provided, start the REPL
-        repl::start(&mut exec_line, &mut state)?;
+        // If stdin is a terminal and no file is provided, start the REPL.  Ctrl+C interrupts a
+        // `sleep` instead of ending the process, unless it's pressed again before the next line.
+        signal_hook::flag::register_conditional_default(SIGINT, Arc::clone(&state.interrupt_flag))?;
+        signal_hook::flag::register(SIGINT, Arc::clone(&state.interrupt_flag))?;
+        if matches.get_flag("no-raw") {
+            repl::start_buffered(io::stdin().lock(), &mut repl_line, &mut state)?;
+        } else {
+            repl::start(&mut repl_line, &mut state)?;
+        }
+        if let Some(code) = state.exit_status {
+            exit(code);
+        }
     } else {
-        // If input is piped in, read lines from stdin
+        // If input is piped in and no file is given, the input is the program
         let stdin = io::stdin();
         let mut input = String::new();
         stdin.lock().read_to_string(&mut input)?;
-        exec_line(&input, &mut state);
+        if let Err(err) = run_script(&input, &mut state) {
+            exit(exit_code(&err));
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlox::OutputBuffer;
+
+    fn state() -> LoxState {
+        let interrupt_flag = Arc::new(AtomicBool::new(false));
+        LoxState {
+            interpreter: Interpreter::new()
+                .with_output(Box::new(OutputBuffer::new()))
+                .with_stop_flag(Arc::clone(&interrupt_flag)),
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            interrupt_flag,
+            report_tokens: false,
+            report_ast: false,
+            dot: false,
+            optimize: false,
+            lint: false,
+            json: false,
+            diagnostics_json: false,
+            exit_status: None,
+        }
+    }
+
+    fn has_variable(state: &LoxState, name: &str) -> bool {
+        state
+            .interpreter
+            .variables()
+            .iter()
+            .any(|(variable, _)| variable == name)
+    }
+
+    #[test]
+    fn clear_forgets_variables_but_keeps_builtins() {
+        let mut state = state();
+        repl_line("let a = 1;", &mut state);
+        assert!(has_variable(&state, "a"));
+
+        repl_line(".clear", &mut state);
+        assert!(!has_variable(&state, "a"));
+        assert!(has_variable(&state, "PI"));
+    }
+
+    #[test]
+    fn load_runs_a_file_in_the_current_session() {
+        let path = std::env::temp_dir().join(format!("rlox-load-{}.lox", process::id()));
+        fs::write(&path, "let loaded = a + 1;").unwrap();
+
+        let mut state = state();
+        repl_line("let a = 41;", &mut state);
+        repl_line(&format!(".load {}", path.display()), &mut state);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            state.interpreter.eval_source("loaded").unwrap(),
+            Object::Number(42.0)
+        );
+    }
+
+    #[test]
+    fn load_without_a_file_is_evaluated_as_lox() {
+        let mut state = state();
+        repl_line(".load", &mut state);
+        assert!(!state.is_stopped());
+        assert!(!has_variable(&state, "loaded"));
+    }
+
+    #[test]
+    fn buffered_repl_evaluates_each_line() {
+        let mut state = state();
+        let input = io::Cursor::new("let a = 1;\nlet b = a + 1;\n");
+        repl::start_buffered(input, &mut repl_line, &mut state).unwrap();
+        assert_eq!(
+            state.interpreter.eval_source("[a, b]").unwrap().to_string(),
+            "[1, 2]"
+        );
+    }
+
+    #[test]
+    fn interrupts_are_cleared_per_line_and_dont_quit() {
+        let mut state = state();
+        state.interrupt_flag.store(true, Ordering::Relaxed);
+        repl_line("let slept = sleep(1);", &mut state);
+        assert!(has_variable(&state, "slept"));
+        assert!(!state.is_stopped());
+    }
+}
@@ -0,0 +1,470 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    debug::HasFileLocation,
+    lexer::Symbol,
+    parser::{BinaryOp, Expr, FunctionDecl, Stmt, StmtVisitor, UnaryOp, Visitor},
+};
+
+/**
+ * Rebuilds a syntax tree with its constant sub-expressions evaluated, so `2 + 3 * 4` becomes `14`
+ * and `!true` becomes `false`.
+ *
+ * Once a condition has folded to a constant, the code it rules out is dropped too: `if (true) A
+ * else B` becomes `A`, a ternary keeps only the branch it would take, and `while (false) ...`
+ * goes away entirely.  The branch that's kept runs exactly as it would have, side effects and all.
+ *
+ * Only operations on literals are folded, and only when the result is certain to be what the
+ * interpreter would produce under any options.  That leaves alone:
+ * - anything involving a variable, call, assignment, or other expression that could have a side
+ *   effect;
+ * - division by zero and arithmetic that overflows, which are errors under `--strict-math`;
+ * - operations that would be runtime errors, like `1 - "a"`, so the error is still raised, with
+ *   its location, when the program runs.
+ */
+pub struct ConstantFolder;
+
+impl Default for ConstantFolder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstantFolder {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn fold_program(&mut self, program: &[Stmt]) -> Vec<Stmt> {
+        program.iter().map(|stmt| stmt.accept(self)).collect()
+    }
+
+    fn fold_exprs(&mut self, exprs: &[Expr]) -> Vec<Expr> {
+        exprs.iter().map(|e| e.accept(self)).collect()
+    }
+
+    fn fold_decl(&mut self, decl: &FunctionDecl) -> FunctionDecl {
+        let defaults = decl
+            .defaults
+            .iter()
+            .map(|default| default.as_ref().map(|d| d.accept(self)))
+            .collect();
+        FunctionDecl::new(
+            &decl.loc,
+            decl.name.clone(),
+            decl.params.clone(),
+            defaults,
+            decl.rest,
+            self.fold_program(&decl.body),
+        )
+    }
+
+    fn fold_numbers(loc: &dyn HasFileLocation, op: &BinaryOp, a: f64, b: f64) -> Option<Expr> {
+        let n = match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Sub => a - b,
+            BinaryOp::Mul => a * b,
+            BinaryOp::Div if b != 0.0 => a / b,
+            BinaryOp::Eq => return Some(Expr::boolean(loc, a == b)),
+            BinaryOp::Ne => return Some(Expr::boolean(loc, a != b)),
+            BinaryOp::Lt => return Some(Expr::boolean(loc, a < b)),
+            BinaryOp::Le => return Some(Expr::boolean(loc, a <= b)),
+            BinaryOp::Gt => return Some(Expr::boolean(loc, a > b)),
+            BinaryOp::Ge => return Some(Expr::boolean(loc, a >= b)),
+            _ => return None,
+        };
+        if n.is_finite() {
+            Some(Expr::number(loc, n))
+        } else {
+            None
+        }
+    }
+}
+
+/**
+ * Whether a literal is truthy, or `None` if `e` isn't a literal.
+ */
+pub fn constant_truthiness(e: &Expr) -> Option<bool> {
+    match e {
+        Expr::Number(_, n) => Some(*n != 0.0),
+        Expr::String(_, s) => Some(!s.is_empty()),
+        Expr::Boolean(_, b) => Some(*b),
+        Expr::Nil(_) => Some(false),
+        _ => None,
+    }
+}
+
+impl Visitor<Expr> for ConstantFolder {
+    fn visit_number(&mut self, loc: &dyn HasFileLocation, n: &f64) -> Expr {
+        Expr::number(loc, *n)
+    }
+
+    fn visit_string(&mut self, loc: &dyn HasFileLocation, s: &String) -> Expr {
+        Expr::string(loc, s.clone())
+    }
+
+    fn visit_boolean(&mut self, loc: &dyn HasFileLocation, b: &bool) -> Expr {
+        Expr::boolean(loc, *b)
+    }
+
+    fn visit_nil(&mut self, loc: &dyn HasFileLocation) -> Expr {
+        Expr::nil(loc)
+    }
+
+    fn visit_grouping(&mut self, loc: &dyn HasFileLocation, e: &Box<Expr>) -> Expr {
+        let e = e.accept(self);
+        if constant_truthiness(&e).is_some() {
+            e
+        } else {
+            Expr::grouping(loc, e)
+        }
+    }
+
+    fn visit_call(&mut self, loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) -> Expr {
+        let callee = callee.accept(self);
+        Expr::call(loc, callee, self.fold_exprs(args))
+    }
+
+    fn visit_method_call(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> Expr {
+        let receiver = receiver.accept(self);
+        Expr::method_call(loc, receiver, name.to_string(), self.fold_exprs(args))
+    }
+
+    fn visit_get(&mut self, loc: &dyn HasFileLocation, object: &Expr, name: &str) -> Expr {
+        Expr::get(loc, object.accept(self), name.to_string())
+    }
+
+    fn visit_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> Expr {
+        let object = object.accept(self);
+        Expr::set(loc, object, name.to_string(), value.accept(self))
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) -> Expr {
+        let object = object.accept(self);
+        Expr::compound_set(loc, object, name.to_string(), *op, value.accept(self))
+    }
+
+    fn visit_index(&mut self, loc: &dyn HasFileLocation, object: &Expr, index: &Expr) -> Expr {
+        let object = object.accept(self);
+        Expr::index(loc, object, index.accept(self))
+    }
+
+    fn visit_slice(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> Expr {
+        let object = object.accept(self);
+        let start = start.as_ref().map(|e| e.accept(self));
+        let end = end.as_ref().map(|e| e.accept(self));
+        Expr::slice(loc, object, start, end)
+    }
+
+    fn visit_list(&mut self, loc: &dyn HasFileLocation, items: &[Expr]) -> Expr {
+        Expr::list(loc, self.fold_exprs(items))
+    }
+
+    fn visit_interpolation(&mut self, loc: &dyn HasFileLocation, parts: &[Expr]) -> Expr {
+        Expr::interpolation(loc, self.fold_exprs(parts))
+    }
+
+    fn visit_range(&mut self, loc: &dyn HasFileLocation, start: &Expr, end: &Expr) -> Expr {
+        let start = start.accept(self);
+        Expr::range(loc, start, end.accept(self))
+    }
+
+    fn visit_ternary(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) -> Expr {
+        let cond = cond.accept(self);
+        match constant_truthiness(&cond) {
+            Some(true) => then.accept(self),
+            Some(false) => else_.accept(self),
+            None => {
+                let then = then.accept(self);
+                Expr::ternary(loc, cond, then, else_.accept(self))
+            }
+        }
+    }
+
+    fn visit_unary_op(&mut self, loc: &dyn HasFileLocation, op: &UnaryOp, e: &Box<Expr>) -> Expr {
+        let e = e.accept(self);
+        match (op, &e) {
+            (UnaryOp::Neg, Expr::Number(_, n)) => Expr::number(loc, -n),
+            (UnaryOp::Pos, Expr::Number(_, n)) => Expr::number(loc, *n),
+            (UnaryOp::Not, _) => match constant_truthiness(&e) {
+                Some(truthy) => Expr::boolean(loc, !truthy),
+                None => Expr::unary_op(loc, *op, e),
+            },
+            _ => Expr::unary_op(loc, *op, e),
+        }
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        op: &BinaryOp,
+        e1: &Box<Expr>,
+        e2: &Box<Expr>,
+    ) -> Expr {
+        let left = e1.accept(self);
+        let right = e2.accept(self);
+
+        // `&&` and `||` give back one of their operands, so a constant left side decides which.
+        if let (BinaryOp::LogicalAnd | BinaryOp::LogicalOr, Some(truthy)) =
+            (op, constant_truthiness(&left))
+        {
+            let keep_left = truthy == matches!(op, BinaryOp::LogicalOr);
+            return if keep_left { left } else { right };
+        }
+
+        let folded = match (op, &left, &right) {
+            (_, Expr::Number(_, a), Expr::Number(_, b)) => Self::fold_numbers(loc, op, *a, *b),
+            (BinaryOp::Add, Expr::String(_, a), Expr::String(_, b)) => {
+                Some(Expr::string(loc, format!("{}{}", a, b)))
+            }
+            (BinaryOp::Eq, Expr::String(_, a), Expr::String(_, b)) => {
+                Some(Expr::boolean(loc, a == b))
+            }
+            (BinaryOp::Ne, Expr::String(_, a), Expr::String(_, b)) => {
+                Some(Expr::boolean(loc, a != b))
+            }
+            (BinaryOp::Eq, Expr::Boolean(_, a), Expr::Boolean(_, b)) => {
+                Some(Expr::boolean(loc, a == b))
+            }
+            (BinaryOp::Ne, Expr::Boolean(_, a), Expr::Boolean(_, b)) => {
+                Some(Expr::boolean(loc, a != b))
+            }
+            _ => None,
+        };
+        folded.unwrap_or_else(|| Expr::binary_op(loc, left, *op, right))
+    }
+
+    fn visit_assign(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        _depth: &Cell<Option<usize>>,
+    ) -> Expr {
+        Expr::assign(loc, *name, expr.accept(self))
+    }
+
+    fn visit_variable(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        _depth: &Cell<Option<usize>>,
+    ) -> Expr {
+        Expr::variable(loc, *name)
+    }
+
+    fn visit_this(&mut self, loc: &dyn HasFileLocation, _depth: &Cell<Option<usize>>) -> Expr {
+        Expr::this(loc)
+    }
+
+    fn visit_super(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        method: &str,
+        _depth: &Cell<Option<usize>>,
+    ) -> Expr {
+        Expr::super_method(loc, method.to_string())
+    }
+
+    fn visit_lambda(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> Expr {
+        Expr::lambda(loc, self.fold_decl(decl))
+    }
+
+    fn visit_loop_expr(&mut self, loc: &dyn HasFileLocation, stmt: &Stmt) -> Expr {
+        Expr::loop_expr(loc, stmt.accept(self))
+    }
+}
+
+impl StmtVisitor<Stmt> for ConstantFolder {
+    fn visit_expr_stmt(&mut self, loc: &dyn HasFileLocation, expr: &Expr) -> Stmt {
+        Stmt::expr(loc, expr.accept(self))
+    }
+
+    fn visit_print(&mut self, loc: &dyn HasFileLocation, expr: &Expr) -> Stmt {
+        Stmt::print(loc, expr.accept(self))
+    }
+
+    fn visit_let(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        init: &Option<Box<Expr>>,
+    ) -> Stmt {
+        Stmt::let_stmt(loc, *name, init.as_ref().map(|e| e.accept(self)))
+    }
+
+    fn visit_delete(&mut self, loc: &dyn HasFileLocation, name: &Symbol) -> Stmt {
+        Stmt::delete_stmt(loc, *name)
+    }
+
+    fn visit_block(&mut self, loc: &dyn HasFileLocation, stmts: &[Stmt]) -> Stmt {
+        Stmt::block(loc, self.fold_program(stmts))
+    }
+
+    fn visit_if(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) -> Stmt {
+        let cond = cond.accept(self);
+        // The branches don't get scopes of their own, so the one that's taken can stand in for the
+        // whole `if`.  With no `else`, a false condition leaves just the `nil` the `if` gives back.
+        match constant_truthiness(&cond) {
+            Some(true) => then.accept(self),
+            Some(false) => match else_ {
+                Some(else_) => else_.accept(self),
+                None => Stmt::expr(loc, Expr::nil(loc)),
+            },
+            None => {
+                let then = then.accept(self);
+                Stmt::if_stmt(loc, cond, then, else_.as_ref().map(|s| s.accept(self)))
+            }
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> Stmt {
+        let cond = cond.accept(self);
+        // A loop that never runs evaluates to `nil`.
+        if constant_truthiness(&cond) == Some(false) {
+            return Stmt::expr(loc, Expr::nil(loc));
+        }
+        Stmt::while_stmt(
+            loc,
+            *label,
+            cond,
+            body.accept(self),
+            increment.as_ref().map(|increment| increment.accept(self)),
+        )
+    }
+
+    fn visit_loop(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        body: &Stmt,
+    ) -> Stmt {
+        Stmt::loop_stmt(loc, *label, body.accept(self))
+    }
+
+    fn visit_for_in(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> Stmt {
+        let iterable = iterable.accept(self);
+        Stmt::for_in_stmt(loc, *label, *name, iterable, body.accept(self))
+    }
+
+    fn visit_break(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) -> Stmt {
+        Stmt::break_stmt(loc, *label, value.as_ref().map(|e| e.accept(self)))
+    }
+
+    fn visit_continue(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>) -> Stmt {
+        Stmt::continue_stmt(loc, *label)
+    }
+
+    fn visit_class(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) -> Stmt {
+        let superclass = superclass.as_ref().map(|e| e.accept(self));
+        let methods = methods.iter().map(|m| self.fold_decl(m)).collect();
+        Stmt::class(loc, name.to_string(), superclass, methods)
+    }
+
+    fn visit_function_stmt(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> Stmt {
+        Stmt::function(loc, self.fold_decl(decl))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{debug::AstPrinter, optimizer::optimize, parse, parser::Stmt, scan_tokens};
+
+    fn print(program: &[Stmt]) -> String {
+        AstPrinter::new().print_program(program)
+    }
+
+    /**
+     * Check that `source` optimizes to the same tree `expected` parses to.
+     */
+    fn assert_folds_to(source: &str, expected: &str) {
+        let program = parse(&scan_tokens(source).unwrap()).unwrap();
+        let expected = parse(&scan_tokens(expected).unwrap()).unwrap();
+        assert_eq!(print(&optimize(&program)), print(&expected), "{}", source);
+    }
+
+    #[test]
+    fn arithmetic_on_literals_is_folded() {
+        assert_folds_to("2 + 3 * 4;", "14;");
+        assert_folds_to("print !true;", "print false;");
+        assert_folds_to("\"a\" + \"b\";", "\"ab\";");
+    }
+
+    #[test]
+    fn impure_and_failing_expressions_are_left_alone() {
+        assert_folds_to("print f(1 + 1);", "print f(2);");
+        assert_folds_to("x = 2 + 2;", "x = 4;");
+        assert_folds_to("1 - \"a\";", "1 - \"a\";");
+        assert_folds_to("1 / 0;", "1 / 0;");
+    }
+
+    #[test]
+    fn constant_conditions_keep_only_the_branch_taken() {
+        assert_folds_to("if (true) { a; } else { b; }", "{ a; }");
+        assert_folds_to("if (false) { a; } else { b; }", "{ b; }");
+        assert_folds_to("print 1 > 2 ? a : b;", "print b;");
+        assert_folds_to("while (false) { print 1; }", "nil;");
+    }
+}
@@ -0,0 +1,13 @@
+mod constant_folder;
+
+pub use constant_folder::{constant_truthiness, ConstantFolder};
+
+use crate::parser::Stmt;
+
+/**
+ * Simplify a parsed program before it runs, folding constants and dropping code that can never
+ * run.  The result behaves the same as the original; it just has less left to evaluate.
+ */
+pub fn optimize(program: &[Stmt]) -> Vec<Stmt> {
+    ConstantFolder::new().fold_program(program)
+}
@@ -7,7 +7,8 @@ use crate::lexer::{Token, TokenType};
 
 use super::ParserError;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -19,6 +20,7 @@ pub enum BinaryOp {
     Le,
     Gt,
     Ge,
+    In,
     LogicalAnd,
     LogicalOr,
 }
@@ -36,6 +38,7 @@ impl BinaryOp {
             TokenType::LessEqual => Ok(BinaryOp::Le),
             TokenType::Greater => Ok(BinaryOp::Gt),
             TokenType::GreaterEqual => Ok(BinaryOp::Ge),
+            TokenType::In => Ok(BinaryOp::In),
             TokenType::LogicalAnd => Ok(BinaryOp::LogicalAnd),
             TokenType::LogicalOr => Ok(BinaryOp::LogicalOr),
             _ => Err(ParserError::unexpected_token(token)),
@@ -56,6 +59,7 @@ impl Display for BinaryOp {
             BinaryOp::Le => write!(f, "<="),
             BinaryOp::Gt => write!(f, ">"),
             BinaryOp::Ge => write!(f, ">="),
+            BinaryOp::In => write!(f, "in"),
             BinaryOp::LogicalAnd => write!(f, "&&"),
             BinaryOp::LogicalOr => write!(f, "||"),
         }
@@ -77,6 +81,7 @@ impl FromStr for BinaryOp {
             "<=" => Ok(BinaryOp::Le),
             ">" => Ok(BinaryOp::Gt),
             ">=" => Ok(BinaryOp::Ge),
+            "in" => Ok(BinaryOp::In),
             "&&" => Ok(BinaryOp::LogicalAnd),
             "||" => Ok(BinaryOp::LogicalOr),
             _ => Err(ParserError::invalid_op(s)),
@@ -0,0 +1,530 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{debug::HasFileLocation, lexer::Symbol};
+
+use super::{BinaryOp, Expr, FunctionDecl, Stmt, StmtVisitor, UnaryOp, Visitor};
+
+/**
+ * A visitor for passes that only care about a few kinds of node, like a linter.  Every method
+ * walks into the node's children by default, so an implementer only overrides the ones it's
+ * interested in, and gets `Visitor<()>` and `StmtVisitor<()>` for free.
+ *
+ * An override that still wants the children visited has to walk them itself.
+ */
+pub trait DefaultVisitor: Sized {
+    /**
+     * The defaults and body of a function, shared by lambdas and class methods.
+     */
+    fn visit_function(&mut self, decl: &FunctionDecl) {
+        for default in decl.defaults.iter().flatten() {
+            default.accept(self);
+        }
+        for stmt in &decl.body {
+            stmt.accept(self);
+        }
+    }
+
+    fn visit_number(&mut self, _loc: &dyn HasFileLocation, _n: &f64) {}
+
+    fn visit_string(&mut self, _loc: &dyn HasFileLocation, _s: &str) {}
+
+    fn visit_boolean(&mut self, _loc: &dyn HasFileLocation, _b: &bool) {}
+
+    fn visit_nil(&mut self, _loc: &dyn HasFileLocation) {}
+
+    fn visit_grouping(&mut self, _loc: &dyn HasFileLocation, e: &Expr) {
+        e.accept(self);
+    }
+
+    fn visit_call(&mut self, _loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) {
+        callee.accept(self);
+        for arg in args {
+            arg.accept(self);
+        }
+    }
+
+    fn visit_method_call(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        _name: &str,
+        args: &[Expr],
+    ) {
+        receiver.accept(self);
+        for arg in args {
+            arg.accept(self);
+        }
+    }
+
+    fn visit_get(&mut self, _loc: &dyn HasFileLocation, object: &Expr, _name: &str) {
+        object.accept(self);
+    }
+
+    fn visit_set(&mut self, _loc: &dyn HasFileLocation, object: &Expr, _name: &str, value: &Expr) {
+        object.accept(self);
+        value.accept(self);
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        _name: &str,
+        _op: &BinaryOp,
+        value: &Expr,
+    ) {
+        object.accept(self);
+        value.accept(self);
+    }
+
+    fn visit_index(&mut self, _loc: &dyn HasFileLocation, object: &Expr, index: &Expr) {
+        object.accept(self);
+        index.accept(self);
+    }
+
+    fn visit_slice(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) {
+        object.accept(self);
+        if let Some(start) = start {
+            start.accept(self);
+        }
+        if let Some(end) = end {
+            end.accept(self);
+        }
+    }
+
+    fn visit_list(&mut self, _loc: &dyn HasFileLocation, items: &[Expr]) {
+        for item in items {
+            item.accept(self);
+        }
+    }
+
+    fn visit_interpolation(&mut self, _loc: &dyn HasFileLocation, parts: &[Expr]) {
+        for part in parts {
+            part.accept(self);
+        }
+    }
+
+    fn visit_range(&mut self, _loc: &dyn HasFileLocation, start: &Expr, end: &Expr) {
+        start.accept(self);
+        end.accept(self);
+    }
+
+    fn visit_ternary(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) {
+        cond.accept(self);
+        then.accept(self);
+        else_.accept(self);
+    }
+
+    fn visit_unary_op(&mut self, _loc: &dyn HasFileLocation, _op: &UnaryOp, e: &Expr) {
+        e.accept(self);
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _op: &BinaryOp,
+        e1: &Expr,
+        e2: &Expr,
+    ) {
+        e1.accept(self);
+        e2.accept(self);
+    }
+
+    fn visit_assign(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _name: &Symbol,
+        expr: &Expr,
+        _depth: &Cell<Option<usize>>,
+    ) {
+        expr.accept(self);
+    }
+
+    fn visit_variable(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _name: &Symbol,
+        _depth: &Cell<Option<usize>>,
+    ) {
+    }
+
+    fn visit_this(&mut self, _loc: &dyn HasFileLocation, _depth: &Cell<Option<usize>>) {}
+
+    fn visit_super(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _method: &str,
+        _depth: &Cell<Option<usize>>,
+    ) {
+    }
+
+    fn visit_lambda(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        self.visit_function(decl);
+    }
+
+    fn visit_loop_expr(&mut self, _loc: &dyn HasFileLocation, stmt: &Stmt) {
+        stmt.accept(self);
+    }
+
+    fn visit_expr_stmt(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_print(&mut self, _loc: &dyn HasFileLocation, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn visit_let(&mut self, _loc: &dyn HasFileLocation, _name: &Symbol, init: &Option<Box<Expr>>) {
+        if let Some(init) = init {
+            init.accept(self);
+        }
+    }
+
+    fn visit_delete(&mut self, _loc: &dyn HasFileLocation, _name: &Symbol) {}
+
+    fn visit_block(&mut self, _loc: &dyn HasFileLocation, stmts: &[Stmt]) {
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+    }
+
+    fn visit_if(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) {
+        cond.accept(self);
+        then.accept(self);
+        if let Some(else_) = else_ {
+            else_.accept(self);
+        }
+    }
+
+    fn visit_while(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) {
+        cond.accept(self);
+        body.accept(self);
+        if let Some(increment) = increment {
+            increment.accept(self);
+        }
+    }
+
+    fn visit_loop(&mut self, _loc: &dyn HasFileLocation, _label: &Option<Symbol>, body: &Stmt) {
+        body.accept(self);
+    }
+
+    fn visit_for_in(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _label: &Option<Symbol>,
+        _name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        iterable.accept(self);
+        body.accept(self);
+    }
+
+    fn visit_break(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) {
+        if let Some(value) = value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_continue(&mut self, _loc: &dyn HasFileLocation, _label: &Option<Symbol>) {}
+
+    fn visit_class(
+        &mut self,
+        _loc: &dyn HasFileLocation,
+        _name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) {
+        if let Some(superclass) = superclass {
+            superclass.accept(self);
+        }
+        for method in methods {
+            self.visit_function(method);
+        }
+    }
+
+    fn visit_function_stmt(&mut self, _loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        self.visit_function(decl);
+    }
+}
+
+impl<T: DefaultVisitor> Visitor<()> for T {
+    fn visit_number(&mut self, loc: &dyn HasFileLocation, n: &f64) {
+        DefaultVisitor::visit_number(self, loc, n)
+    }
+
+    fn visit_string(&mut self, loc: &dyn HasFileLocation, s: &String) {
+        DefaultVisitor::visit_string(self, loc, s)
+    }
+
+    fn visit_boolean(&mut self, loc: &dyn HasFileLocation, b: &bool) {
+        DefaultVisitor::visit_boolean(self, loc, b)
+    }
+
+    fn visit_nil(&mut self, loc: &dyn HasFileLocation) {
+        DefaultVisitor::visit_nil(self, loc)
+    }
+
+    fn visit_grouping(&mut self, loc: &dyn HasFileLocation, e: &Box<Expr>) {
+        DefaultVisitor::visit_grouping(self, loc, e)
+    }
+
+    fn visit_call(&mut self, loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) {
+        DefaultVisitor::visit_call(self, loc, callee, args)
+    }
+
+    fn visit_method_call(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) {
+        DefaultVisitor::visit_method_call(self, loc, receiver, name, args)
+    }
+
+    fn visit_get(&mut self, loc: &dyn HasFileLocation, object: &Expr, name: &str) {
+        DefaultVisitor::visit_get(self, loc, object, name)
+    }
+
+    fn visit_set(&mut self, loc: &dyn HasFileLocation, object: &Expr, name: &str, value: &Expr) {
+        DefaultVisitor::visit_set(self, loc, object, name, value)
+    }
+
+    fn visit_compound_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) {
+        DefaultVisitor::visit_compound_set(self, loc, object, name, op, value)
+    }
+
+    fn visit_index(&mut self, loc: &dyn HasFileLocation, object: &Expr, index: &Expr) {
+        DefaultVisitor::visit_index(self, loc, object, index)
+    }
+
+    fn visit_slice(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) {
+        DefaultVisitor::visit_slice(self, loc, object, start, end)
+    }
+
+    fn visit_list(&mut self, loc: &dyn HasFileLocation, items: &[Expr]) {
+        DefaultVisitor::visit_list(self, loc, items)
+    }
+
+    fn visit_interpolation(&mut self, loc: &dyn HasFileLocation, parts: &[Expr]) {
+        DefaultVisitor::visit_interpolation(self, loc, parts)
+    }
+
+    fn visit_range(&mut self, loc: &dyn HasFileLocation, start: &Expr, end: &Expr) {
+        DefaultVisitor::visit_range(self, loc, start, end)
+    }
+
+    fn visit_ternary(&mut self, loc: &dyn HasFileLocation, cond: &Expr, then: &Expr, else_: &Expr) {
+        DefaultVisitor::visit_ternary(self, loc, cond, then, else_)
+    }
+
+    fn visit_unary_op(&mut self, loc: &dyn HasFileLocation, op: &UnaryOp, e: &Box<Expr>) {
+        DefaultVisitor::visit_unary_op(self, loc, op, e)
+    }
+
+    fn visit_binary_op(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        op: &BinaryOp,
+        e1: &Box<Expr>,
+        e2: &Box<Expr>,
+    ) {
+        DefaultVisitor::visit_binary_op(self, loc, op, e1, e2)
+    }
+
+    fn visit_assign(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        depth: &Cell<Option<usize>>,
+    ) {
+        DefaultVisitor::visit_assign(self, loc, name, expr, depth)
+    }
+
+    fn visit_variable(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        depth: &Cell<Option<usize>>,
+    ) {
+        DefaultVisitor::visit_variable(self, loc, name, depth)
+    }
+
+    fn visit_this(&mut self, loc: &dyn HasFileLocation, depth: &Cell<Option<usize>>) {
+        DefaultVisitor::visit_this(self, loc, depth)
+    }
+
+    fn visit_super(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        method: &str,
+        depth: &Cell<Option<usize>>,
+    ) {
+        DefaultVisitor::visit_super(self, loc, method, depth)
+    }
+
+    fn visit_lambda(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        DefaultVisitor::visit_lambda(self, loc, decl)
+    }
+
+    fn visit_loop_expr(&mut self, loc: &dyn HasFileLocation, stmt: &Stmt) {
+        DefaultVisitor::visit_loop_expr(self, loc, stmt)
+    }
+}
+
+impl<T: DefaultVisitor> StmtVisitor<()> for T {
+    fn visit_expr_stmt(&mut self, loc: &dyn HasFileLocation, expr: &Expr) {
+        DefaultVisitor::visit_expr_stmt(self, loc, expr)
+    }
+
+    fn visit_print(&mut self, loc: &dyn HasFileLocation, expr: &Expr) {
+        DefaultVisitor::visit_print(self, loc, expr)
+    }
+
+    fn visit_let(&mut self, loc: &dyn HasFileLocation, name: &Symbol, init: &Option<Box<Expr>>) {
+        DefaultVisitor::visit_let(self, loc, name, init)
+    }
+
+    fn visit_delete(&mut self, loc: &dyn HasFileLocation, name: &Symbol) {
+        DefaultVisitor::visit_delete(self, loc, name)
+    }
+
+    fn visit_block(&mut self, loc: &dyn HasFileLocation, stmts: &[Stmt]) {
+        DefaultVisitor::visit_block(self, loc, stmts)
+    }
+
+    fn visit_if(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) {
+        DefaultVisitor::visit_if(self, loc, cond, then, else_)
+    }
+
+    fn visit_while(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) {
+        DefaultVisitor::visit_while(self, loc, label, cond, body, increment)
+    }
+
+    fn visit_loop(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>, body: &Stmt) {
+        DefaultVisitor::visit_loop(self, loc, label, body)
+    }
+
+    fn visit_for_in(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) {
+        DefaultVisitor::visit_for_in(self, loc, label, name, iterable, body)
+    }
+
+    fn visit_break(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) {
+        DefaultVisitor::visit_break(self, loc, label, value)
+    }
+
+    fn visit_continue(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>) {
+        DefaultVisitor::visit_continue(self, loc, label)
+    }
+
+    fn visit_class(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) {
+        DefaultVisitor::visit_class(self, loc, name, superclass, methods)
+    }
+
+    fn visit_function_stmt(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) {
+        DefaultVisitor::visit_function_stmt(self, loc, decl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::scan_tokens, parser::parse};
+
+    #[derive(Default)]
+    struct NumberCounter {
+        count: usize,
+    }
+
+    impl DefaultVisitor for NumberCounter {
+        fn visit_number(&mut self, _loc: &dyn HasFileLocation, _n: &f64) {
+            self.count += 1;
+        }
+    }
+
+    #[test]
+    fn overriding_one_method_still_walks_the_whole_tree() {
+        let source = "let f = fun (a, b = 2) { a * b + 3 };\nprint [f(1), \"x\", -4][0];";
+        let mut counter = NumberCounter::default();
+        for stmt in parse(&scan_tokens(source).unwrap()).unwrap() {
+            stmt.accept(&mut counter);
+        }
+        assert_eq!(counter.count, 5);
+    }
+}
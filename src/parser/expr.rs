@@ -1,32 +1,52 @@
+use std::{cell::Cell, rc::Rc};
+
 use crate::{
     debug::{FileLocation, HasFileLocation},
-    lexer::Literal,
+    lexer::{Literal, Symbol},
 };
 
-use super::{BinaryOp, UnaryOp, Visitor};
+use super::{BinaryOp, FunctionDecl, Stmt, UnaryOp, Visitor};
 
-#[derive(Debug, Clone)]
+/**
+ * An expression.  Two trees are equal when they have the same shape and values, and also the same
+ * locations and resolved depths.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Number(FileLocation, f64),
     String(FileLocation, String),
     Boolean(FileLocation, bool),
     Nil(FileLocation),
     Grouping(FileLocation, Box<Expr>),
-    Variable(FileLocation, String),
+    List(FileLocation, Box<Vec<Expr>>),
+    Call(FileLocation, Box<Expr>, Box<Vec<Expr>>),
+    MethodCall(FileLocation, Box<Expr>, String, Box<Vec<Expr>>),
+    Get(FileLocation, Box<Expr>, String),
+    Set(FileLocation, Box<Expr>, String, Box<Expr>),
+    // `object.name += value`, kept whole so the object is only evaluated once.
+    CompoundSet(FileLocation, Box<Expr>, String, BinaryOp, Box<Expr>),
+    Index(FileLocation, Box<Expr>, Box<Expr>),
+    // Either end of a slice can be left out, meaning the start or end of the list or string.
+    Slice(
+        FileLocation,
+        Box<Expr>,
+        Option<Box<Expr>>,
+        Option<Box<Expr>>,
+    ),
+    Interpolation(FileLocation, Box<Vec<Expr>>),
+    Range(FileLocation, Box<Expr>, Box<Expr>),
+    Ternary(FileLocation, Box<Expr>, Box<Expr>, Box<Expr>),
+    // The depth is filled in by the resolver: how many scopes out the variable lives, or `None` for a global.
+    Variable(FileLocation, Symbol, Cell<Option<usize>>),
     UnaryOp(FileLocation, UnaryOp, Box<Expr>),
     BinaryOp(FileLocation, Box<Expr>, BinaryOp, Box<Expr>),
-
-    Print(FileLocation, Box<Expr>),
-    If(FileLocation, Box<Expr>, Box<Expr>, Option<Box<Expr>>),
-    Program(FileLocation, Box<Vec<Expr>>),
-    // TODO: Not sure that Let and LetInit need to be separate entities.
-    Let(FileLocation, String),
-    LetInit(FileLocation, String, Box<Expr>),
-    Assign(FileLocation, String, Box<Expr>),
-    Block(FileLocation, Box<Vec<Expr>>),
-    While(FileLocation, Box<Expr>, Box<Expr>),
-    Break(FileLocation),
-    Continue(FileLocation),
+    Assign(FileLocation, Symbol, Box<Expr>, Cell<Option<usize>>),
+    This(FileLocation, Cell<Option<usize>>),
+    Super(FileLocation, String, Cell<Option<usize>>),
+    Lambda(FileLocation, Rc<FunctionDecl>),
+    // A `loop` statement used for the value its `break` gives.
+    Loop(FileLocation, Box<Stmt>),
 }
 
 impl Expr {
@@ -42,8 +62,8 @@ impl Expr {
         Self::Boolean(FileLocation::from_loc(loc), b)
     }
 
-    pub fn variable(loc: &dyn HasFileLocation, v: String) -> Self {
-        Self::Variable(FileLocation::from_loc(loc), v)
+    pub fn variable(loc: &dyn HasFileLocation, v: Symbol) -> Self {
+        Self::Variable(FileLocation::from_loc(loc), v, Cell::new(None))
     }
 
     pub fn literal(loc: &dyn HasFileLocation, l: Literal) -> Self {
@@ -64,73 +84,131 @@ impl Expr {
         Self::Grouping(FileLocation::from_loc(loc), Box::new(e))
     }
 
-    pub fn unary_op(loc: &dyn HasFileLocation, op: UnaryOp, e: Expr) -> Self {
-        Self::UnaryOp(FileLocation::from_loc(loc), op, Box::new(e))
+    pub fn call(loc: &dyn HasFileLocation, callee: Expr, args: Vec<Expr>) -> Self {
+        Self::Call(
+            FileLocation::from_loc(loc),
+            Box::new(callee),
+            Box::new(args),
+        )
     }
 
-    pub fn binary_op(loc: &dyn HasFileLocation, e1: Expr, op: BinaryOp, e2: Expr) -> Self {
-        Self::BinaryOp(FileLocation::from_loc(loc), Box::new(e1), op, Box::new(e2))
+    pub fn method_call(
+        loc: &dyn HasFileLocation,
+        receiver: Expr,
+        name: String,
+        args: Vec<Expr>,
+    ) -> Self {
+        Self::MethodCall(
+            FileLocation::from_loc(loc),
+            Box::new(receiver),
+            name,
+            Box::new(args),
+        )
+    }
+
+    pub fn get(loc: &dyn HasFileLocation, object: Expr, name: String) -> Self {
+        Self::Get(FileLocation::from_loc(loc), Box::new(object), name)
     }
 
-    pub fn print(loc: &dyn HasFileLocation, e: Expr) -> Self {
-        Self::Print(FileLocation::from_loc(loc), Box::new(e))
+    pub fn index(loc: &dyn HasFileLocation, object: Expr, index: Expr) -> Self {
+        Self::Index(
+            FileLocation::from_loc(loc),
+            Box::new(object),
+            Box::new(index),
+        )
     }
 
-    pub fn if_stmt(
+    pub fn slice(
         loc: &dyn HasFileLocation,
-        condition: Expr,
-        then: Expr,
-        else_: Option<Expr>,
+        object: Expr,
+        start: Option<Expr>,
+        end: Option<Expr>,
     ) -> Self {
-        match else_ {
-            Some(e) => Self::If(
-                FileLocation::from_loc(loc),
-                Box::new(condition),
-                Box::new(then),
-                Some(Box::new(e)),
-            ),
-            None => Self::If(
-                FileLocation::from_loc(loc),
-                Box::new(condition),
-                Box::new(then),
-                None,
-            ),
-        }
+        Self::Slice(
+            FileLocation::from_loc(loc),
+            Box::new(object),
+            start.map(Box::new),
+            end.map(Box::new),
+        )
     }
 
-    pub fn let_stmt(loc: &dyn HasFileLocation, name: String, e: Option<Expr>) -> Self {
-        match e {
-            Some(e) => Self::LetInit(FileLocation::from_loc(loc), name, Box::new(e)),
-            None => Self::Let(FileLocation::from_loc(loc), name),
-        }
+    pub fn set(loc: &dyn HasFileLocation, object: Expr, name: String, value: Expr) -> Self {
+        Self::Set(
+            FileLocation::from_loc(loc),
+            Box::new(object),
+            name,
+            Box::new(value),
+        )
     }
 
-    pub fn program(loc: &dyn HasFileLocation, exprs: Vec<Expr>) -> Self {
-        Self::Program(FileLocation::from_loc(loc), Box::new(exprs))
+    pub fn compound_set(
+        loc: &dyn HasFileLocation,
+        object: Expr,
+        name: String,
+        op: BinaryOp,
+        value: Expr,
+    ) -> Self {
+        Self::CompoundSet(
+            FileLocation::from_loc(loc),
+            Box::new(object),
+            name,
+            op,
+            Box::new(value),
+        )
+    }
+
+    pub fn list(loc: &dyn HasFileLocation, items: Vec<Expr>) -> Self {
+        Self::List(FileLocation::from_loc(loc), Box::new(items))
+    }
+
+    pub fn interpolation(loc: &dyn HasFileLocation, parts: Vec<Expr>) -> Self {
+        Self::Interpolation(FileLocation::from_loc(loc), Box::new(parts))
+    }
+
+    pub fn range(loc: &dyn HasFileLocation, start: Expr, end: Expr) -> Self {
+        Self::Range(FileLocation::from_loc(loc), Box::new(start), Box::new(end))
+    }
+
+    pub fn ternary(loc: &dyn HasFileLocation, cond: Expr, then: Expr, else_: Expr) -> Self {
+        Self::Ternary(
+            FileLocation::from_loc(loc),
+            Box::new(cond),
+            Box::new(then),
+            Box::new(else_),
+        )
     }
 
-    pub fn block(loc: &dyn HasFileLocation, exprs: Vec<Expr>) -> Self {
-        Self::Block(FileLocation::from_loc(loc), Box::new(exprs))
+    pub fn unary_op(loc: &dyn HasFileLocation, op: UnaryOp, e: Expr) -> Self {
+        Self::UnaryOp(FileLocation::from_loc(loc), op, Box::new(e))
     }
 
-    pub fn while_stmt(loc: &dyn HasFileLocation, condition: Expr, body: Expr) -> Self {
-        Self::While(
+    pub fn binary_op(loc: &dyn HasFileLocation, e1: Expr, op: BinaryOp, e2: Expr) -> Self {
+        Self::BinaryOp(FileLocation::from_loc(loc), Box::new(e1), op, Box::new(e2))
+    }
+
+    pub fn assign(loc: &dyn HasFileLocation, name: Symbol, e: Expr) -> Self {
+        Self::Assign(
             FileLocation::from_loc(loc),
-            Box::new(condition),
-            Box::new(body),
+            name,
+            Box::new(e),
+            Cell::new(None),
         )
     }
 
-    pub fn break_stmt(loc: &dyn HasFileLocation) -> Self {
-        Self::Break(FileLocation::from_loc(loc))
+    pub fn this(loc: &dyn HasFileLocation) -> Self {
+        Self::This(FileLocation::from_loc(loc), Cell::new(None))
+    }
+
+    pub fn super_method(loc: &dyn HasFileLocation, method: String) -> Self {
+        Self::Super(FileLocation::from_loc(loc), method, Cell::new(None))
     }
 
-    pub fn continue_stmt(loc: &dyn HasFileLocation) -> Self {
-        Self::Continue(FileLocation::from_loc(loc))
+    pub fn lambda(loc: &dyn HasFileLocation, decl: FunctionDecl) -> Self {
+        Self::Lambda(FileLocation::from_loc(loc), Rc::new(decl))
     }
 
-    pub fn assign(loc: &dyn HasFileLocation, name: String, e: Expr) -> Self {
-        Self::Assign(FileLocation::from_loc(loc), name, Box::new(e))
+    pub fn loop_expr(loc: &dyn HasFileLocation, stmt: Stmt) -> Self {
+        Self::Loop(FileLocation::from_loc(loc), Box::new(stmt))
     }
 
     pub fn accept<R>(&self, visitor: &mut dyn Visitor<R>) -> R {
@@ -140,19 +218,29 @@ impl Expr {
             Self::Boolean(loc, b) => visitor.visit_boolean(loc, b),
             Self::Nil(loc) => visitor.visit_nil(loc),
             Self::Grouping(loc, e) => visitor.visit_grouping(loc, e),
+            Self::MethodCall(loc, receiver, name, args) => {
+                visitor.visit_method_call(loc, receiver, name, args)
+            }
+            Self::Get(loc, object, name) => visitor.visit_get(loc, object, name),
+            Self::Set(loc, object, name, value) => visitor.visit_set(loc, object, name, value),
+            Self::CompoundSet(loc, object, name, op, value) => {
+                visitor.visit_compound_set(loc, object, name, op, value)
+            }
+            Self::Index(loc, object, index) => visitor.visit_index(loc, object, index),
+            Self::Slice(loc, object, start, end) => visitor.visit_slice(loc, object, start, end),
+            Self::List(loc, items) => visitor.visit_list(loc, items),
+            Self::Call(loc, callee, args) => visitor.visit_call(loc, callee, args),
+            Self::Interpolation(loc, parts) => visitor.visit_interpolation(loc, parts),
+            Self::Range(loc, start, end) => visitor.visit_range(loc, start, end),
+            Self::Ternary(loc, cond, then, else_) => visitor.visit_ternary(loc, cond, then, else_),
             Self::UnaryOp(loc, op, e) => visitor.visit_unary_op(loc, op, e),
             Self::BinaryOp(loc, op, e1, e2) => visitor.visit_binary_op(loc, e1, op, e2),
-            Self::Print(loc, e) => visitor.visit_print(loc, e),
-            Self::If(loc, c, t, e) => visitor.visit_if(loc, c, t, e),
-            Self::Let(loc, name) => visitor.visit_let(loc, name),
-            Self::LetInit(loc, name, e) => visitor.visit_let_init(loc, name, e),
-            Self::Assign(loc, name, e) => visitor.visit_assign(loc, name, e),
-            Self::Variable(loc, name) => visitor.visit_variable(loc, name),
-            Self::Program(loc, e) => visitor.visit_program(loc, e),
-            Self::Block(loc, e) => visitor.visit_block(loc, e),
-            Self::While(loc, c, e) => visitor.visit_while(loc, c, e),
-            Self::Break(loc) => visitor.visit_break(loc),
-            Self::Continue(loc) => visitor.visit_continue(loc),
+            Self::Assign(loc, name, e, depth) => visitor.visit_assign(loc, name, e, depth),
+            Self::Variable(loc, name, depth) => visitor.visit_variable(loc, name, depth),
+            Self::This(loc, depth) => visitor.visit_this(loc, depth),
+            Self::Super(loc, method, depth) => visitor.visit_super(loc, method, depth),
+            Self::Lambda(loc, decl) => visitor.visit_lambda(loc, decl),
+            Self::Loop(loc, stmt) => visitor.visit_loop_expr(loc, stmt),
         }
     }
 }
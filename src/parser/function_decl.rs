@@ -0,0 +1,52 @@
+use crate::{
+    debug::{FileLocation, HasFileLocation},
+    lexer::Symbol,
+};
+
+use super::{Expr, Stmt};
+
+/**
+ * A named function with its parameters and the statements of its body, e.g. a class method.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionDecl {
+    pub loc: FileLocation,
+    pub name: String,
+    pub params: Vec<Symbol>,
+
+    // The default value for each parameter, if it has one.  Defaults always follow the required
+    // parameters.
+    pub defaults: Vec<Option<Expr>>,
+
+    // The name that collects any arguments past the last parameter, as a list.
+    pub rest: Option<Symbol>,
+    pub body: Vec<Stmt>,
+}
+
+impl FunctionDecl {
+    pub fn new(
+        loc: &dyn HasFileLocation,
+        name: String,
+        params: Vec<Symbol>,
+        defaults: Vec<Option<Expr>>,
+        rest: Option<Symbol>,
+        body: Vec<Stmt>,
+    ) -> Self {
+        Self {
+            loc: FileLocation::from_loc(loc),
+            name,
+            params,
+            defaults,
+            rest,
+            body,
+        }
+    }
+
+    /**
+     * How many parameters must be passed, i.e. the ones without a default.
+     */
+    pub fn required_params(&self) -> usize {
+        self.defaults.iter().filter(|d| d.is_none()).count()
+    }
+}
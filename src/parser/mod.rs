@@ -1,27 +1,23 @@
 mod binary_op;
+mod default_visitor;
 mod expr;
+mod function_decl;
 mod parser;
 mod parser_error;
+mod stmt;
+mod stmt_visitor;
 mod token_stream;
 mod unary_op;
 mod visitor;
 
 pub use binary_op::BinaryOp;
+pub use default_visitor::DefaultVisitor;
 pub use expr::Expr;
+pub use function_decl::FunctionDecl;
 pub use parser::parse;
 pub use parser_error::ParserError;
+pub use stmt::Stmt;
+pub use stmt_visitor::StmtVisitor;
 pub use token_stream::TokenStream;
 pub use unary_op::UnaryOp;
 pub use visitor::Visitor;
-
-// pub enum Stmt {
-//     Expr(Expr),
-//     Print(Expr),
-//     Var(String, Expr),
-//     Block(Vec<Stmt>),
-//     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
-//     While(Expr, Box<Stmt>),
-//     Break,
-//     Function(String, Vec<String>, Box<Stmt>),
-//     Return(Expr),
-// }
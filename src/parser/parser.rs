@@ -2,49 +2,91 @@
  * The token parser for this language grammar:
  * program        → statement* EOF ;
  * statement      → exprStmt
+ *                | classStmt
+ *                | funDecl
  *                | letStmt
- *                | printStmt ;
+ *                | deleteStmt
+ *                | printStmt
+ *                | ifStmt
+ *                | whileStmt
+ *                | forStmt
+ *                | forInStmt
+ *                | loopStmt
+ *                | labeledStmt
+ *                | breakStmt
+ *                | continueStmt
+ *                | block ;
+ * classStmt      → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+ * funDecl        → "fun" function ;
+ * function       → IDENTIFIER "(" parameters? ")" block ;
+ * parameters     → parameter ( "," parameter )* ( "," "..." IDENTIFIER )? ","?
+ *                | "..." IDENTIFIER ","? ;
+ * parameter      → IDENTIFIER ( "=" expression )? ;
+ * lambda         → "fun" "(" parameters? ")" block ;
  * letStmt        → "let" IDENTIFIER ( "=" expression )? ";" ;
+ * deleteStmt     → "delete" IDENTIFIER ";" ;
  * printStmt      → "print" expression ";" ;
- * ifStmt         → "if" "(" expression ")" statement
- *                  ( "else" statement )? ;
- * exprStmt       → expression ";" ;
- * expression     → equality ;
+ * block          → "{" statement* "}" ;
+ * whileStmt      → "while" expression statement ;
+ * forStmt        → "for" "(" ( letStmt | exprStmt | ";" )
+ *                  expression? ";" expression? ")" statement ;
+ * labeledStmt    → LABEL ":" ( whileStmt | forStmt | loopStmt ) ;
+ * loopStmt       → "loop" statement ;
+ * breakStmt      → "break" LABEL? expression? ";" ;
+ * continueStmt   → "continue" LABEL? ";" ;
+ * forInStmt      → "for" "(" IDENTIFIER "in" expression ")" statement ;
+ * ifStmt         → "if" expression statement ( "else" statement )? ;
+ * exprStmt       → expression ( "??" statement )? ";" ;
+ * expression     → assignment ;
+ * assignment     → ( call "." )? IDENTIFIER ( "=" | "+=" | "-=" | "*=" | "/=" ) assignment
+ *                | conditional ;
+ * conditional    → logic_or ( "?" expression ":" conditional )? ;
+ * logic_or       → logic_and ( "||" logic_and )* ;
+ * logic_and      → equality ( "&&" equality )* ;
  * equality       → comparison ( ( "!=" | "==" ) comparison )* ;
- * comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
+ * comparison     → range ( ( ">" | ">=" | "<" | "<=" | "in" ) range )* ;
+ * range          → term ( ".." term )? ;
  * term           → factor ( ( "-" | "+" ) factor )* ;
  * factor         → unary ( ( "/" | "*" ) unary )* ;
- * unary          → ( "!" | "-" ) unary
- *                | primary ;
- * primary        → NUMBER | STRING | "true" | "false" | "nil"
- *                | "(" expression ")" ;
+ * unary          → ( "!" | "-" | "+" ) unary
+ *                | call ;
+ * call           → primary ( "(" arguments? ")" | "." IDENTIFIER ( "(" arguments? ")" )?
+ *                | "[" expression "]" | "[" expression? ":" expression? "]" )* ;
+ * arguments      → expression ( "," expression )* ","? ;
+ * primary        → NUMBER | STRING | "true" | "false" | "nil" | "this"
+ *                | "super" "." IDENTIFIER
+ *                | lambda
+ *                | loopStmt
+ *                | labeledStmt
+ *                | INTERPOLATION expression ( INTERPOLATION expression )* STRING
+ *                | "(" expression ")"
+ *                | "[" ( expression ( "," expression )* ","? )? "]" ;
  */
 use crate::{
     debug::{ErrorSet, FileLocation, HasFileLocation},
-    lexer::{Token, TokenType},
+    lexer::{Symbol, Token, TokenType},
 };
 
-use super::{BinaryOp, Expr, ParserError, TokenStream, UnaryOp};
+use super::{BinaryOp, Expr, FunctionDecl, ParserError, Stmt, TokenStream, UnaryOp};
 
-pub fn parse(tokens: &Vec<Token>) -> Result<Expr, ErrorSet> {
-    let mut stream = TokenStream::new(tokens.clone());
+pub fn parse(tokens: &[Token]) -> Result<Vec<Stmt>, ErrorSet> {
+    let mut stream = TokenStream::new(tokens.to_vec());
     if stream.is_at_end() {
         let mut errors = ErrorSet::new();
-        errors.push(ParserError::new("unexpected end of file", 1, 1));
+        errors.push(stream.error_at_end("unexpected end of file"));
         return Err(errors);
     }
 
-    let expr = parse_program(&mut stream)?;
-    Ok(expr)
+    let program = parse_program(&mut stream)?;
+    Ok(program)
 }
 
-fn parse_program(stream: &mut TokenStream) -> Result<Expr, ErrorSet> {
-    let loc = FileLocation::from_loc(stream.peek().unwrap());
-    let mut exprs = Vec::new();
+fn parse_program(stream: &mut TokenStream) -> Result<Vec<Stmt>, ErrorSet> {
+    let mut stmts = Vec::new();
     let mut errors = ErrorSet::new();
     while !stream.is_at_end() {
         match parse_stmt(stream) {
-            Ok(expr) => exprs.push(expr),
+            Ok(stmt) => stmts.push(stmt),
             Err(e) => {
                 errors.push(e);
                 synchronize(stream);
@@ -54,7 +96,8 @@ fn parse_program(stream: &mut TokenStream) -> Result<Expr, ErrorSet> {
 
         if let Some(token) = stream.prev() {
             if token.token_type == TokenType::RightBrace {
-                // The closing brace is the delimiter.
+                // The closing brace is the delimiter, though one may still follow, e.g. after a lambda.
+                stream.match_token(vec![TokenType::Comma, TokenType::Semicolon]);
                 continue;
             }
         }
@@ -62,9 +105,7 @@ fn parse_program(stream: &mut TokenStream) -> Result<Expr, ErrorSet> {
         let is_at_end = stream.is_at_end();
         if let Some(token) = stream.peek() {
             // The last statement need not end with a semicolon.
-            if is_at_end
-                && !vec![TokenType::Comma, TokenType::Semicolon].contains(&token.token_type)
-            {
+            if is_at_end && ![TokenType::Comma, TokenType::Semicolon].contains(&token.token_type) {
                 break;
             }
             match stream.consume(vec![TokenType::Comma, TokenType::Semicolon]) {
@@ -79,48 +120,52 @@ fn parse_program(stream: &mut TokenStream) -> Result<Expr, ErrorSet> {
     }
 
     if errors.is_empty() {
-        Ok(Expr::program(&loc, exprs))
+        Ok(stmts)
     } else {
         Err(errors)
     }
 }
 
-fn parse_stmt(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     if let Some(token) = stream.peek() {
         match token.token_type {
             TokenType::Print => parse_stmt_print(stream),
             TokenType::If => parse_stmt_if(stream),
             TokenType::Let => parse_stmt_let(stream),
+            TokenType::Class => parse_stmt_class(stream),
+            // A `fun` without a name is a lambda, which starts an expression.
+            TokenType::Fun
+                if stream
+                    .peek_next()
+                    .is_some_and(|next| next.token_type == TokenType::Identifier) =>
+            {
+                parse_stmt_function(stream)
+            }
+            TokenType::Delete => parse_stmt_delete(stream),
             TokenType::LeftBrace => parse_stmt_block(stream),
-            TokenType::While => parse_stmt_while(stream),
-            TokenType::For => parse_stmt_for(stream),
+            TokenType::While => parse_stmt_while(stream, None),
+            TokenType::For => parse_stmt_for(stream, None),
+            TokenType::Loop => parse_stmt_loop(stream, None),
+            TokenType::Label => parse_stmt_labeled(stream),
+            // Reserved, so that it can't be used as a name, but there's nothing to parse.
+            TokenType::Return => Err(ParserError::at(
+                "'return' isn't supported; a function returns the value of its last statement",
+                token,
+            )),
             TokenType::Break => parse_stmt_break(stream),
             TokenType::Continue => parse_stmt_continue(stream),
             _ => parse_stmt_expr(stream),
         }
     } else {
-        let prev = stream.prev().unwrap();
-        Err(ParserError::new(
-            "unexpected end of file",
-            prev.get_line(),
-            prev.get_column(),
-        ))
+        Err(stream.error_at_end("unexpected end of file"))
     }
 }
 
-fn parse_stmt_expr(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_expr(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
     let expr = parse_expr(stream)?;
 
-    if stream.match_token(vec![TokenType::QuestionMark]) {
-        let loc = FileLocation::from_loc(stream.peek().unwrap());
-        let then_branch = parse_stmt(stream)?;
-        let else_branch = if stream.match_token(vec![TokenType::Colon]) {
-            Some(parse_stmt(stream)?)
-        } else {
-            None
-        };
-        return Ok(Expr::if_stmt(&loc, expr, then_branch, else_branch));
-    } else if stream.match_token(vec![TokenType::DoubleQuestionMark]) {
+    if stream.match_token(vec![TokenType::DoubleQuestionMark]) {
         // Implement the null-coalescing operator.
         let loc = FileLocation::from_loc(stream.peek().unwrap());
         let if_nil = parse_stmt(stream)?;
@@ -128,13 +173,18 @@ fn parse_stmt_expr(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         let null_check = Expr::binary_op(&loc, expr.clone(), BinaryOp::Eq, Expr::nil(&loc));
 
         // If <expr> then <expr> else <if_false>
-        return Ok(Expr::if_stmt(&loc, null_check, if_nil, Some(expr)));
+        return Ok(Stmt::if_stmt(
+            &loc,
+            null_check,
+            if_nil,
+            Some(Stmt::expr(&loc, expr)),
+        ));
     }
 
-    Ok(expr)
+    Ok(Stmt::expr(&loc, expr))
 }
 
-fn parse_stmt_let(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_let(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::Let])?;
     let name = stream.consume(vec![TokenType::Identifier])?;
@@ -143,17 +193,138 @@ fn parse_stmt_let(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     } else {
         None
     };
-    Ok(Expr::let_stmt(&loc, name.lexeme.clone(), initializer))
+    Ok(Stmt::let_stmt(
+        &loc,
+        Symbol::intern(&name.lexeme),
+        initializer,
+    ))
 }
 
-fn parse_stmt_print(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_class(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
+    stream.consume(vec![TokenType::Class])?;
+    let name = stream.consume(vec![TokenType::Identifier])?;
+    let superclass = if stream.match_token(vec![TokenType::Less]) {
+        let superclass = stream.consume(vec![TokenType::Identifier])?;
+        if superclass.lexeme == name.lexeme {
+            return Err(ParserError::at(
+                "a class cannot inherit from itself",
+                &superclass,
+            ));
+        }
+        Some(Expr::variable(
+            &superclass,
+            Symbol::intern(&superclass.lexeme),
+        ))
+    } else {
+        None
+    };
+    stream.consume(vec![TokenType::LeftBrace])?;
+
+    let mut methods = Vec::new();
+    while !stream.match_token(vec![TokenType::RightBrace]) {
+        if stream.is_at_end() {
+            return Err(stream.error_at_end("expected '}'"));
+        }
+        methods.push(parse_function(stream)?);
+    }
+    Ok(Stmt::class(&loc, name.lexeme, superclass, methods))
+}
+
+/**
+ * `fun name(params) { ... }` declares a function in the current scope.
+ */
+fn parse_stmt_function(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
+    stream.consume(vec![TokenType::Fun])?;
+    let decl = parse_function(stream)?;
+    Ok(Stmt::function(&loc, decl))
+}
+
+fn parse_function(stream: &mut TokenStream) -> Result<FunctionDecl, ParserError> {
+    let name = stream.consume(vec![TokenType::Identifier])?;
+    parse_function_rest(stream, &name, name.lexeme.clone())
+}
+
+/**
+ * Parse the parameters and body of a function, after its name.
+ */
+fn parse_function_rest(
+    stream: &mut TokenStream,
+    loc: &dyn HasFileLocation,
+    name: String,
+) -> Result<FunctionDecl, ParserError> {
+    stream.consume(vec![TokenType::LeftParen])?;
+
+    let mut params: Vec<Symbol> = Vec::new();
+    let mut defaults: Vec<Option<Expr>> = Vec::new();
+    let mut rest: Option<Symbol> = None;
+    while !stream.match_token(vec![TokenType::RightParen]) {
+        if let Some(token) = stream.peek() {
+            if rest.is_some() {
+                return Err(ParserError::at(
+                    "a rest parameter must be the last one",
+                    token,
+                ));
+            }
+        }
+        let is_rest = stream.match_token(vec![TokenType::Ellipsis]);
+        let param = stream.consume(vec![TokenType::Identifier])?;
+        let symbol = Symbol::intern(&param.lexeme);
+        if params.contains(&symbol) {
+            return Err(ParserError::at("duplicate parameter name", &param));
+        }
+        if is_rest {
+            rest = Some(symbol);
+            if !stream.match_token(vec![TokenType::Comma]) {
+                stream.consume(vec![TokenType::RightParen])?;
+                break;
+            }
+            continue;
+        }
+        let default = if stream.match_token(vec![TokenType::Equal]) {
+            Some(parse_expr(stream)?)
+        } else if defaults.iter().any(|d| d.is_some()) {
+            return Err(ParserError::at(
+                "a parameter without a default can't follow one with a default",
+                &param,
+            ));
+        } else {
+            None
+        };
+        params.push(symbol);
+        defaults.push(default);
+        if !stream.match_token(vec![TokenType::Comma]) {
+            stream.consume(vec![TokenType::RightParen])?;
+            break;
+        }
+    }
+
+    let body = match parse_stmt_block(stream)? {
+        Stmt::Block(_, stmts) => *stmts,
+        _ => unreachable!("parse_stmt_block always returns a block"),
+    };
+    Ok(FunctionDecl::new(loc, name, params, defaults, rest, body))
+}
+
+/**
+ * `delete name` removes a variable from the current scope.
+ */
+fn parse_stmt_delete(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
+    stream.consume(vec![TokenType::Delete])?;
+    let name = stream.consume(vec![TokenType::Identifier])?;
+    Ok(Stmt::delete_stmt(&loc, Symbol::intern(&name.lexeme)))
+}
+
+fn parse_stmt_print(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::Print])?;
     let expr = parse_expr(stream)?;
-    Ok(Expr::print(&loc, expr))
+    Ok(Stmt::print(&loc, expr))
 }
 
-fn parse_stmt_if(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_if(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::If])?;
     // stream.consume(vec![TokenType::LeftParen])?;
@@ -165,26 +336,26 @@ fn parse_stmt_if(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     } else {
         None
     };
-    Ok(Expr::if_stmt(&loc, condition, then_branch, else_branch))
+    Ok(Stmt::if_stmt(&loc, condition, then_branch, else_branch))
 }
 
-fn parse_stmt_block(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_block(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::LeftBrace])?;
 
-    let mut exprs = Vec::new();
+    let mut stmts = Vec::new();
+    // Parsing carries on past an error to find the end of the block, but it's the first error
+    // that gets reported, rather than whatever the recovery stumbles over.
+    let mut first_error = None;
     while stream.peek().unwrap().token_type != TokenType::RightBrace {
         let loc = FileLocation::from_loc(stream.peek().unwrap());
         if stream.is_at_end() {
-            return Err(ParserError::new(
-                "expected '}'",
-                loc.get_line(),
-                loc.get_column(),
-            ));
+            return Err(first_error.unwrap_or_else(|| ParserError::at("expected '}'", &loc)));
         }
         match parse_stmt(stream) {
-            Ok(expr) => exprs.push(expr),
-            Err(_e) => {
+            Ok(stmt) => stmts.push(stmt),
+            Err(e) => {
+                first_error.get_or_insert(e);
                 synchronize(stream);
                 continue;
             }
@@ -192,14 +363,15 @@ fn parse_stmt_block(stream: &mut TokenStream) -> Result<Expr, ParserError> {
 
         if let Some(token) = stream.prev() {
             if token.token_type == TokenType::RightBrace {
-                // The closing brace is the delimiter.
+                // The closing brace is the delimiter, though one may still follow, e.g. after a lambda.
+                stream.match_token(vec![TokenType::Comma, TokenType::Semicolon]);
                 continue;
             }
         }
 
         if let Some(token) = stream.peek() {
             // The last statement need not end with a semicolon.
-            if !vec![TokenType::Comma, TokenType::Semicolon].contains(&token.token_type) {
+            if ![TokenType::Comma, TokenType::Semicolon].contains(&token.token_type) {
                 break;
             }
             match stream.consume(vec![TokenType::Comma, TokenType::Semicolon]) {
@@ -212,22 +384,68 @@ fn parse_stmt_block(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         }
     }
     stream.consume(vec![TokenType::RightBrace])?;
-    Ok(Expr::block(&loc, exprs))
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(Stmt::block(&loc, stmts)),
+    }
+}
+
+/**
+ * A loop with a label in front, which `break` and `continue` can name to reach past inner loops.
+ */
+fn parse_stmt_labeled(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
+    let token = stream.consume(vec![TokenType::Label])?;
+    let label = Some(label_name(&token));
+    stream.consume(vec![TokenType::Colon])?;
+    match stream.peek().map(|token| token.token_type) {
+        Some(TokenType::While) => parse_stmt_while(stream, label),
+        Some(TokenType::For) => parse_stmt_for(stream, label),
+        Some(TokenType::Loop) => parse_stmt_loop(stream, label),
+        _ => Err(ParserError::at("only a loop can have a label", &token)),
+    }
+}
+
+fn label_name(token: &Token) -> Symbol {
+    Symbol::intern(token.lexeme.trim_start_matches('\''))
+}
+
+/**
+ * The label after a `break` or `continue`, if there is one.
+ */
+fn parse_jump_label(stream: &mut TokenStream) -> Option<Symbol> {
+    match stream.peek() {
+        Some(token) if token.token_type == TokenType::Label => {
+            let name = label_name(token);
+            stream.advance();
+            Some(name)
+        }
+        _ => None,
+    }
 }
 
-fn parse_stmt_while(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_while(stream: &mut TokenStream, label: Option<Symbol>) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::While])?;
     let condition = parse_expr(stream)?;
     let body = parse_stmt(stream)?;
-    Ok(Expr::while_stmt(&loc, condition, body))
+    Ok(Stmt::while_stmt(&loc, label, condition, body, None))
 }
 
-fn parse_stmt_for(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_for(stream: &mut TokenStream, label: Option<Symbol>) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::For])?;
     stream.consume(vec![TokenType::LeftParen])?;
 
+    let is_for_in = match (stream.peek(), stream.peek_next()) {
+        (Some(name), Some(keyword)) => {
+            name.token_type == TokenType::Identifier && keyword.token_type == TokenType::In
+        }
+        _ => false,
+    };
+    if is_for_in {
+        return parse_stmt_for_in(stream, &loc, label);
+    }
+
     let initializer = if stream.match_token(vec![TokenType::Semicolon]) {
         None
     } else if let Some(token) = stream.peek() {
@@ -237,11 +455,7 @@ fn parse_stmt_for(stream: &mut TokenStream) -> Result<Expr, ParserError> {
             Some(parse_stmt_expr(stream)?)
         }
     } else {
-        return Err(ParserError::new(
-            "expected initializer",
-            loc.get_line(),
-            loc.get_column(),
-        ));
+        return Err(ParserError::at("expected initializer", &loc));
     };
     if initializer.is_some() {
         stream.consume(vec![TokenType::Semicolon])?;
@@ -262,29 +476,73 @@ fn parse_stmt_for(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     };
 
     stream.consume(vec![TokenType::RightParen])?;
-    let mut body = parse_stmt(stream)?;
-    if let Some(increment) = increment {
-        body = Expr::block(&loc, vec![body, increment]);
-    }
-
-    body = Expr::while_stmt(&loc, condition, body);
+    let body = parse_stmt(stream)?;
+    let body = Stmt::while_stmt(&loc, label, condition, body, increment);
     if let Some(initializer) = initializer {
-        Ok(Expr::block(&loc, vec![initializer, body]))
+        Ok(Stmt::block(&loc, vec![initializer, body]))
     } else {
         Ok(body)
     }
 }
 
-fn parse_stmt_break(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+/**
+ * Parse the rest of a `for (name in iterable) body` loop, after the opening parenthesis.
+ */
+fn parse_stmt_for_in(
+    stream: &mut TokenStream,
+    loc: &FileLocation,
+    label: Option<Symbol>,
+) -> Result<Stmt, ParserError> {
+    let name = stream.consume(vec![TokenType::Identifier])?;
+    stream.consume(vec![TokenType::In])?;
+    let iterable = parse_expr(stream)?;
+    stream.consume(vec![TokenType::RightParen])?;
+    let body = parse_stmt(stream)?;
+    Ok(Stmt::for_in_stmt(
+        loc,
+        label,
+        Symbol::intern(&name.lexeme),
+        iterable,
+        body,
+    ))
+}
+
+fn parse_stmt_loop(stream: &mut TokenStream, label: Option<Symbol>) -> Result<Stmt, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
+    stream.consume(vec![TokenType::Loop])?;
+    let body = parse_stmt(stream)?;
+    Ok(Stmt::loop_stmt(&loc, label, body))
+}
+
+fn parse_stmt_break(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::Break])?;
-    Ok(Expr::break_stmt(&loc))
+    let label = parse_jump_label(stream);
+    // Anything other than the end of the statement is the value to break with.
+    let ends_stmt = match stream.peek() {
+        Some(token) => matches!(
+            token.token_type,
+            TokenType::Semicolon
+                | TokenType::Comma
+                | TokenType::RightBrace
+                | TokenType::Else
+                | TokenType::EOF
+        ),
+        None => true,
+    };
+    let value = if ends_stmt {
+        None
+    } else {
+        Some(parse_expr(stream)?)
+    };
+    Ok(Stmt::break_stmt(&loc, label, value))
 }
 
-fn parse_stmt_continue(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+fn parse_stmt_continue(stream: &mut TokenStream) -> Result<Stmt, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
     stream.consume(vec![TokenType::Continue])?;
-    Ok(Expr::continue_stmt(&loc))
+    let label = parse_jump_label(stream);
+    Ok(Stmt::continue_stmt(&loc, label))
 }
 
 fn parse_expr(stream: &mut TokenStream) -> Result<Expr, ParserError> {
@@ -293,22 +551,66 @@ fn parse_expr(stream: &mut TokenStream) -> Result<Expr, ParserError> {
 
 fn parse_assignment(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     let loc = FileLocation::from_loc(stream.peek().unwrap());
-    let expr = parse_logical_or(stream)?;
+    let expr = parse_conditional(stream)?;
     if stream.match_token(vec![TokenType::Equal]) {
         let value = parse_assignment(stream)?;
         match expr {
-            Expr::Variable(_, name) => Ok(Expr::assign(&loc, name, value)),
-            _ => Err(ParserError::new(
-                "invalid assignment target",
-                loc.get_line(),
-                loc.get_column(),
-            )),
+            Expr::Variable(_, name, _) => Ok(Expr::assign(&loc, name, value)),
+            Expr::Get(get_loc, object, name) => Ok(Expr::set(&get_loc, *object, name, value)),
+            _ => Err(ParserError::at("invalid assignment target", &loc)),
+        }
+    } else if let Some(operator) = compound_assignment_op(stream) {
+        // `x += e` is sugar for `x = x + e`.
+        let value = parse_assignment(stream)?;
+        match expr {
+            Expr::Variable(var_loc, name, _) => {
+                let current = Expr::variable(&var_loc, name);
+                Ok(Expr::assign(
+                    &loc,
+                    name,
+                    Expr::binary_op(&loc, current, operator, value),
+                ))
+            }
+            Expr::Get(get_loc, object, name) => {
+                Ok(Expr::compound_set(&get_loc, *object, name, operator, value))
+            }
+            _ => Err(ParserError::at("invalid assignment target", &loc)),
         }
     } else {
         Ok(expr)
     }
 }
 
+/**
+ * Consume a compound assignment operator, returning the binary operator it applies.
+ */
+fn compound_assignment_op(stream: &mut TokenStream) -> Option<BinaryOp> {
+    let operator = match stream.peek()?.token_type {
+        TokenType::PlusEqual => BinaryOp::Add,
+        TokenType::MinusEqual => BinaryOp::Sub,
+        TokenType::StarEqual => BinaryOp::Mul,
+        TokenType::SlashEqual => BinaryOp::Div,
+        _ => return None,
+    };
+    stream.advance();
+    Some(operator)
+}
+
+fn parse_conditional(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+    let expr = parse_logical_or(stream)?;
+
+    if stream.match_token(vec![TokenType::QuestionMark]) {
+        let loc = FileLocation::from_loc(stream.prev().unwrap());
+        let then_branch = parse_expr(stream)?;
+        stream.consume(vec![TokenType::Colon])?;
+        // Recursing on the else branch makes `a ? b : c ? d : e` group as `a ? b : (c ? d : e)`.
+        let else_branch = parse_conditional(stream)?;
+        return Ok(Expr::ternary(&loc, expr, then_branch, else_branch));
+    }
+
+    Ok(expr)
+}
+
 fn parse_logical_or(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     let mut expr = parse_logical_and(stream)?;
 
@@ -319,6 +621,26 @@ fn parse_logical_or(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         expr = Expr::binary_op(&loc, expr, operator, right);
     }
 
+    // The scanner knows `&` and `|`, but there are no bitwise operators to go with them.  This is
+    // where they'd otherwise end the expression with a confusing error further on.
+    if let Some(token) = stream.peek() {
+        match token.token_type {
+            TokenType::BitwiseAnd => {
+                return Err(ParserError::at(
+                    "unknown operator '&'; did you mean '&&'?",
+                    token,
+                ))
+            }
+            TokenType::BitwiseOr => {
+                return Err(ParserError::at(
+                    "unknown operator '|'; did you mean '||'?",
+                    token,
+                ))
+            }
+            _ => {}
+        }
+    }
+
     Ok(expr)
 }
 
@@ -342,7 +664,7 @@ fn parse_equality(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         let loc = FileLocation::from_loc(token);
         match token.token_type {
             TokenType::BangEqual | TokenType::EqualEqual => {
-                let operator = BinaryOp::from_token(stream.next().unwrap())?;
+                let operator = BinaryOp::from_token(stream.advance().unwrap())?;
                 let right = parse_comparison(stream)?;
                 expr = Expr::binary_op(&loc, expr, operator, right);
             }
@@ -354,7 +676,7 @@ fn parse_equality(stream: &mut TokenStream) -> Result<Expr, ParserError> {
 }
 
 fn parse_comparison(stream: &mut TokenStream) -> Result<Expr, ParserError> {
-    let mut expr = parse_term(stream)?;
+    let mut expr = parse_range(stream)?;
 
     while let Some(token) = stream.peek() {
         let loc = FileLocation::from_loc(token);
@@ -362,9 +684,10 @@ fn parse_comparison(stream: &mut TokenStream) -> Result<Expr, ParserError> {
             TokenType::Greater
             | TokenType::GreaterEqual
             | TokenType::Less
-            | TokenType::LessEqual => {
-                let operator = BinaryOp::from_token(stream.next().unwrap())?;
-                let right = parse_term(stream)?;
+            | TokenType::LessEqual
+            | TokenType::In => {
+                let operator = BinaryOp::from_token(stream.advance().unwrap())?;
+                let right = parse_range(stream)?;
                 expr = Expr::binary_op(&loc, expr, operator, right);
             }
             _ => break,
@@ -374,6 +697,21 @@ fn parse_comparison(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     Ok(expr)
 }
 
+fn parse_range(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+    let start = parse_term(stream)?;
+
+    if let Some(token) = stream.peek() {
+        if token.token_type == TokenType::DotDot {
+            let loc = FileLocation::from_loc(token);
+            stream.advance();
+            let end = parse_term(stream)?;
+            return Ok(Expr::range(&loc, start, end));
+        }
+    }
+
+    Ok(start)
+}
+
 fn parse_term(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     let mut expr = parse_factor(stream)?;
 
@@ -381,7 +719,7 @@ fn parse_term(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         let loc = FileLocation::from_loc(token);
         match token.token_type {
             TokenType::Minus | TokenType::Plus => {
-                let operator = BinaryOp::from_token(stream.next().unwrap())?;
+                let operator = BinaryOp::from_token(stream.advance().unwrap())?;
                 let right = parse_factor(stream)?;
                 expr = Expr::binary_op(&loc, expr, operator, right);
             }
@@ -399,7 +737,7 @@ fn parse_factor(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         let loc = FileLocation::from_loc(token);
         match token.token_type {
             TokenType::Slash | TokenType::Star => {
-                let operator = BinaryOp::from_token(stream.next().unwrap())?;
+                let operator = BinaryOp::from_token(stream.advance().unwrap())?;
                 let right = parse_unary(stream)?;
                 expr = Expr::binary_op(&loc, expr, operator, right);
             }
@@ -414,8 +752,8 @@ fn parse_unary(stream: &mut TokenStream) -> Result<Expr, ParserError> {
     if let Some(token) = stream.peek() {
         let loc = FileLocation::from_loc(token);
         match token.token_type {
-            TokenType::Bang | TokenType::Minus => {
-                let operator = UnaryOp::from_token(stream.next().unwrap())?;
+            TokenType::Bang | TokenType::Minus | TokenType::Plus => {
+                let operator = UnaryOp::from_token(stream.advance().unwrap())?;
                 let right = parse_unary(stream)?;
                 return Ok(Expr::unary_op(&loc, operator, right));
             }
@@ -423,14 +761,107 @@ fn parse_unary(stream: &mut TokenStream) -> Result<Expr, ParserError> {
         }
     }
 
-    parse_primary(stream)
+    parse_call(stream)
+}
+
+fn parse_call(stream: &mut TokenStream) -> Result<Expr, ParserError> {
+    let loc = FileLocation::from_loc(stream.peek().unwrap());
+    let mut expr = parse_primary(stream)?;
+
+    loop {
+        if stream.match_token(vec![TokenType::LeftParen]) {
+            let args = parse_expr_list(stream, TokenType::RightParen)?;
+            expr = Expr::call(&loc, expr, args);
+        } else if stream.match_token(vec![TokenType::Dot]) {
+            let name = stream.consume(vec![TokenType::Identifier])?;
+            let name_loc = FileLocation::from_loc(&name);
+            if stream.match_token(vec![TokenType::LeftParen]) {
+                let args = parse_expr_list(stream, TokenType::RightParen)?;
+                expr = Expr::method_call(&name_loc, expr, name.lexeme, args);
+            } else {
+                expr = Expr::get(&name_loc, expr, name.lexeme);
+            }
+        } else if let Some(bracket) = stream.peek() {
+            if bracket.token_type != TokenType::LeftBracket {
+                break;
+            }
+            let bracket_loc = FileLocation::from_loc(bracket);
+            stream.advance();
+            expr = parse_index_rest(stream, &bracket_loc, expr)?;
+        } else {
+            break;
+        }
+    }
+
+    Ok(expr)
+}
+
+/**
+ * Parse the rest of `object[index]` or `object[start:end]`, after the opening bracket.
+ */
+fn parse_index_rest(
+    stream: &mut TokenStream,
+    loc: &FileLocation,
+    object: Expr,
+) -> Result<Expr, ParserError> {
+    let start = if stream.check(TokenType::Colon) {
+        None
+    } else {
+        Some(parse_expr(stream)?)
+    };
+
+    if stream.match_token(vec![TokenType::Colon]) {
+        let end = if stream.check(TokenType::RightBracket) {
+            None
+        } else {
+            Some(parse_expr(stream)?)
+        };
+        stream.consume(vec![TokenType::RightBracket])?;
+        return Ok(Expr::slice(loc, object, start, end));
+    }
+
+    stream.consume(vec![TokenType::RightBracket])?;
+    match start {
+        Some(index) => Ok(Expr::index(loc, object, index)),
+        None => unreachable!("an index without a colon always has an expression"),
+    }
+}
+
+/**
+ * Parse comma-separated expressions up to and including the closing token.
+ * A trailing comma is allowed, but only after at least one expression.
+ */
+fn parse_expr_list(stream: &mut TokenStream, close: TokenType) -> Result<Vec<Expr>, ParserError> {
+    let mut exprs = Vec::new();
+    if stream.match_token(vec![close]) {
+        return Ok(exprs);
+    }
+    loop {
+        exprs.push(parse_expr(stream)?);
+        if !stream.match_token(vec![TokenType::Comma]) {
+            break;
+        }
+        if stream.match_token(vec![close]) {
+            return Ok(exprs);
+        }
+    }
+    stream.consume(vec![close])?;
+    Ok(exprs)
 }
 
 fn parse_primary(stream: &mut TokenStream) -> Result<Expr, ParserError> {
-    if let Some(token) = stream.next() {
+    // A loop only ends with a `break`, so it's the one statement that can give a value mid-expression.
+    if let Some(token) = stream.peek() {
+        let loc = FileLocation::from_loc(token);
+        match token.token_type {
+            TokenType::Loop => return Ok(Expr::loop_expr(&loc, parse_stmt_loop(stream, None)?)),
+            TokenType::Label => return Ok(Expr::loop_expr(&loc, parse_stmt_labeled(stream)?)),
+            _ => {}
+        }
+    }
+
+    if let Some(token) = stream.advance() {
         let loc = FileLocation::from_loc(token);
-        let line = token.get_line();
-        let column = token.get_column();
 
         match token.token_type {
             TokenType::False
@@ -444,27 +875,47 @@ fn parse_primary(stream: &mut TokenStream) -> Result<Expr, ParserError> {
                 stream.consume(vec![TokenType::RightParen])?;
                 Ok(Expr::grouping(&loc, expr))
             }
-            _ => Err(ParserError::new("expected expression", line, column)),
+            TokenType::Interpolation => {
+                // The scanner splits "a${b}c${d}e" into INTERPOLATION("a") b INTERPOLATION("c") d STRING("e").
+                let mut parts = vec![Expr::literal(&loc, token.literal.clone())];
+                loop {
+                    parts.push(parse_expr(stream)?);
+                    let segment =
+                        stream.consume(vec![TokenType::Interpolation, TokenType::String])?;
+                    parts.push(Expr::literal(&segment, segment.literal.clone()));
+                    if segment.token_type == TokenType::String {
+                        break;
+                    }
+                }
+                Ok(Expr::interpolation(&loc, parts))
+            }
+            TokenType::This => Ok(Expr::this(&loc)),
+            TokenType::Fun => {
+                let decl = parse_function_rest(stream, &loc, "lambda".to_string())?;
+                Ok(Expr::lambda(&loc, decl))
+            }
+            TokenType::Super => {
+                stream.consume(vec![TokenType::Dot])?;
+                let method = stream.consume(vec![TokenType::Identifier])?;
+                Ok(Expr::super_method(&loc, method.lexeme))
+            }
+            TokenType::LeftBracket => {
+                let items = parse_expr_list(stream, TokenType::RightBracket)?;
+                Ok(Expr::list(&loc, items))
+            }
+            _ => Err(ParserError::at("expected expression", &loc)),
         }
     } else {
-        if let Some(token) = stream.prev() {
-            Err(ParserError::new(
-                "expected expression",
-                token.get_line(),
-                token.get_column(),
-            ))
-        } else {
-            Err(ParserError::new("expected expression", 0, 0))
-        }
+        Err(stream.error_at_end("expected expression"))
     }
 }
 
 fn synchronize(stream: &mut TokenStream) {
-    stream.next();
+    stream.advance();
 
     while !stream.is_at_end() {
         if let Some(token) = stream.peek() {
-            if vec![TokenType::Comma, TokenType::Semicolon].contains(&token.token_type) {
+            if [TokenType::Comma, TokenType::Semicolon].contains(&token.token_type) {
                 return;
             }
 
@@ -472,15 +923,136 @@ fn synchronize(stream: &mut TokenStream) {
                 TokenType::Class
                 | TokenType::Fun
                 | TokenType::Let
+                | TokenType::Delete
                 | TokenType::For
                 | TokenType::If
                 | TokenType::While
+                | TokenType::Loop
                 | TokenType::Print
                 | TokenType::Return => return,
                 _ => {}
             }
         }
 
-        stream.next();
+        stream.advance();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{scan_tokens, TokenIter};
+
+    /**
+     * The message of each error found parsing `source`, which must scan.
+     */
+    fn parse_errors(source: &str) -> Vec<String> {
+        match parse(&scan_tokens(source).unwrap()) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|err| err.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn statements_are_not_expressions() {
+        assert_eq!(parse_errors("1 + print x;")[0], "expected expression");
+        assert_eq!(
+            parse_errors("let v = if (true) 1 else 2;")[0],
+            "expected expression"
+        );
+        assert_eq!(parse_errors("print let x = 1;")[0], "expected expression");
+    }
+
+    #[test]
+    fn defaults_must_follow_required_parameters() {
+        assert_eq!(
+            parse_errors("fun f(a = 1, b) { a }")[0],
+            "a parameter without a default can't follow one with a default"
+        );
+        assert!(parse_errors("fun f(a, b = 10) { a + b }").is_empty());
+    }
+
+    #[test]
+    fn parsed_tree_matches_a_hand_built_one() {
+        let stmts = parse(&scan_tokens("1 + 2;").unwrap()).unwrap();
+        let expected = Expr::binary_op(
+            &FileLocation::new(1, 3),
+            Expr::number(&FileLocation::new(1, 1), 1.0),
+            BinaryOp::Add,
+            Expr::number(&FileLocation::new(1, 5), 2.0),
+        );
+        assert_eq!(stmts, vec![Stmt::expr(&FileLocation::new(1, 1), expected)]);
+    }
+
+    #[test]
+    fn unused_tokens_get_their_own_errors() {
+        let no_return =
+            "'return' isn't supported; a function returns the value of its last statement";
+        assert_eq!(parse_errors("return 1;")[0], no_return);
+        assert_eq!(parse_errors("fun f() { return 1; }"), vec![no_return]);
+        assert_eq!(
+            parse_errors("print 1 & 2;")[0],
+            "unknown operator '&'; did you mean '&&'?"
+        );
+        assert_eq!(
+            parse_errors("print 1 | 2;")[0],
+            "unknown operator '|'; did you mean '||'?"
+        );
+    }
+
+    #[test]
+    fn lists_arguments_and_parameters_allow_a_trailing_comma() {
+        assert!(parse_errors("[1, 2,];").is_empty());
+        assert!(parse_errors("fun f(a, b,) { a } f(1, 2,); f(1,);").is_empty());
+        assert!(parse_errors("\"s\".substring(0, 1,);").is_empty());
+
+        // A comma still needs something before it.
+        assert_eq!(parse_errors("[,];")[0], "expected expression");
+        assert_eq!(parse_errors("f(1,,);")[0], "expected expression");
+    }
+
+    #[test]
+    fn errors_at_the_end_point_at_the_last_line() {
+        let errors = parse(&scan_tokens("let a = 1;\nprint (a +\n").unwrap()).unwrap_err();
+        let err = errors.iter().next().unwrap();
+        assert_eq!(err.to_string(), "expected expression");
+        assert_eq!(err.get_line(), 2);
+        // The `+` is the last thing there is to point at.
+        assert_eq!(err.get_column(), 10);
+
+        let errors = parse(&scan_tokens("").unwrap()).unwrap_err();
+        let err = errors.iter().next().unwrap();
+        assert_eq!(err.to_string(), "unexpected end of file");
+        assert_eq!(err.get_line(), 1);
+    }
+
+    #[test]
+    fn trivia_is_skipped() {
+        let source = "let x = 1; // one\n/* two */ print x;";
+        let tokens = TokenIter::new(source)
+            .with_trivia(true)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            parse(&tokens).unwrap(),
+            parse(&scan_tokens(source).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn var_is_the_same_as_let() {
+        for (with_var, with_let) in [
+            ("var x = 1;", "let x = 1;"),
+            ("var x;", "let x;"),
+            (
+                "for (var i = 0; i < 3; i += 1) print i;",
+                "for (let i = 0; i < 3; i += 1) print i;",
+            ),
+        ] {
+            assert_eq!(
+                parse(&scan_tokens(with_var).unwrap()).unwrap(),
+                parse(&scan_tokens(with_let).unwrap()).unwrap()
+            );
+        }
     }
 }
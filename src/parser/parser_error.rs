@@ -9,6 +9,8 @@ pub struct ParserError {
     pub msg: String,
     line: usize,
     column: usize,
+    end_line: usize,
+    end_column: usize,
 }
 
 impl ParserError {
@@ -17,6 +19,21 @@ impl ParserError {
             msg: msg.to_string(),
             line,
             column,
+            end_line: line,
+            end_column: column,
+        }
+    }
+
+    /**
+     * Create an error covering the full span of `loc`.
+     */
+    pub fn at(msg: &str, loc: &dyn HasFileLocation) -> Self {
+        Self {
+            msg: msg.to_string(),
+            line: loc.get_line(),
+            column: loc.get_column(),
+            end_line: loc.get_end_line(),
+            end_column: loc.get_end_column(),
         }
     }
 
@@ -25,6 +42,8 @@ impl ParserError {
             msg: format!("unexpected token: {}", token.token_type),
             line: token.get_line(),
             column: token.get_column(),
+            end_line: token.get_end_line(),
+            end_column: token.get_end_column(),
         }
     }
 
@@ -33,6 +52,8 @@ impl ParserError {
             msg: format!("invalid operator: {}", op),
             line: 0,
             column: 0,
+            end_line: 0,
+            end_column: 0,
         }
     }
 }
@@ -45,6 +66,14 @@ impl HasFileLocation for ParserError {
     fn get_column(&self) -> usize {
         self.column
     }
+
+    fn get_end_column(&self) -> usize {
+        self.end_column
+    }
+
+    fn get_end_line(&self) -> usize {
+        self.end_line
+    }
 }
 
 impl Error for ParserError {}
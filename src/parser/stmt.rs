@@ -0,0 +1,189 @@
+use std::rc::Rc;
+
+use crate::{
+    debug::{FileLocation, HasFileLocation},
+    lexer::Symbol,
+};
+
+use super::{Expr, FunctionDecl, StmtVisitor};
+
+/**
+ * A statement.  Statements still evaluate to a value, so a block or an `if` can be used for its
+ * result, but they can't appear in the middle of an expression.  A `loop` is the exception, and is
+ * wrapped in an `Expr::Loop` when it's used for its value.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stmt {
+    Expr(FileLocation, Box<Expr>),
+    Print(FileLocation, Box<Expr>),
+    Let(FileLocation, Symbol, Option<Box<Expr>>),
+    Delete(FileLocation, Symbol),
+    Block(FileLocation, Box<Vec<Stmt>>),
+    If(FileLocation, Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
+    // Loops carry their label, if they have one, and `break` and `continue` the label they target.
+    // A `for` loop's increment is kept apart from its body, so a `continue` still runs it.
+    While(
+        FileLocation,
+        Option<Symbol>,
+        Box<Expr>,
+        Box<Stmt>,
+        Option<Box<Expr>>,
+    ),
+    Loop(FileLocation, Option<Symbol>, Box<Stmt>),
+    ForIn(FileLocation, Option<Symbol>, Symbol, Box<Expr>, Box<Stmt>),
+    Break(FileLocation, Option<Symbol>, Option<Box<Expr>>),
+    Continue(FileLocation, Option<Symbol>),
+    Class(
+        FileLocation,
+        String,
+        Option<Box<Expr>>,
+        Box<Vec<FunctionDecl>>,
+    ),
+    Function(FileLocation, Rc<FunctionDecl>),
+}
+
+impl Stmt {
+    pub fn expr(loc: &dyn HasFileLocation, e: Expr) -> Self {
+        Self::Expr(FileLocation::from_loc(loc), Box::new(e))
+    }
+
+    pub fn print(loc: &dyn HasFileLocation, e: Expr) -> Self {
+        Self::Print(FileLocation::from_loc(loc), Box::new(e))
+    }
+
+    pub fn let_stmt(loc: &dyn HasFileLocation, name: Symbol, e: Option<Expr>) -> Self {
+        Self::Let(FileLocation::from_loc(loc), name, e.map(Box::new))
+    }
+
+    pub fn delete_stmt(loc: &dyn HasFileLocation, name: Symbol) -> Self {
+        Self::Delete(FileLocation::from_loc(loc), name)
+    }
+
+    pub fn block(loc: &dyn HasFileLocation, stmts: Vec<Stmt>) -> Self {
+        Self::Block(FileLocation::from_loc(loc), Box::new(stmts))
+    }
+
+    pub fn if_stmt(
+        loc: &dyn HasFileLocation,
+        condition: Expr,
+        then: Stmt,
+        else_: Option<Stmt>,
+    ) -> Self {
+        Self::If(
+            FileLocation::from_loc(loc),
+            Box::new(condition),
+            Box::new(then),
+            else_.map(Box::new),
+        )
+    }
+
+    pub fn while_stmt(
+        loc: &dyn HasFileLocation,
+        label: Option<Symbol>,
+        condition: Expr,
+        body: Stmt,
+        increment: Option<Expr>,
+    ) -> Self {
+        Self::While(
+            FileLocation::from_loc(loc),
+            label,
+            Box::new(condition),
+            Box::new(body),
+            increment.map(Box::new),
+        )
+    }
+
+    pub fn loop_stmt(loc: &dyn HasFileLocation, label: Option<Symbol>, body: Stmt) -> Self {
+        Self::Loop(FileLocation::from_loc(loc), label, Box::new(body))
+    }
+
+    pub fn for_in_stmt(
+        loc: &dyn HasFileLocation,
+        label: Option<Symbol>,
+        name: Symbol,
+        iterable: Expr,
+        body: Stmt,
+    ) -> Self {
+        Self::ForIn(
+            FileLocation::from_loc(loc),
+            label,
+            name,
+            Box::new(iterable),
+            Box::new(body),
+        )
+    }
+
+    pub fn break_stmt(
+        loc: &dyn HasFileLocation,
+        label: Option<Symbol>,
+        value: Option<Expr>,
+    ) -> Self {
+        Self::Break(FileLocation::from_loc(loc), label, value.map(Box::new))
+    }
+
+    pub fn continue_stmt(loc: &dyn HasFileLocation, label: Option<Symbol>) -> Self {
+        Self::Continue(FileLocation::from_loc(loc), label)
+    }
+
+    pub fn class(
+        loc: &dyn HasFileLocation,
+        name: String,
+        superclass: Option<Expr>,
+        methods: Vec<FunctionDecl>,
+    ) -> Self {
+        Self::Class(
+            FileLocation::from_loc(loc),
+            name,
+            superclass.map(Box::new),
+            Box::new(methods),
+        )
+    }
+
+    pub fn function(loc: &dyn HasFileLocation, decl: FunctionDecl) -> Self {
+        Self::Function(FileLocation::from_loc(loc), Rc::new(decl))
+    }
+
+    /**
+     * Where the statement starts.
+     */
+    pub fn loc(&self) -> &FileLocation {
+        match self {
+            Self::Expr(loc, _)
+            | Self::Print(loc, _)
+            | Self::Let(loc, _, _)
+            | Self::Delete(loc, _)
+            | Self::Block(loc, _)
+            | Self::If(loc, _, _, _)
+            | Self::While(loc, _, _, _, _)
+            | Self::Loop(loc, _, _)
+            | Self::ForIn(loc, _, _, _, _)
+            | Self::Break(loc, _, _)
+            | Self::Continue(loc, _)
+            | Self::Class(loc, _, _, _)
+            | Self::Function(loc, _) => loc,
+        }
+    }
+
+    pub fn accept<R>(&self, visitor: &mut dyn StmtVisitor<R>) -> R {
+        match self {
+            Self::Expr(loc, e) => visitor.visit_expr_stmt(loc, e),
+            Self::Print(loc, e) => visitor.visit_print(loc, e),
+            Self::Let(loc, name, e) => visitor.visit_let(loc, name, e),
+            Self::Delete(loc, name) => visitor.visit_delete(loc, name),
+            Self::Block(loc, stmts) => visitor.visit_block(loc, stmts),
+            Self::If(loc, c, t, e) => visitor.visit_if(loc, c, t, e),
+            Self::While(loc, label, c, body, inc) => visitor.visit_while(loc, label, c, body, inc),
+            Self::Loop(loc, label, body) => visitor.visit_loop(loc, label, body),
+            Self::ForIn(loc, label, name, i, body) => {
+                visitor.visit_for_in(loc, label, name, i, body)
+            }
+            Self::Break(loc, label, value) => visitor.visit_break(loc, label, value),
+            Self::Continue(loc, label) => visitor.visit_continue(loc, label),
+            Self::Class(loc, name, superclass, methods) => {
+                visitor.visit_class(loc, name, superclass, methods)
+            }
+            Self::Function(loc, decl) => visitor.visit_function_stmt(loc, decl),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::rc::Rc;
+
+use crate::{debug::HasFileLocation, lexer::Symbol};
+
+use super::{Expr, FunctionDecl, Stmt};
+
+pub trait StmtVisitor<R> {
+    fn visit_expr_stmt(&mut self, loc: &dyn HasFileLocation, expr: &Expr) -> R;
+    fn visit_print(&mut self, loc: &dyn HasFileLocation, expr: &Expr) -> R;
+    fn visit_let(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        init: &Option<Box<Expr>>,
+    ) -> R;
+    fn visit_delete(&mut self, loc: &dyn HasFileLocation, name: &Symbol) -> R;
+    fn visit_block(&mut self, loc: &dyn HasFileLocation, stmts: &[Stmt]) -> R;
+    fn visit_if(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Stmt,
+        else_: &Option<Box<Stmt>>,
+    ) -> R;
+    fn visit_while(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        cond: &Expr,
+        body: &Stmt,
+        increment: &Option<Box<Expr>>,
+    ) -> R;
+    fn visit_loop(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>, body: &Stmt) -> R;
+    fn visit_for_in(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        name: &Symbol,
+        iterable: &Expr,
+        body: &Stmt,
+    ) -> R;
+    fn visit_break(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        label: &Option<Symbol>,
+        value: &Option<Box<Expr>>,
+    ) -> R;
+    fn visit_continue(&mut self, loc: &dyn HasFileLocation, label: &Option<Symbol>) -> R;
+    fn visit_class(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &str,
+        superclass: &Option<Box<Expr>>,
+        methods: &[FunctionDecl],
+    ) -> R;
+    fn visit_function_stmt(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> R;
+}
@@ -1,7 +1,4 @@
-use crate::{
-    debug::HasFileLocation,
-    lexer::{Token, TokenType},
-};
+use crate::lexer::{Token, TokenType};
 
 use super::ParserError;
 
@@ -11,7 +8,14 @@ pub struct TokenStream {
 }
 
 impl TokenStream {
+    /**
+     * A stream over `tokens`, leaving out any trivia the scanner kept.
+     */
     pub fn new(tokens: Vec<Token>) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| !token.is_trivia())
+            .collect();
         Self { tokens, index: 0 }
     }
 
@@ -23,15 +27,20 @@ impl TokenStream {
     }
 
     pub fn peek(&self) -> Option<&Token> {
-        // self.skip_space();
         self.tokens.get(self.index)
     }
 
-    pub fn next(&mut self) -> Option<&Token> {
+    pub fn peek_next(&self) -> Option<&Token> {
+        self.tokens.get(self.index + 1)
+    }
+
+    /**
+     * Consume the next token and return it, or return `None` at the end of the stream.
+     */
+    pub fn advance(&mut self) -> Option<&Token> {
         if self.is_at_end() {
             return None;
         }
-        // self.skip_space();
         let token = self.tokens.get(self.index);
         self.index += 1;
         token
@@ -48,6 +57,14 @@ impl TokenStream {
         return false;
     }
 
+    /**
+     * Check if the next token is of the given type, without consuming it.
+     */
+    pub fn check(&self, token_type: TokenType) -> bool {
+        self.peek()
+            .is_some_and(|token| token.token_type == token_type)
+    }
+
     /**
      * Check if the next token is of the given type.
      * Consume the token if it is a match.
@@ -62,29 +79,8 @@ impl TokenStream {
         false
     }
 
-    /**
-     * Skip any tokens that don't provide value to the output expression.
-     */
-    // fn skip_space(&mut self) {
-    //     self.skip_tokens(vec![
-    //         TokenType::Whitespace,
-    //         TokenType::NewLine,
-    //         TokenType::Comment,
-    //     ]);
-    // }
-
-    // fn skip_tokens(&mut self, token_types: Vec<TokenType>) {
-    //     while let Some(token) = self.tokens.get(self.index) {
-    //         if token_types.contains(&token.token_type) {
-    //             self.index += 1
-    //         } else {
-    //             break;
-    //         }
-    //     }
-    // }
-
     // pub fn consume(&mut self, token_type: TokenType) -> Result<Token, ParserError> {
-    //     if let Some(token) = self.next() {
+    //     if let Some(token) = self.advance() {
     //         if token.token_type == token_type {
     //             return Ok(token.clone());
     //         }
@@ -105,20 +101,25 @@ impl TokenStream {
      * Consume the next token if its type is in token_types.
      */
     pub fn consume(&mut self, token_types: Vec<TokenType>) -> Result<Token, ParserError> {
-        if let Some(token) = self.next() {
+        if let Some(token) = self.advance() {
             if token_types.contains(&token.token_type) {
                 return Ok(token.clone());
             }
-            return Err(ParserError::new(
+            return Err(ParserError::at(
                 format!("expected one of {:?}", token_types).as_str(),
-                token.get_line(),
-                token.get_column(),
+                token,
             ));
         }
-        Err(ParserError::new(
-            format!("expected one of {:?}", token_types).as_str(),
-            0,
-            0,
-        ))
+        Err(self.error_at_end(format!("expected one of {:?}", token_types).as_str()))
+    }
+
+    /**
+     * An error for running out of input, pointing at the last token that was read.
+     */
+    pub fn error_at_end(&self, msg: &str) -> ParserError {
+        match self.prev().or(self.peek()) {
+            Some(token) => ParserError::at(msg, token),
+            None => ParserError::new(msg, 1, 1),
+        }
     }
 }
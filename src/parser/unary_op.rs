@@ -4,9 +4,11 @@ use crate::lexer::{Token, TokenType};
 
 use super::ParserError;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOp {
     Neg,
+    Pos,
     Not,
 }
 
@@ -14,6 +16,7 @@ impl UnaryOp {
     pub fn from_token(token: &Token) -> Result<Self, ParserError> {
         match token.token_type {
             TokenType::Minus => Ok(Self::Neg),
+            TokenType::Plus => Ok(Self::Pos),
             TokenType::Bang => Ok(Self::Not),
             _ => Err(ParserError::unexpected_token(token)),
         }
@@ -24,6 +27,7 @@ impl Display for UnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Neg => write!(f, "-"),
+            Self::Pos => write!(f, "+"),
             Self::Not => write!(f, "!"),
         }
     }
@@ -35,6 +39,7 @@ impl FromStr for UnaryOp {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "-" => Ok(Self::Neg),
+            "+" => Ok(Self::Pos),
             "!" => Ok(Self::Not),
             _ => Err(ParserError::invalid_op(s)),
         }
@@ -1,6 +1,8 @@
-use crate::debug::HasFileLocation;
+use std::{cell::Cell, rc::Rc};
 
-use super::{BinaryOp, Expr, UnaryOp};
+use crate::{debug::HasFileLocation, lexer::Symbol};
+
+use super::{BinaryOp, Expr, FunctionDecl, Stmt, UnaryOp};
 
 pub trait Visitor<R> {
     fn visit_number(&mut self, loc: &dyn HasFileLocation, n: &f64) -> R;
@@ -8,6 +10,48 @@ pub trait Visitor<R> {
     fn visit_boolean(&mut self, loc: &dyn HasFileLocation, b: &bool) -> R;
     fn visit_nil(&mut self, loc: &dyn HasFileLocation) -> R;
     fn visit_grouping(&mut self, loc: &dyn HasFileLocation, e: &Box<Expr>) -> R;
+    fn visit_call(&mut self, loc: &dyn HasFileLocation, callee: &Expr, args: &[Expr]) -> R;
+    fn visit_method_call(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        receiver: &Expr,
+        name: &str,
+        args: &[Expr],
+    ) -> R;
+    fn visit_get(&mut self, loc: &dyn HasFileLocation, object: &Expr, name: &str) -> R;
+    fn visit_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        value: &Expr,
+    ) -> R;
+    fn visit_compound_set(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        name: &str,
+        op: &BinaryOp,
+        value: &Expr,
+    ) -> R;
+    fn visit_index(&mut self, loc: &dyn HasFileLocation, object: &Expr, index: &Expr) -> R;
+    fn visit_slice(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> R;
+    fn visit_list(&mut self, loc: &dyn HasFileLocation, items: &[Expr]) -> R;
+    fn visit_interpolation(&mut self, loc: &dyn HasFileLocation, parts: &[Expr]) -> R;
+    fn visit_range(&mut self, loc: &dyn HasFileLocation, start: &Expr, end: &Expr) -> R;
+    fn visit_ternary(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        cond: &Expr,
+        then: &Expr,
+        else_: &Expr,
+    ) -> R;
     fn visit_unary_op(&mut self, loc: &dyn HasFileLocation, op: &UnaryOp, e: &Box<Expr>) -> R;
     fn visit_binary_op(
         &mut self,
@@ -16,21 +60,26 @@ pub trait Visitor<R> {
         e1: &Box<Expr>,
         e2: &Box<Expr>,
     ) -> R;
-    fn visit_print(&mut self, loc: &dyn HasFileLocation, expr: &Box<Expr>) -> R;
-    fn visit_if(
+    fn visit_assign(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        expr: &Expr,
+        depth: &Cell<Option<usize>>,
+    ) -> R;
+    fn visit_variable(
+        &mut self,
+        loc: &dyn HasFileLocation,
+        name: &Symbol,
+        depth: &Cell<Option<usize>>,
+    ) -> R;
+    fn visit_this(&mut self, loc: &dyn HasFileLocation, depth: &Cell<Option<usize>>) -> R;
+    fn visit_super(
         &mut self,
         loc: &dyn HasFileLocation,
-        cond: &Box<Expr>,
-        then: &Box<Expr>,
-        else_: &Option<Box<Expr>>,
+        method: &str,
+        depth: &Cell<Option<usize>>,
     ) -> R;
-    fn visit_let(&mut self, loc: &dyn HasFileLocation, name: &String) -> R;
-    fn visit_let_init(&mut self, loc: &dyn HasFileLocation, name: &String, expr: &Box<Expr>) -> R;
-    fn visit_assign(&mut self, loc: &dyn HasFileLocation, name: &String, expr: &Box<Expr>) -> R;
-    fn visit_variable(&mut self, loc: &dyn HasFileLocation, name: &String) -> R;
-    fn visit_program(&mut self, loc: &dyn HasFileLocation, exprs: &Vec<Expr>) -> R;
-    fn visit_block(&mut self, loc: &dyn HasFileLocation, exprs: &Vec<Expr>) -> R;
-    fn visit_while(&mut self, loc: &dyn HasFileLocation, cond: &Box<Expr>, body: &Box<Expr>) -> R;
-    fn visit_break(&mut self, loc: &dyn HasFileLocation) -> R;
-    fn visit_continue(&mut self, loc: &dyn HasFileLocation) -> R;
+    fn visit_lambda(&mut self, loc: &dyn HasFileLocation, decl: &Rc<FunctionDecl>) -> R;
+    fn visit_loop_expr(&mut self, loc: &dyn HasFileLocation, stmt: &Stmt) -> R;
 }
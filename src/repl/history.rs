@@ -0,0 +1,181 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Overrides the location of the history file.
+const HISTORY_PATH_VAR: &str = "RLOX_HISTORY";
+const HISTORY_FILE_NAME: &str = ".rlox_history";
+
+/**
+ * The lines previously submitted to the REPL, navigable with the up and down arrows.
+ */
+pub struct History {
+    entries: Vec<String>,
+
+    // The entry currently being displayed.  `entries.len()` refers to the line being edited.
+    index: usize,
+
+    // The in-progress line, saved when navigating away from it.
+    draft: String,
+
+    // The number of entries that were loaded from disk rather than typed this session.
+    loaded: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: 0,
+            draft: String::new(),
+            loaded: 0,
+        }
+    }
+
+    /**
+     * The history file: `$RLOX_HISTORY` if set, otherwise `.rlox_history` in the home directory.
+     */
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os(HISTORY_PATH_VAR) {
+            return Some(PathBuf::from(path));
+        }
+        env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+    }
+
+    /**
+     * Load the history from `path`.  A missing or unreadable file yields an empty history.
+     */
+    pub fn load(path: &Path) -> Self {
+        let mut history = Self::new();
+        if let Ok(contents) = fs::read_to_string(path) {
+            let mut entry = String::new();
+            for line in contents.lines() {
+                // A trailing backslash means the entry carries on to the next line.
+                match line.strip_suffix('\\') {
+                    Some(line) => {
+                        entry.push_str(line);
+                        entry.push('\n');
+                    }
+                    None => {
+                        entry.push_str(line);
+                        history.push(&entry);
+                        entry.clear();
+                    }
+                }
+            }
+        }
+        history.loaded = history.entries.len();
+        history
+    }
+
+    /**
+     * Append the entries from this session to the file at `path`, one per line.  The line breaks
+     * within a multi-line entry are escaped with a backslash.
+     */
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for entry in &self.entries[self.loaded..] {
+            writeln!(file, "{}", entry.replace('\n', "\\\n"))?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Record a submitted line and return to the bottom of the history.
+     */
+    pub fn push(&mut self, line: &str) {
+        let is_repeat = self
+            .entries
+            .last()
+            .map(|last| last == line)
+            .unwrap_or(false);
+        if !line.trim().is_empty() && !is_repeat {
+            self.entries.push(line.to_string());
+        }
+        self.index = self.entries.len();
+        self.draft.clear();
+    }
+
+    /**
+     * Step back to the previous entry, saving `current` if it is the in-progress line.
+     */
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        if self.index == 0 {
+            return None;
+        }
+        if self.index == self.entries.len() {
+            self.draft = current.to_string();
+        }
+        self.index -= 1;
+        Some(&self.entries[self.index])
+    }
+
+    /**
+     * Step forward to the next entry, restoring the in-progress line past the newest one.
+     */
+    pub fn next(&mut self) -> Option<&str> {
+        if self.index >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        if self.index == self.entries.len() {
+            Some(&self.draft)
+        } else {
+            Some(&self.entries[self.index])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_are_saved_and_reloaded() {
+        let dir = env::temp_dir().join(format!("rlox-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(HISTORY_FILE_NAME);
+
+        // The first session starts from a missing file.
+        let mut history = History::load(&path);
+        assert!(history.previous("").is_none());
+        history.push("let a = 1;");
+        history.push("fun f() {\n  a\n}");
+        history.save(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "let a = 1;\nfun f() {\\\n  a\\\n}\n"
+        );
+
+        // The second session sees both, and only appends what it adds itself.
+        let mut history = History::load(&path);
+        history.push("print f();");
+        history.save(&path).unwrap();
+        let mut history = History::load(&path);
+        assert_eq!(history.previous(""), Some("print f();"));
+        assert_eq!(history.previous(""), Some("fun f() {\n  a\n}"));
+        assert_eq!(history.previous(""), Some("let a = 1;"));
+        assert_eq!(history.previous(""), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_path_can_be_overridden() {
+        env::set_var(HISTORY_PATH_VAR, "/tmp/elsewhere");
+        assert_eq!(
+            History::default_path(),
+            Some(PathBuf::from("/tmp/elsewhere"))
+        );
+        env::remove_var(HISTORY_PATH_VAR);
+    }
+}
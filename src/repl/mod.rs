@@ -1,14 +1,47 @@
+mod history;
+
 use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use std::io::{self, Write};
+use std::io::{self, BufRead, Write};
 
-use crate::interpreter::HasStopFlag;
+use history::History;
+use rlox::interpreter::HasStopFlag;
+use rlox::lexer::{scan_tokens, TokenType};
 
 const PROMPT: &str = "\r\n> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/**
+ * Check whether `source` stops partway through a construct, meaning more lines should be read
+ * before it is evaluated.  Brackets are counted from the scanned tokens, so any inside strings
+ * and comments are skipped.
+ */
+fn is_incomplete(source: &str) -> bool {
+    match scan_tokens(source) {
+        Ok(tokens) => {
+            let mut depth: isize = 0;
+            for token in tokens {
+                match token.token_type {
+                    TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => {
+                        depth += 1
+                    }
+                    TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => {
+                        depth -= 1
+                    }
+                    _ => {}
+                }
+            }
+            depth > 0
+        }
+        Err(err) => err.msg.starts_with("unterminated"),
+    }
+}
 
 fn handle_enter<TCallback, TState>(
     input_buffer: &mut String,
     cursor_position: &mut usize,
+    pending: &mut String,
+    history: &mut History,
     callback: &mut TCallback,
     state: &mut TState,
 ) where
@@ -16,15 +49,36 @@ fn handle_enter<TCallback, TState>(
     TState: HasStopFlag,
 {
     print!("\r\n");
-    callback(&input_buffer, state);
+
+    // Keep reading until the input is complete.  A blank line submits it regardless.
+    let is_blank = input_buffer.trim().is_empty();
+    pending.push_str(input_buffer);
     input_buffer.clear();
     *cursor_position = 0;
+    if !is_blank && is_incomplete(pending) {
+        pending.push('\n');
+        print!("{}", CONTINUATION_PROMPT);
+        return;
+    }
+
+    // The whole entry goes into the history, rather than each line of it.
+    history.push(pending.trim_end());
+    callback(pending, state);
+    pending.clear();
 
     if !state.is_stopped() {
         print!("{}", PROMPT);
     }
 }
 
+/**
+ * Print part of the line being edited.  An entry recalled from the history can span several lines,
+ * which are shown on one, with a space standing in for each line break.
+ */
+fn echo(text: &str) {
+    print!("{}", text.replace('\n', " "));
+}
+
 fn handle_backspace(input_buffer: &mut String, cursor_position: &mut usize) {
     if *cursor_position <= 0 {
         return;
@@ -34,7 +88,8 @@ fn handle_backspace(input_buffer: &mut String, cursor_position: &mut usize) {
     (*cursor_position) -= 1;
     print!("\x08"); // Move back one space
                     // Clear the line and reprint the buffer from the cursor position
-    print!("{} ", &input_buffer[*cursor_position..]);
+    echo(&input_buffer[*cursor_position..]);
+    print!(" ");
     // Move the cursor back to the current position
     for _ in *cursor_position..input_buffer.len() {
         print!("\x08");
@@ -50,7 +105,8 @@ fn handle_delete(input_buffer: &mut String, cursor_position: &mut usize) {
     // Remove character at cursor position
     input_buffer.remove(*cursor_position);
     // Clear the line from cursor position and reprint the buffer
-    print!("{} ", &input_buffer[*cursor_position..]);
+    echo(&input_buffer[*cursor_position..]);
+    print!(" ");
     // Move the cursor back to the original position
     for _ in *cursor_position..input_buffer.len() {
         print!("\x08");
@@ -72,14 +128,110 @@ fn handle_cursor_right(input_buffer: &mut String, cursor_position: &mut usize) {
         return;
     }
 
-    print!("{}", &input_buffer[*cursor_position..*cursor_position + 1]);
+    echo(&input_buffer[*cursor_position..*cursor_position + 1]);
     *cursor_position += 1;
 }
 
+/**
+ * Find the start of the whitespace-delimited word before the cursor.
+ */
+fn previous_word_start(input_buffer: &str, cursor_position: usize) -> usize {
+    let before = input_buffer[..cursor_position].trim_end();
+    match before.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+        Some((index, c)) => index + c.len_utf8(),
+        None => 0,
+    }
+}
+
+/**
+ * Find the end of the whitespace-delimited word after the cursor.
+ */
+fn next_word_end(input_buffer: &str, cursor_position: usize) -> usize {
+    let after = &input_buffer[cursor_position..];
+    let word_start = after.len() - after.trim_start().len();
+    let word_len = after[word_start..]
+        .find(char::is_whitespace)
+        .unwrap_or(after.len() - word_start);
+    cursor_position + word_start + word_len
+}
+
+fn handle_word_left(input_buffer: &str, cursor_position: &mut usize) {
+    let target = previous_word_start(input_buffer, *cursor_position);
+    for _ in target..*cursor_position {
+        print!("\x08");
+    }
+    *cursor_position = target;
+}
+
+fn handle_word_right(input_buffer: &str, cursor_position: &mut usize) {
+    let target = next_word_end(input_buffer, *cursor_position);
+    echo(&input_buffer[*cursor_position..target]);
+    *cursor_position = target;
+}
+
+fn handle_delete_word(input_buffer: &mut String, cursor_position: &mut usize) {
+    let target = previous_word_start(input_buffer, *cursor_position);
+    let removed = *cursor_position - target;
+    if removed == 0 {
+        return;
+    }
+
+    input_buffer.replace_range(target..*cursor_position, "");
+    for _ in 0..removed {
+        print!("\x08");
+    }
+    *cursor_position = target;
+
+    // Reprint the rest of the line over the deleted word, then move back into position.
+    echo(&input_buffer[target..]);
+    print!("{}", " ".repeat(removed));
+    for _ in target..input_buffer.len() + removed {
+        print!("\x08");
+    }
+}
+
+/**
+ * Replace the line being edited with `line`, leaving the cursor at the end of it.
+ */
+fn replace_line(input_buffer: &mut String, cursor_position: &mut usize, line: &str) {
+    // Move back to the start of the line, then overwrite it and clear whatever is left over.
+    for _ in 0..*cursor_position {
+        print!("\x08");
+    }
+    echo(line);
+    print!("\x1b[K");
+
+    input_buffer.clear();
+    input_buffer.push_str(line);
+    *cursor_position = input_buffer.len();
+}
+
+fn handle_history_previous(
+    input_buffer: &mut String,
+    cursor_position: &mut usize,
+    history: &mut History,
+) {
+    if let Some(line) = history.previous(input_buffer) {
+        let line = line.to_string();
+        replace_line(input_buffer, cursor_position, &line);
+    }
+}
+
+fn handle_history_next(
+    input_buffer: &mut String,
+    cursor_position: &mut usize,
+    history: &mut History,
+) {
+    if let Some(line) = history.next() {
+        let line = line.to_string();
+        replace_line(input_buffer, cursor_position, &line);
+    }
+}
+
 fn handle_character(input_buffer: &mut String, cursor_position: &mut usize, c: char) {
     input_buffer.insert(*cursor_position, c);
     *cursor_position += 1;
-    print!("{}", &input_buffer[*cursor_position - 1..]);
+    echo(&input_buffer[*cursor_position - 1..]);
     // Move the cursor back to the current position
     for _ in *cursor_position..input_buffer.len() {
         print!("\x08");
@@ -90,6 +242,8 @@ fn process_key_event<TCallback, TState>(
     key_event: KeyEvent,
     input_buffer: &mut String,
     cursor_position: &mut usize,
+    pending: &mut String,
+    history: &mut History,
     callback: &mut TCallback,
     state: &mut TState,
 ) where
@@ -106,7 +260,14 @@ fn process_key_event<TCallback, TState>(
             modifiers: _,
             kind: _,
             state: _,
-        } => handle_enter(input_buffer, cursor_position, callback, state),
+        } => handle_enter(
+            input_buffer,
+            cursor_position,
+            pending,
+            history,
+            callback,
+            state,
+        ),
 
         KeyEvent {
             code: KeyCode::Backspace,
@@ -122,6 +283,20 @@ fn process_key_event<TCallback, TState>(
             state: _,
         } => handle_delete(input_buffer, cursor_position),
 
+        KeyEvent {
+            code: KeyCode::Left,
+            modifiers: KeyModifiers::CONTROL,
+            kind: _,
+            state: _,
+        } => handle_word_left(input_buffer, cursor_position),
+
+        KeyEvent {
+            code: KeyCode::Right,
+            modifiers: KeyModifiers::CONTROL,
+            kind: _,
+            state: _,
+        } => handle_word_right(input_buffer, cursor_position),
+
         KeyEvent {
             code: KeyCode::Left,
             modifiers: _,
@@ -136,6 +311,20 @@ fn process_key_event<TCallback, TState>(
             state: _,
         } => handle_cursor_right(input_buffer, cursor_position),
 
+        KeyEvent {
+            code: KeyCode::Up,
+            modifiers: _,
+            kind: _,
+            state: _,
+        } => handle_history_previous(input_buffer, cursor_position, history),
+
+        KeyEvent {
+            code: KeyCode::Down,
+            modifiers: _,
+            kind: _,
+            state: _,
+        } => handle_history_next(input_buffer, cursor_position, history),
+
         KeyEvent {
             code: KeyCode::Char(c),
             modifiers: KeyModifiers::CONTROL,
@@ -143,6 +332,13 @@ fn process_key_event<TCallback, TState>(
             state: _,
         } if c == 'c' || c == 'd' || c == 'z' => state.trigger_stop(),
 
+        KeyEvent {
+            code: KeyCode::Char('w'),
+            modifiers: KeyModifiers::CONTROL,
+            kind: _,
+            state: _,
+        } => handle_delete_word(input_buffer, cursor_position),
+
         KeyEvent {
             code: KeyCode::Char(c),
             modifiers: _,
@@ -162,15 +358,29 @@ where
     TCallback: FnMut(&str, &mut TState),
     TState: HasStopFlag,
 {
-    print!("Welcome to your REPL! Type 'Ctrl+Z' to quit.\r\n");
+    // Some terminals and editor consoles can't do raw mode, so fall back to reading whole lines.
+    if enable_raw_mode().is_err() {
+        return start_buffered(io::stdin().lock(), callback, state);
+    }
 
-    // Enable raw mode for the terminal
-    // let stdin = io::stdin();
-    enable_raw_mode()?;
+    print!("Welcome to your REPL! Type '.help' for commands, or 'Ctrl+Z' to quit.\r\n");
     let mut stdout = io::stdout();
 
     let mut input_buffer = String::new();
     let mut cursor_position = 0;
+    let mut pending = String::new();
+    // Leave raw mode while each entry runs, so Ctrl+C reaches the program as a signal and can
+    // interrupt it, rather than waiting in the input to quit the REPL afterwards.
+    let mut callback = |input: &str, state: &mut TState| {
+        let _ = disable_raw_mode();
+        callback(input, state);
+        let _ = enable_raw_mode();
+    };
+    let history_path = History::default_path();
+    let mut history = match &history_path {
+        Some(path) => History::load(path),
+        None => History::new(),
+    };
 
     print!("{}", PROMPT);
     stdout.flush()?;
@@ -183,7 +393,9 @@ where
                 key_event,
                 &mut input_buffer,
                 &mut cursor_position,
-                callback,
+                &mut pending,
+                &mut history,
+                &mut callback,
                 state,
             );
             stdout.flush()?;
@@ -195,8 +407,289 @@ where
     }
 
     stdout.flush()?;
+    if let Some(path) = &history_path {
+        // Losing the history isn't worth failing over.
+        let _ = history.save(path);
+    }
     print!("\r\nGoodbye!\r\n");
     disable_raw_mode()?;
 
     Ok(())
 }
+
+/**
+ * Run the REPL over plain lines of input, without raw mode or any of the key handling.  The
+ * terminal does the line editing, if there is one, and the REPL ends when the input does.
+ */
+pub fn start_buffered<TReader, TCallback, TState>(
+    reader: TReader,
+    callback: &mut TCallback,
+    state: &mut TState,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    TReader: BufRead,
+    TCallback: FnMut(&str, &mut TState),
+    TState: HasStopFlag,
+{
+    print!("Welcome to your REPL! Type '.help' for commands, or end the input to quit.\r\n");
+    let mut stdout = io::stdout();
+    let mut pending = String::new();
+
+    print!("{}", PROMPT);
+    stdout.flush()?;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        // The same as `handle_enter`: keep reading until the input is complete.
+        let is_blank = line.trim().is_empty();
+        pending.push_str(&line);
+        if !is_blank && is_incomplete(&pending) {
+            pending.push('\n');
+            print!("{}", CONTINUATION_PROMPT);
+            stdout.flush()?;
+            continue;
+        }
+
+        callback(&pending, state);
+        pending.clear();
+        if state.is_stopped() {
+            break;
+        }
+        print!("{}", PROMPT);
+        stdout.flush()?;
+    }
+
+    // Whatever was left unfinished when the input ran out still gets a chance to run.
+    if !pending.trim().is_empty() && !state.is_stopped() {
+        callback(&pending, state);
+    }
+
+    print!("\r\nGoodbye!\r\n");
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /**
+     * Records what the REPL submits instead of evaluating it.
+     */
+    #[derive(Default)]
+    struct Recorder {
+        submitted: Vec<String>,
+        stopped: bool,
+    }
+
+    impl HasStopFlag for Recorder {
+        fn trigger_stop(&mut self) {
+            self.stopped = true;
+        }
+
+        fn is_stopped(&self) -> bool {
+            self.stopped
+        }
+    }
+
+    /**
+     * The line editor's state, driven one synthetic key event at a time.
+     */
+    #[derive(Default)]
+    struct Editor {
+        input_buffer: String,
+        cursor_position: usize,
+        pending: String,
+        history: History,
+        state: Recorder,
+    }
+
+    impl Editor {
+        fn key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+            process_key_event(
+                KeyEvent::new(code, modifiers),
+                &mut self.input_buffer,
+                &mut self.cursor_position,
+                &mut self.pending,
+                &mut self.history,
+                &mut |input: &str, state: &mut Recorder| state.submitted.push(input.to_string()),
+                &mut self.state,
+            );
+        }
+
+        fn key(&mut self, code: KeyCode) {
+            self.key_with(code, KeyModifiers::NONE);
+        }
+
+        fn type_text(&mut self, text: &str) {
+            for c in text.chars() {
+                self.key(KeyCode::Char(c));
+            }
+        }
+
+        fn enter_line(&mut self, text: &str) {
+            self.type_text(text);
+            self.key(KeyCode::Enter);
+        }
+    }
+
+    #[test]
+    fn typing_and_editing_update_the_buffer() {
+        let mut editor = Editor::default();
+        editor.type_text("prnt 1;");
+        for _ in 0..6 {
+            editor.key(KeyCode::Left);
+        }
+        editor.key(KeyCode::Right);
+        editor.key(KeyCode::Char('i'));
+        assert_eq!(editor.input_buffer, "print 1;");
+        assert_eq!(editor.cursor_position, 3);
+
+        editor.key(KeyCode::Backspace);
+        editor.key(KeyCode::Delete);
+        assert_eq!(editor.input_buffer, "prt 1;");
+        assert_eq!(editor.cursor_position, 2);
+
+        editor.key(KeyCode::Enter);
+        assert_eq!(editor.state.submitted, vec!["prt 1;"]);
+        assert_eq!(editor.input_buffer, "");
+        assert_eq!(editor.cursor_position, 0);
+    }
+
+    #[test]
+    fn up_and_down_walk_the_history() {
+        let mut editor = Editor::default();
+
+        // With no history, the arrows leave the line alone.
+        editor.type_text("x");
+        editor.key(KeyCode::Up);
+        editor.key(KeyCode::Down);
+        assert_eq!(editor.input_buffer, "x");
+        editor.key(KeyCode::Backspace);
+
+        editor.enter_line("let a = 1;");
+        editor.enter_line("let b = 2;");
+        editor.type_text("a +");
+
+        editor.key(KeyCode::Up);
+        assert_eq!(editor.input_buffer, "let b = 2;");
+        assert_eq!(editor.cursor_position, "let b = 2;".len());
+        editor.key(KeyCode::Up);
+        assert_eq!(editor.input_buffer, "let a = 1;");
+
+        // Past the oldest entry it stays put.
+        editor.key(KeyCode::Up);
+        assert_eq!(editor.input_buffer, "let a = 1;");
+
+        editor.key(KeyCode::Down);
+        assert_eq!(editor.input_buffer, "let b = 2;");
+
+        // Past the newest entry the in-progress line comes back.
+        editor.key(KeyCode::Down);
+        assert_eq!(editor.input_buffer, "a +");
+        assert_eq!(editor.cursor_position, 3);
+        editor.key(KeyCode::Down);
+        assert_eq!(editor.input_buffer, "a +");
+    }
+
+    #[test]
+    fn control_keys_stop_the_repl() {
+        let mut editor = Editor::default();
+        editor.key_with(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        assert!(editor.state.stopped);
+        assert!(editor.state.submitted.is_empty());
+    }
+
+    #[test]
+    fn control_arrows_move_by_word() {
+        let mut editor = Editor::default();
+        editor.type_text("let  total = a + b;");
+
+        editor.key_with(KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(editor.cursor_position, "let  total = a + ".len());
+        for _ in 0..4 {
+            editor.key_with(KeyCode::Left, KeyModifiers::CONTROL);
+        }
+        assert_eq!(editor.cursor_position, "let  ".len());
+        editor.key_with(KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(editor.cursor_position, 0);
+        // At the start of the line there's nowhere further to go.
+        editor.key_with(KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(editor.cursor_position, 0);
+
+        editor.key_with(KeyCode::Right, KeyModifiers::CONTROL);
+        assert_eq!(editor.cursor_position, "let".len());
+        editor.key_with(KeyCode::Right, KeyModifiers::CONTROL);
+        assert_eq!(editor.cursor_position, "let  total".len());
+        for _ in 0..5 {
+            editor.key_with(KeyCode::Right, KeyModifiers::CONTROL);
+        }
+        assert_eq!(editor.cursor_position, editor.input_buffer.len());
+        assert_eq!(editor.input_buffer, "let  total = a + b;");
+    }
+
+    #[test]
+    fn control_w_deletes_the_previous_word() {
+        let mut editor = Editor::default();
+        editor.type_text("print alpha beta  ");
+        editor.key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(editor.input_buffer, "print alpha ");
+        assert_eq!(editor.cursor_position, "print alpha ".len());
+
+        // Deleting mid-line keeps what follows the cursor.
+        editor.type_text("gamma");
+        for _ in 0.."gamma".len() {
+            editor.key(KeyCode::Left);
+        }
+        editor.key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(editor.input_buffer, "print gamma");
+        assert_eq!(editor.cursor_position, "print ".len());
+
+        editor.key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        editor.key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(editor.input_buffer, "gamma");
+        assert_eq!(editor.cursor_position, 0);
+    }
+
+    #[test]
+    fn a_block_split_over_two_lines_is_evaluated_once() {
+        let mut editor = Editor::default();
+        editor.enter_line("if (true) {");
+        assert!(editor.state.submitted.is_empty());
+        editor.enter_line("print 1; }");
+        assert_eq!(editor.state.submitted, vec!["if (true) {\nprint 1; }"]);
+
+        // The history holds the whole entry, once.
+        editor.key(KeyCode::Up);
+        assert_eq!(editor.input_buffer, "if (true) {\nprint 1; }");
+        editor.key(KeyCode::Up);
+        assert_eq!(editor.input_buffer, "if (true) {\nprint 1; }");
+    }
+
+    #[test]
+    fn buffered_input_waits_for_the_rest_of_a_block() {
+        let input = io::Cursor::new("let xs = [1,\n2];\nfun f() {\n1 }\n");
+        let mut submitted = Vec::new();
+        let mut state = Recorder::default();
+        start_buffered(
+            input,
+            &mut |line: &str, _: &mut Recorder| submitted.push(line.to_string()),
+            &mut state,
+        )
+        .unwrap();
+        assert_eq!(submitted, vec!["let xs = [1,\n2];", "fun f() {\n1 }"]);
+    }
+
+    #[test]
+    fn only_real_brackets_keep_the_input_open() {
+        assert!(is_incomplete("let xs = [1, 2"));
+        assert!(is_incomplete("f(1, [2"));
+        assert!(is_incomplete("print \"unterminated"));
+        assert!(is_incomplete("/* still going"));
+        assert!(!is_incomplete("print \"{ ( [\";"));
+        assert!(!is_incomplete("print 1; // {"));
+        assert!(!is_incomplete("print 1; /* [ */"));
+        assert!(!is_incomplete("let xs = [1, [2]];"));
+    }
+}
@@ -0,0 +1,61 @@
+// Exports for running rlox in a browser through `wasm-bindgen`.  Build with the `wasm` feature,
+// e.g. `wasm-pack build --features wasm`.
+
+use wasm_bindgen::prelude::*;
+
+use crate::run_captured;
+
+/**
+ * The outcome of running a program: what it printed, its final value, and any errors.
+ */
+#[wasm_bindgen]
+pub struct RunResult {
+    output: String,
+    value: Option<String>,
+    diagnostics: String,
+}
+
+#[wasm_bindgen]
+impl RunResult {
+    /**
+     * Everything the program printed.
+     */
+    #[wasm_bindgen(getter)]
+    pub fn output(&self) -> String {
+        self.output.clone()
+    }
+
+    /**
+     * The final value as display text, or `undefined` if the program failed.
+     */
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<String> {
+        self.value.clone()
+    }
+
+    /**
+     * The errors as a JSON array of diagnostics, which is `[]` when the program succeeded.
+     */
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> String {
+        self.diagnostics.clone()
+    }
+}
+
+/**
+ * Scan, parse, and evaluate `source` in a fresh interpreter.
+ */
+#[wasm_bindgen]
+pub fn run(source: &str) -> RunResult {
+    let (output, result) = run_captured(source);
+    let (value, diagnostics) = match result {
+        Ok(value) => (Some(value.to_string()), Vec::new()),
+        Err(diagnostics) => (None, diagnostics),
+    };
+    let items: Vec<String> = diagnostics.iter().map(|d| d.to_json()).collect();
+    RunResult {
+        output,
+        value,
+        diagnostics: format!("[{}]", items.join(",")),
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+};
+
+fn rlox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .output()
+        .expect("failed to run rlox")
+}
+
+/**
+ * Run rlox with `input` piped into its stdin.
+ */
+fn rlox_piped(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run rlox");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .expect("failed to write to rlox");
+    child.wait_with_output().expect("failed to run rlox")
+}
+
+/**
+ * Write `source` to a script in the temp directory, named after the test so parallel tests don't
+ * collide.
+ */
+fn script(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("rlox-{}-{}.lox", name, std::process::id()));
+    fs::write(&path, source).expect("failed to write script");
+    path
+}
+
+#[test]
+fn file_stops_at_the_statement_that_errors() {
+    let path = script("line3", "let a = 1;\nlet b = 2;\nprint a + c;\nprint b;\n");
+    let output = rlox(&[path.to_str().unwrap()]);
+    fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(70));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("undefined variable: c"), "{}", stderr);
+    assert!(stderr.contains("3 | print a + c;"), "{}", stderr);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn parse_error_exits_with_65() {
+    let output = rlox(&["-e", "print (1;"]);
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn runtime_error_exits_with_70() {
+    let output = rlox(&["-e", "print 1 + nil;"]);
+    assert_eq!(output.status.code(), Some(70));
+}
+
+#[test]
+fn unreadable_file_exits_with_74() {
+    let path = env::temp_dir().join("rlox-missing-script.lox");
+    let output = rlox(&[path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(74));
+}
+
+#[test]
+fn eval_prints_only_what_the_code_prints() {
+    let output = rlox(&["-e", "print 1+2;"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3");
+}
+
+#[test]
+fn tokens_flag_dumps_the_token_types() {
+    let output = rlox(&["--tokens", "-e", "let x = 1 + 2;"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Tokens:"), "{}", stdout);
+    for name in [
+        "Let",
+        "Identifier",
+        "Equal",
+        "Number",
+        "Plus",
+        "Semicolon",
+        "EOF",
+    ] {
+        assert!(
+            stdout.contains(&format!("token_type: {},", name)),
+            "{} missing from {}",
+            name,
+            stdout
+        );
+    }
+}
+
+#[test]
+fn ast_flag_prints_the_tree_as_an_s_expression() {
+    let output = rlox(&["--ast", "-e", "1 + 2 * 3;"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\t(+ 1 (* 2 3))\r\n"), "{}", stdout);
+}
+
+#[test]
+fn scripts_read_piped_stdin_with_input() {
+    let path = script(
+        "input",
+        "let x = input(\"> \");\nprint x + \"!\";\nprint input(\"\");\n",
+    );
+    let output = rlox_piped(&[path.to_str().unwrap()], "42\n");
+    fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(0));
+    // The second `input` hits the end of stdin and gets nil.
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "> 42!nil");
+}
+
+#[test]
+fn piped_stdin_is_the_program_without_a_script() {
+    let output = rlox_piped(&[], "let a = 2;\nprint a * 21;\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42");
+}
+
+#[test]
+fn nil_results_are_not_echoed() {
+    let output = rlox(&["-e", "let a = 1; nil;"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty(), "{:?}", output.stdout);
+
+    let output = rlox(&["-e", "1 + 1;"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "\r\n2\r\n");
+}
+
+#[test]
+fn string_results_are_echoed_quoted() {
+    let output = rlox(&["-e", r#""a\"b\n";"#]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "\r\n\"a\\\"b\\n\"\r\n"
+    );
+
+    // `print` still writes the string itself.
+    let output = rlox(&["-e", r#"print "a\"b";"#]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\"b");
+}
+
+#[test]
+fn exit_ends_the_program_with_its_status() {
+    let output = rlox(&["-e", "print 1; exit(3); print 2;"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1");
+    assert!(output.stderr.is_empty());
+
+    assert_eq!(rlox(&["-e", "exit();"]).status.code(), Some(0));
+    let output = rlox(&["-e", "exit(1.5);"]);
+    assert_eq!(output.status.code(), Some(70));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("exit expects an integer status code"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn env_reads_environment_variables() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args([
+            "-e",
+            r#"print [env("RLOX_TEST_VALUE"), env("RLOX_TEST_UNSET")];"#,
+        ])
+        .env("RLOX_TEST_VALUE", "hi")
+        .env_remove("RLOX_TEST_UNSET")
+        .output()
+        .expect("failed to run rlox");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), r#"["hi", nil]"#);
+}
+
+#[test]
+fn json_diagnostics_list_every_parse_error() {
+    let output = rlox(&["--diagnostics", "json", "-e", "print (1;\nprint 2 +;"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "[{\"stage\":\"parser\",\"message\":\"expected one of [RightParen]\",\"line\":1,\"column\":9},\
+         {\"stage\":\"parser\",\"message\":\"expected expression\",\"line\":2,\"column\":10}]"
+    );
+}
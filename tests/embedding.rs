@@ -0,0 +1,94 @@
+use rlox::{run_captured, Object, Stage};
+
+#[test]
+fn captured_run_returns_output_and_value() {
+    let (output, result) = run_captured("print \"hi\"; print 1 + 1; 40 + 2");
+    assert_eq!(output, "hi2");
+    assert!(matches!(result, Ok(Object::Number(n)) if n == 42.0));
+}
+
+#[test]
+fn captured_run_keeps_output_from_before_an_error() {
+    let (output, result) = run_captured("print \"before\";\nprint 1 + nil;\nprint \"after\";");
+    assert_eq!(output, "before");
+
+    let diagnostics = result.unwrap_err();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].stage, Stage::Runtime);
+    let json = diagnostics[0].to_json();
+    assert!(json.starts_with("{\"stage\":\"runtime\""), "{}", json);
+    assert!(json.contains("\"line\":2"), "{}", json);
+}
+
+#[test]
+fn captured_run_reports_parse_errors() {
+    let (output, result) = run_captured("print (1;");
+    assert!(output.is_empty());
+    assert_eq!(result.unwrap_err()[0].stage, Stage::Parser);
+}
+
+#[test]
+fn run_returns_the_final_value() {
+    assert_eq!(rlox::run("1 + 2").unwrap(), Object::Number(3.0));
+}
+
+#[test]
+fn run_errors_can_be_downcast_to_the_failing_stage() {
+    let err = rlox::run("print (1;").unwrap_err();
+    assert!(err.downcast_ref::<rlox::ErrorSet>().is_some());
+
+    let err = rlox::run("1 + nil").unwrap_err();
+    let err = err.downcast_ref::<rlox::RuntimeError>().unwrap();
+    assert!(err.msg.starts_with("operands must be"), "{}", err.msg);
+}
+
+#[test]
+fn eval_source_keeps_state_between_calls() {
+    let mut interpreter = rlox::Interpreter::new().with_output(Box::new(rlox::OutputBuffer::new()));
+    interpreter.eval_source("let a = 20;").unwrap();
+    assert_eq!(
+        interpreter
+            .eval_source("fun double(x) { x * 2 }")
+            .unwrap()
+            .to_string(),
+        "<fn double>"
+    );
+    assert_eq!(
+        interpreter.eval_source("double(a) + 2").unwrap(),
+        Object::Number(42.0)
+    );
+
+    // Each stage's errors come back as diagnostics, and the session carries on after them.
+    assert_eq!(
+        interpreter.eval_source("\"open").unwrap_err()[0].stage,
+        Stage::Lexer
+    );
+    assert_eq!(
+        interpreter.eval_source("let = 1;").unwrap_err()[0].stage,
+        Stage::Parser
+    );
+    assert_eq!(
+        interpreter.eval_source("a + nil").unwrap_err()[0].stage,
+        Stage::Runtime
+    );
+    assert_eq!(interpreter.eval_source("a").unwrap(), Object::Number(20.0));
+}
+
+#[test]
+fn every_statement_on_a_line_runs() {
+    let (output, result) = run_captured("let x = 1; let y = 2; print x + y; x * 10");
+    assert_eq!(output, "3");
+    assert_eq!(result.unwrap(), Object::Number(10.0));
+}
+
+#[test]
+fn underscore_holds_the_last_result() {
+    let mut interpreter = rlox::Interpreter::new().with_output(Box::new(rlox::OutputBuffer::new()));
+    interpreter
+        .eval_source("let x = 1; let y = 2; print x + y; x * 10")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval_source("_ + 1").unwrap(),
+        Object::Number(11.0)
+    );
+}
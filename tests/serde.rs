@@ -0,0 +1,605 @@
+// Round trips the syntax tree through JSON.  There's no JSON crate among the dependencies, so a
+// small serializer and parser for serde's externally tagged shape is defined here.
+#![cfg(feature = "serde")]
+
+use rlox::parser::{Expr, Stmt};
+
+mod json {
+    use std::fmt::{self, Display};
+
+    use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+    use serde::ser::{self, Serialize};
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Json {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Json>),
+        Object(Vec<(String, Json)>),
+    }
+
+    #[derive(Debug)]
+    pub struct Error(String);
+
+    impl Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl ser::Error for Error {
+        fn custom<T: Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    impl de::Error for Error {
+        fn custom<T: Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+        Ok(value.serialize(Serializer)?.to_string())
+    }
+
+    pub fn from_str<T: de::DeserializeOwned>(text: &str) -> Result<T, Error> {
+        let mut parser = Parser { text, pos: 0 };
+        let value = parser.value()?;
+        T::deserialize(value)
+    }
+
+    impl Display for Json {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Json::Null => write!(f, "null"),
+                Json::Bool(b) => write!(f, "{}", b),
+                Json::Number(n) => write!(f, "{}", n),
+                Json::String(s) => write!(f, "{:?}", s),
+                Json::Array(items) => {
+                    let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                    write!(f, "[{}]", items.join(","))
+                }
+                Json::Object(fields) => {
+                    let fields: Vec<String> = fields
+                        .iter()
+                        .map(|(key, value)| format!("{:?}:{}", key, value))
+                        .collect();
+                    write!(f, "{{{}}}", fields.join(","))
+                }
+            }
+        }
+    }
+
+    struct Parser<'a> {
+        text: &'a str,
+        pos: usize,
+    }
+
+    impl Parser<'_> {
+        fn peek(&self) -> Option<char> {
+            self.text[self.pos..].chars().next()
+        }
+
+        fn eat(&mut self, expected: char) -> Result<(), Error> {
+            match self.peek() {
+                Some(c) if c == expected => {
+                    self.pos += c.len_utf8();
+                    Ok(())
+                }
+                other => Err(Error(format!("expected {:?}, found {:?}", expected, other))),
+            }
+        }
+
+        fn value(&mut self) -> Result<Json, Error> {
+            match self.peek() {
+                Some('n') => self.word("null", Json::Null),
+                Some('t') => self.word("true", Json::Bool(true)),
+                Some('f') => self.word("false", Json::Bool(false)),
+                Some('"') => Ok(Json::String(self.string()?)),
+                Some('[') => {
+                    self.eat('[')?;
+                    let mut items = Vec::new();
+                    while self.peek() != Some(']') {
+                        if !items.is_empty() {
+                            self.eat(',')?;
+                        }
+                        items.push(self.value()?);
+                    }
+                    self.eat(']')?;
+                    Ok(Json::Array(items))
+                }
+                Some('{') => {
+                    self.eat('{')?;
+                    let mut fields = Vec::new();
+                    while self.peek() != Some('}') {
+                        if !fields.is_empty() {
+                            self.eat(',')?;
+                        }
+                        let key = self.string()?;
+                        self.eat(':')?;
+                        fields.push((key, self.value()?));
+                    }
+                    self.eat('}')?;
+                    Ok(Json::Object(fields))
+                }
+                _ => {
+                    let rest = &self.text[self.pos..];
+                    let len = rest
+                        .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                        .unwrap_or(rest.len());
+                    self.pos += len;
+                    rest[..len]
+                        .parse()
+                        .map(Json::Number)
+                        .map_err(|_| Error(format!("bad number {:?}", &rest[..len])))
+                }
+            }
+        }
+
+        fn word(&mut self, word: &str, value: Json) -> Result<Json, Error> {
+            if !self.text[self.pos..].starts_with(word) {
+                return Err(Error(format!("expected {}", word)));
+            }
+            self.pos += word.len();
+            Ok(value)
+        }
+
+        // Only the escapes `{:?}` writes for the strings in a syntax tree are handled.
+        fn string(&mut self) -> Result<String, Error> {
+            self.eat('"')?;
+            let mut s = String::new();
+            loop {
+                let c = self
+                    .peek()
+                    .ok_or(Error("unterminated string".to_string()))?;
+                self.pos += c.len_utf8();
+                match c {
+                    '"' => return Ok(s),
+                    '\\' => {
+                        let escaped = self.peek().ok_or(Error("bad escape".to_string()))?;
+                        self.pos += escaped.len_utf8();
+                        s.push(match escaped {
+                            'n' => '\n',
+                            'r' => '\r',
+                            't' => '\t',
+                            other => other,
+                        });
+                    }
+                    c => s.push(c),
+                }
+            }
+        }
+    }
+
+    struct Serializer;
+
+    pub struct SerializeArray {
+        variant: Option<&'static str>,
+        items: Vec<Json>,
+    }
+
+    pub struct SerializeObject {
+        variant: Option<&'static str>,
+        fields: Vec<(String, Json)>,
+        key: Option<String>,
+    }
+
+    fn tagged(variant: Option<&'static str>, value: Json) -> Json {
+        match variant {
+            Some(variant) => Json::Object(vec![(variant.to_string(), value)]),
+            None => value,
+        }
+    }
+
+    impl ser::Serializer for Serializer {
+        type Ok = Json;
+        type Error = Error;
+        type SerializeSeq = SerializeArray;
+        type SerializeTuple = SerializeArray;
+        type SerializeTupleStruct = SerializeArray;
+        type SerializeTupleVariant = SerializeArray;
+        type SerializeMap = SerializeObject;
+        type SerializeStruct = SerializeObject;
+        type SerializeStructVariant = SerializeObject;
+
+        fn serialize_bool(self, v: bool) -> Result<Json, Error> {
+            Ok(Json::Bool(v))
+        }
+        fn serialize_i8(self, v: i8) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_i16(self, v: i16) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_i32(self, v: i32) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_i64(self, v: i64) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_u8(self, v: u8) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_u16(self, v: u16) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_u32(self, v: u32) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_u64(self, v: u64) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_f32(self, v: f32) -> Result<Json, Error> {
+            self.serialize_f64(v as f64)
+        }
+        fn serialize_f64(self, v: f64) -> Result<Json, Error> {
+            Ok(Json::Number(v))
+        }
+        fn serialize_char(self, v: char) -> Result<Json, Error> {
+            Ok(Json::String(v.to_string()))
+        }
+        fn serialize_str(self, v: &str) -> Result<Json, Error> {
+            Ok(Json::String(v.to_string()))
+        }
+        fn serialize_bytes(self, v: &[u8]) -> Result<Json, Error> {
+            Ok(Json::Array(
+                v.iter().map(|b| Json::Number(*b as f64)).collect(),
+            ))
+        }
+        fn serialize_none(self) -> Result<Json, Error> {
+            Ok(Json::Null)
+        }
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Json, Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<Json, Error> {
+            Ok(Json::Null)
+        }
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<Json, Error> {
+            Ok(Json::Null)
+        }
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+        ) -> Result<Json, Error> {
+            Ok(Json::String(variant.to_string()))
+        }
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<Json, Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<Json, Error> {
+            Ok(tagged(Some(variant), value.serialize(self)?))
+        }
+        fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeArray, Error> {
+            Ok(SerializeArray {
+                variant: None,
+                items: Vec::new(),
+            })
+        }
+        fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+            self.serialize_seq(Some(len))
+        }
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            len: usize,
+        ) -> Result<SerializeArray, Error> {
+            self.serialize_seq(Some(len))
+        }
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            _len: usize,
+        ) -> Result<SerializeArray, Error> {
+            Ok(SerializeArray {
+                variant: Some(variant),
+                items: Vec::new(),
+            })
+        }
+        fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject, Error> {
+            Ok(SerializeObject {
+                variant: None,
+                fields: Vec::new(),
+                key: None,
+            })
+        }
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            len: usize,
+        ) -> Result<SerializeObject, Error> {
+            self.serialize_map(Some(len))
+        }
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            _len: usize,
+        ) -> Result<SerializeObject, Error> {
+            Ok(SerializeObject {
+                variant: Some(variant),
+                fields: Vec::new(),
+                key: None,
+            })
+        }
+    }
+
+    impl SerializeArray {
+        fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.items.push(value.serialize(Serializer)?);
+            Ok(())
+        }
+
+        fn finish(self) -> Result<Json, Error> {
+            Ok(tagged(self.variant, Json::Array(self.items)))
+        }
+    }
+
+    impl ser::SerializeSeq for SerializeArray {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.push(value)
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl ser::SerializeTuple for SerializeArray {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.push(value)
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl ser::SerializeTupleStruct for SerializeArray {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.push(value)
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl ser::SerializeTupleVariant for SerializeArray {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.push(value)
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl SerializeObject {
+        fn finish(self) -> Result<Json, Error> {
+            Ok(tagged(self.variant, Json::Object(self.fields)))
+        }
+    }
+
+    impl ser::SerializeMap for SerializeObject {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+            match key.serialize(Serializer)? {
+                Json::String(key) => {
+                    self.key = Some(key);
+                    Ok(())
+                }
+                other => Err(Error(format!("map keys must be strings, not {}", other))),
+            }
+        }
+        fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            let key = self.key.take().expect("a key comes before its value");
+            self.fields.push((key, value.serialize(Serializer)?));
+            Ok(())
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl ser::SerializeStruct for SerializeObject {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_field<T: ?Sized + Serialize>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.fields
+                .push((key.to_string(), value.serialize(Serializer)?));
+            Ok(())
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl ser::SerializeStructVariant for SerializeObject {
+        type Ok = Json;
+        type Error = Error;
+        fn serialize_field<T: ?Sized + Serialize>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.fields
+                .push((key.to_string(), value.serialize(Serializer)?));
+            Ok(())
+        }
+        fn end(self) -> Result<Json, Error> {
+            self.finish()
+        }
+    }
+
+    impl<'de> de::Deserializer<'de> for Json {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                Json::Null => visitor.visit_unit(),
+                Json::Bool(b) => visitor.visit_bool(b),
+                // Whole numbers are offered as integers, so fields like line numbers accept them.
+                Json::Number(n) if n >= 0.0 && n.fract() == 0.0 => visitor.visit_u64(n as u64),
+                Json::Number(n) => visitor.visit_f64(n),
+                Json::String(s) => visitor.visit_string(s),
+                Json::Array(items) => {
+                    visitor.visit_seq(de::value::SeqDeserializer::new(items.into_iter()))
+                }
+                Json::Object(fields) => {
+                    visitor.visit_map(de::value::MapDeserializer::new(fields.into_iter()))
+                }
+            }
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self {
+                Json::Null => visitor.visit_none(),
+                other => visitor.visit_some(other),
+            }
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            match self {
+                Json::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+                Json::Object(mut fields) if fields.len() == 1 => {
+                    let (variant, value) = fields.pop().unwrap();
+                    visitor.visit_enum(Enum { variant, value })
+                }
+                other => Err(Error(format!("expected an enum, found {}", other))),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Json {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    struct Enum {
+        variant: String,
+        value: Json,
+    }
+
+    impl<'de> de::EnumAccess<'de> for Enum {
+        type Error = Error;
+        type Variant = Json;
+
+        fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Json), Error> {
+            let variant = seed.deserialize(self.variant.into_deserializer())?;
+            Ok((variant, self.value))
+        }
+    }
+
+    impl<'de> de::VariantAccess<'de> for Json {
+        type Error = Error;
+
+        fn unit_variant(self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+            seed.deserialize(self)
+        }
+
+        fn tuple_variant<V: Visitor<'de>>(
+            self,
+            _len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            de::Deserializer::deserialize_any(self, visitor)
+        }
+
+        fn struct_variant<V: Visitor<'de>>(
+            self,
+            _fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            de::Deserializer::deserialize_any(self, visitor)
+        }
+    }
+}
+
+fn parse(source: &str) -> Vec<Stmt> {
+    rlox::parse(&rlox::scan_tokens(source).unwrap()).unwrap()
+}
+
+fn expr(source: &str) -> Expr {
+    match parse(source).remove(0) {
+        Stmt::Expr(_, expr) => *expr,
+        other => panic!("expected an expression statement, found {:?}", other),
+    }
+}
+
+#[test]
+fn expressions_survive_a_json_round_trip() {
+    let tree = expr("1 + 2;");
+    let text = json::to_string(&tree).unwrap();
+    assert_eq!(
+        text,
+        r#"{"BinaryOp":[{"line":1,"column":3,"end_line":1,"end_column":3},{"Number":[{"line":1,"column":1,"end_line":1,"end_column":1},1]},"Add",{"Number":[{"line":1,"column":5,"end_line":1,"end_column":5},2]}]}"#
+    );
+    assert_eq!(json::from_str::<Expr>(&text).unwrap(), tree);
+}
+
+#[test]
+fn whole_programs_survive_a_json_round_trip() {
+    let program = parse(
+        "class A { init(n = 1) { this.n = n; } }\n\
+         fun twice(f, ...xs) { map(xs, fun (x) { f(f(x)) }) }\n\
+         'outer: for (x in 0..3) { if (x > 1) { break 'outer \"s${x}\"; } else { continue; } }\n\
+         let a = [1, 2][0:1]; a += [-1]; delete a;",
+    );
+    let text = json::to_string(&program).unwrap();
+    assert_eq!(json::from_str::<Vec<Stmt>>(&text).unwrap(), program);
+}
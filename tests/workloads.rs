@@ -0,0 +1,21 @@
+// The benchmark workloads, checked on every test run rather than only under `cargo bench`.
+
+fn run(source: &str) -> String {
+    match rlox::run(source) {
+        Ok(value) => value.to_string(),
+        Err(err) => panic!("the workload failed: {:?}", err),
+    }
+}
+
+#[test]
+fn workload_gives_the_expected_result() {
+    assert_eq!(
+        run(include_str!("../benches/workload.lox")),
+        "[2584, 1046622.67552155]"
+    );
+}
+
+#[test]
+fn scopes_workload_gives_the_expected_result() {
+    assert_eq!(run(include_str!("../benches/scopes.lox")), "200030000");
+}